@@ -1,3 +1,4 @@
+#[cfg(windows)]
 #[inline]
 fn include_windows() {
     let mut res = winres::WindowsResource::new();
@@ -6,6 +7,6 @@ fn include_windows() {
 }
 
 fn main() {
-    #[cfg_attr(not(debug_assetions), cfg(windows))]
+    #[cfg(windows)]
     include_windows();
 }
@@ -1,19 +1,56 @@
-const LETTERBOX_RATIO: f32 = 9.0 / 32.0;
+use std::path::{Path, PathBuf};
 
+use crate::tetris_input::{Bindings, Handling};
+use crate::tetromino::piece_set::PieceSet;
+use crate::universe::color::ColorPalette;
+use crate::universe::entry_delay::{EntryDelay, TimingPreset};
+use crate::universe::finesse::FinesseSettings;
+use crate::universe::lock_delay::LockReset;
+use crate::universe::mode::GameMode;
+use crate::universe::randomizer::RandomizerPreset;
+use crate::universe::rotations::RotationPreset;
+use crate::universe::scoring::ScoringPreset;
+
+// Widest share of its part of the window a board can take, leaving room for the hold and next queue either side
+const MAX_BOARD_SHARE: f32 = 0.6;
+// Where the HUD starts in a board's share of the window, as a fraction of the share's width
+const HUD_MARGIN: f32 = 150.0 / 1600.0;
+const DEFAULT_PREVIEWS: usize = 5;
+const DEFAULT_LOCK_DELAY: u32 = 30;
+const DEFAULT_BOARD_WIDTH: u32 = 10;
+const DEFAULT_BOARD_HEIGHT: u32 = 20;
+// Rows above the visible board that tetrominos spawn into, as per the guideline
+const DEFAULT_BUFFER_HEIGHT: u32 = 4;
+
+#[derive(Clone)]
 pub struct Dimensions {
     w: f32,
     h: f32,
     actual_w: f32,
     canvas_l: f32,
     canvas_r: f32,
+    // Bottom of the board, boards shorter than the window sit in the middle of it
+    canvas_b: f32,
+    // Size of a (square) cell
+    cell: f32,
+    // Share of the window this board and its letterbox get, when several boards sit side by side
+    slot_l: f32,
+    slot_w: f32,
 }
 
 impl Dimensions {
-    fn new(w: f32, h: f32) -> Self {
-        // Actual tetris board is the area left after taking away the letterbox ratio
-        let actual_w = w * LETTERBOX_RATIO;
+    /// Lays out board number `slot` of `slots` side by side boards, each `board_w` by `board_h` cells
+    fn new(w: f32, h: f32, slot: usize, slots: usize, board_w: u32, board_h: u32) -> Self {
+        // Every board gets an equal share of the window
+        let slot_w = w / slots.max(1) as f32;
+        let slot_l = slot_w * slot as f32;
+        // Cells are as big as they can be while the board fits both the window's height and its share of the width
+        let cell = (h / board_h.max(1) as f32).min(slot_w * MAX_BOARD_SHARE / board_w.max(1) as f32);
+        // Actual tetris board is the area left after taking away the letterboxes
+        let actual_w = cell * board_w as f32;
+        let canvas_b = (h + cell * board_h as f32) / 2.0;
         // Calculate the postiion at the left point at which the board starts
-        let canvas_l = (w - actual_w) / 2.0;
+        let canvas_l = slot_l + (slot_w - actual_w) / 2.0;
         // Calculate the postiion at the right point at which the board ends
         let canvas_r = canvas_l + actual_w;
         Self {
@@ -21,24 +58,98 @@ impl Dimensions {
             h,
             actual_w,
             canvas_l,
-            canvas_r
+            canvas_r,
+            canvas_b,
+            cell,
+            slot_l,
+            slot_w,
         }
     }
 
 }
 
+#[derive(Clone)]
 pub struct Config {
     ticks: u32,
     title: String,
+    // Fixed seed for every session, a fresh one is picked per session otherwise
+    seed: Option<u64>,
+    // Number of upcoming tetrominos shown in the next queue
+    previews: usize,
+    // Ticks a tetromino can spend on the stack before it locks
+    lock_delay: u32,
+    // What restarts the lock delay
+    lock_reset: LockReset,
+    // How long the board freezes for between tetrominos, in ticks
+    entry_delay: EntryDelay,
+    // Which rule scores every game
+    scoring: ScoringPreset,
+    // Which randomizer deals tetrominos
+    randomizer: RandomizerPreset,
+    // Which rotation system tetrominos turn by
+    rotation: RotationPreset,
+    // Which pieces the randomizer deals
+    pieces: PieceSet,
+    // What every game is played for
+    mode: GameMode,
+    // Board size in cells, the buffer being the hidden rows above the visible ones
+    board_width: u32,
+    board_height: u32,
+    buffer_height: u32,
+    // How held keys repeat
+    handling: Handling,
+    // What happens on a finesse fault
+    finesse: FinesseSettings,
+    // Which keys stand for which action
+    bindings: Bindings,
+    // Colors everything's drawn in
+    palette: ColorPalette,
+    // Volumes from 0, silent, to 1, the sound files' own volume
+    music_volume: f32,
+    effects_volume: f32,
+    // Where settings changed in game get written back to, if anywhere
+    settings_path: Option<PathBuf>,
+    // Which of how many side by side boards this config lays out
+    slot: usize,
+    slots: usize,
     pub dimensions: Dimensions,
 }
 
 impl Config {
     pub fn new(ticks: u32, w: f32, h: f32, title: String) -> Self {
-        let dimensions = Dimensions::new(w, h);
+        let dimensions = Dimensions::new(
+            w,
+            h,
+            0,
+            1,
+            DEFAULT_BOARD_WIDTH,
+            DEFAULT_BOARD_HEIGHT,
+        );
         Config {
             ticks,
             title,
+            seed: None,
+            previews: DEFAULT_PREVIEWS,
+            lock_delay: DEFAULT_LOCK_DELAY,
+            lock_reset: LockReset::default(),
+            entry_delay: TimingPreset::default().entry_delay(ticks),
+            scoring: ScoringPreset::default(),
+            randomizer: RandomizerPreset::default(),
+            rotation: RotationPreset::default(),
+            pieces: PieceSet::default(),
+            mode: GameMode::default(),
+            board_width: DEFAULT_BOARD_WIDTH,
+            board_height: DEFAULT_BOARD_HEIGHT,
+            buffer_height: DEFAULT_BUFFER_HEIGHT,
+            handling: Handling::default(),
+            finesse: FinesseSettings::default(),
+            bindings: Bindings::default(),
+            palette: ColorPalette::default(),
+            music_volume: 1.0,
+            effects_volume: 1.0,
+            settings_path: None,
+            slot: 0,
+            slots: 1,
             dimensions
         }
     }
@@ -48,14 +159,228 @@ impl Config {
         &self.ticks
     }
 
+    /// Set the config's ticks, only takes effect if set before the context is built
+    pub fn set_ticks(&mut self, ticks: u32) {
+        self.ticks = ticks;
+    }
+
     /// Get a reference to the config's title.
     pub fn title(&self) -> &str {
         &self.title
     }
 
+    /// Get the config's seed, if sessions should always start from the same one
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Set the config's seed. `None` picks a fresh seed per session
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    /// Get the config's number of next queue previews
+    pub fn previews(&self) -> usize {
+        self.previews
+    }
+
+    /// Set the config's number of next queue previews, takes effect on the next session
+    pub fn set_previews(&mut self, previews: usize) {
+        self.previews = previews;
+    }
+
+    /// Get the config's lock delay, in ticks
+    pub fn lock_delay(&self) -> u32 {
+        self.lock_delay
+    }
+
+    /// Set the config's lock delay, in ticks
+    pub fn set_lock_delay(&mut self, lock_delay: u32) {
+        self.lock_delay = lock_delay;
+    }
+
+    /// Get the config's lock reset rule
+    pub fn lock_reset(&self) -> LockReset {
+        self.lock_reset
+    }
+
+    /// Set the config's lock reset rule
+    pub fn set_lock_reset(&mut self, lock_reset: LockReset) {
+        self.lock_reset = lock_reset;
+    }
+
+    /// Get the config's delays between tetrominos
+    pub fn entry_delay(&self) -> EntryDelay {
+        self.entry_delay
+    }
+
+    /// Set the config's delays between tetrominos
+    pub fn set_entry_delay(&mut self, entry_delay: EntryDelay) {
+        self.entry_delay = entry_delay;
+    }
+
+    /// Get the config's scoring rule
+    pub fn scoring(&self) -> ScoringPreset {
+        self.scoring
+    }
+
+    /// Set the config's scoring rule, takes effect on the next game
+    pub fn set_scoring(&mut self, scoring: ScoringPreset) {
+        self.scoring = scoring;
+    }
+
+    /// Get the config's randomizer
+    pub fn randomizer(&self) -> RandomizerPreset {
+        self.randomizer
+    }
+
+    /// Set the config's randomizer, takes effect on the next session
+    pub fn set_randomizer(&mut self, randomizer: RandomizerPreset) {
+        self.randomizer = randomizer;
+    }
+
+    /// Get the config's rotation system
+    pub fn rotation(&self) -> RotationPreset {
+        self.rotation
+    }
+
+    /// Set the config's rotation system, takes effect on the next session
+    pub fn set_rotation(&mut self, rotation: RotationPreset) {
+        self.rotation = rotation;
+    }
+
+    /// Get the config's game mode
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Set the config's game mode, takes effect on the next game
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+    }
+
+    /// Get the config's set of pieces
+    pub fn pieces(&self) -> &PieceSet {
+        &self.pieces
+    }
+
+    /// Set the config's set of pieces, takes effect on the next session
+    pub fn set_pieces(&mut self, pieces: PieceSet) {
+        self.pieces = pieces;
+        self.resize(self.dimensions.w, self.dimensions.h);
+    }
+
+    /// Get the config's board width, in cells
+    pub fn board_width(&self) -> u32 {
+        self.board_width
+    }
+
+    /// Get the config's visible board height, in cells
+    pub fn board_height(&self) -> u32 {
+        self.board_height
+    }
+
+    /// Get the config's hidden buffer height above the board, in cells
+    pub fn buffer_height(&self) -> u32 {
+        self.buffer_height
+    }
+
+    /// Set the config's board size in cells, takes effect on the next session
+    pub fn set_board(&mut self, width: u32, height: u32, buffer_height: u32) {
+        self.board_width = width;
+        self.board_height = height;
+        self.buffer_height = buffer_height;
+        self.resize(self.dimensions.w, self.dimensions.h);
+    }
+
+    /// Get the config's handling, i.e. how held keys repeat
+    pub fn handling(&self) -> Handling {
+        self.handling
+    }
+
+    /// Set the config's handling
+    pub fn set_handling(&mut self, handling: Handling) {
+        self.handling = handling;
+    }
+
+    /// Get what the config does about finesse faults
+    pub fn finesse(&self) -> FinesseSettings {
+        self.finesse
+    }
+
+    /// Set what the config does about finesse faults
+    pub fn set_finesse(&mut self, finesse: FinesseSettings) {
+        self.finesse = finesse;
+    }
+
+    /// Get a reference to the config's key bindings
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    /// Set the config's key bindings
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.bindings = bindings;
+    }
+
+    /// Get a reference to the config's color palette
+    pub fn palette(&self) -> &ColorPalette {
+        &self.palette
+    }
+
+    /// Set the config's color palette
+    pub fn set_palette(&mut self, palette: ColorPalette) {
+        self.palette = palette;
+    }
+
+    /// Get the config's music volume
+    pub fn music_volume(&self) -> f32 {
+        self.music_volume
+    }
+
+    /// Set the config's music volume
+    pub fn set_music_volume(&mut self, music_volume: f32) {
+        self.music_volume = music_volume;
+    }
+
+    /// Get the config's sound effect volume
+    pub fn effects_volume(&self) -> f32 {
+        self.effects_volume
+    }
+
+    /// Set the config's sound effect volume
+    pub fn set_effects_volume(&mut self, effects_volume: f32) {
+        self.effects_volume = effects_volume;
+    }
+
+    /// Get where the config's settings file is, if it came from one
+    pub fn settings_path(&self) -> Option<&Path> {
+        self.settings_path.as_deref()
+    }
+
+    /// Set where settings changed in game get written back to
+    pub fn set_settings_path(&mut self, settings_path: Option<PathBuf>) {
+        self.settings_path = settings_path;
+    }
+
     /// Resizes the board
     pub fn resize(&mut self, w: f32, h: f32) {
-        self.dimensions = Dimensions::new(w, h);
+        self.dimensions = Dimensions::new(
+            w,
+            h,
+            self.slot,
+            self.slots,
+            // Boards widen to fit the widest piece, like the simulation does
+            self.board_width.max(self.pieces.widest()),
+            self.board_height,
+        );
+    }
+
+    /// Lays the board out as number `slot` of `slots` side by side boards
+    pub fn set_slot(&mut self, slot: usize, slots: usize) {
+        self.slot = slot;
+        self.slots = slots;
+        self.resize(self.dimensions.w, self.dimensions.h);
     }
 
     /// Get a reference to the config's h.
@@ -83,6 +408,31 @@ impl Config {
         &self.dimensions.canvas_r
     }
 
+    /// Get a reference to the config's canvas b, the bottom of the board
+    pub fn canvas_b(&self) -> &f32 {
+        &self.dimensions.canvas_b
+    }
+
+    /// Get a reference to the size of a board cell
+    pub fn cell(&self) -> &f32 {
+        &self.dimensions.cell
+    }
+
+    /// Get a reference to the left of the board's share of the window
+    pub fn slot_l(&self) -> &f32 {
+        &self.dimensions.slot_l
+    }
+
+    /// Get a reference to the width of the board's share of the window
+    pub fn slot_w(&self) -> &f32 {
+        &self.dimensions.slot_w
+    }
+
+    /// Left of the HUD text, in the board's share of the window
+    pub fn hud_l(&self) -> f32 {
+        self.dimensions.slot_l + self.dimensions.slot_w * HUD_MARGIN
+    }
+
 }
 impl Default for Config {
     fn default() -> Self {
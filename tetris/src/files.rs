@@ -0,0 +1,85 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Deserializer};
+
+// The game's own directory under the platform's config and data directories
+const APP_DIR: &str = "tetris-wgpu-rs";
+
+/// Where the game keeps what it writes as it's played, e.g. `~/.local/share/tetris-wgpu-rs` on Linux
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// Where the game keeps its settings, e.g. `~/.config/tetris-wgpu-rs` on Linux
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// Something wrong with a file read in a format like TOML or JSON, whose own errors are `E`
+#[derive(Debug)]
+pub enum FileError<E> {
+    Io(std::io::Error),
+    /// The file isn't valid in its format in the first place
+    Syntax(E),
+    /// The given key, e.g. `board.width`, is set to something that can't be used
+    Invalid {
+        key: String,
+        message: String,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for FileError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io(e) => write!(f, "{}", e),
+            FileError::Syntax(e) => write!(f, "{}", e),
+            FileError::Invalid { key, message } => write!(f, "{}: {}", key, message),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> Error for FileError<E> {}
+
+impl<E> From<std::io::Error> for FileError<E> {
+    fn from(e: std::io::Error) -> Self {
+        FileError::Io(e)
+    }
+}
+
+/// A file format's errors, which tell a malformed file apart from one holding something that can't be used
+pub trait FormatError: Sized {
+    /// What's wrong with the value, or `None` if the file is malformed instead
+    fn invalid(&self) -> Option<String>;
+}
+
+impl FormatError for toml::de::Error {
+    fn invalid(&self) -> Option<String> {
+        Some(self.message().to_string())
+    }
+}
+
+impl FormatError for serde_json::Error {
+    fn invalid(&self) -> Option<String> {
+        self.is_data().then(|| self.to_string())
+    }
+}
+
+/// Deserializes a whole file, naming the key whatever can't be used is at
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, FileError<D::Error>>
+where
+    D: Deserializer<'de>,
+    D::Error: FormatError,
+    T: Deserialize<'de>,
+{
+    // Keeps track of where in the file deserializing got to, so that errors can name the key
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let key = e.path().to_string();
+        let e = e.into_inner();
+        match e.invalid() {
+            Some(message) => FileError::Invalid { key, message },
+            None => FileError::Syntax(e),
+        }
+    })
+}
@@ -1,13 +1,13 @@
 mod tetris_input;
-mod tetromino;
+pub mod tetromino;
 pub mod config;
+pub mod files;
+pub mod settings;
 pub mod prod;
 
 /// Universe is where all the functionality is
 pub mod universe;
 
-use std::collections::HashMap;
-
 use tetromino::*;
 use config::Config;
 
@@ -1,9 +1,117 @@
 use std::env;
 use std::path::PathBuf;
-use tetris::{config::Config, universe::Universe};
+use tetris::{
+    config::Config,
+    settings::Settings,
+    tetromino::piece_set::PieceSet,
+    universe::{
+        entry_delay::TimingPreset, high_scores::HighScores, mode::GameMode,
+        randomizer::RandomizerPreset, replay::Replay, rotations::RotationPreset, save::Save,
+        versus::Versus, Universe,
+    },
+};
 
 fn main() {
-    let config = Config::default();
+    let mut config = Config::default();
+
+    // `--settings <file>` reads settings from somewhere other than the platform's config directory.
+    // Either way the file is written out with the defaults if it isn't there yet
+    let settings_path = env::args()
+        .skip_while(|arg| arg != "--settings")
+        .nth(1)
+        .map(PathBuf::from)
+        .or_else(Settings::default_path);
+    if let Some(path) = settings_path {
+        let settings = Settings::load_or_create(&path)
+            .unwrap_or_else(|e| panic!("unable to load {}: {}", path.display(), e));
+        settings.apply(&mut config);
+        config.set_settings_path(Some(path));
+    }
+
+    // `--export-scores <file>` writes the high score tables out as JSON, to share them
+    if let Some(file) = env::args()
+        .skip_while(|arg| arg != "--export-scores")
+        .nth(1)
+    {
+        let scores = load_high_scores();
+        scores
+            .save(&file)
+            .unwrap_or_else(|e| panic!("unable to write {}: {}", file, e));
+        return;
+    }
+    // `--import-scores <file>` merges exported high score tables into ours
+    if let Some(file) = env::args()
+        .skip_while(|arg| arg != "--import-scores")
+        .nth(1)
+    {
+        let theirs = HighScores::load_or_default(&file)
+            .unwrap_or_else(|e| panic!("unable to load {}: {}", file, e));
+        let mut scores = load_high_scores();
+        let added = scores.merge(theirs);
+        if let Some(path) = HighScores::default_path() {
+            scores
+                .save(&path)
+                .unwrap_or_else(|e| panic!("unable to write {}: {}", path.display(), e));
+        }
+        println!("Added {} high scores from {}", added, file);
+        return;
+    }
+
+    // `--replay <file>` watches a saved replay instead of playing
+    let replay = env::args()
+        .skip_while(|arg| arg != "--replay")
+        .nth(1)
+        .map(|path| {
+            Replay::load(&path).unwrap_or_else(|e| panic!("unable to load {}: {}", path, e))
+        });
+    // `--mode <name>` picks what the game is played for, e.g. sprint
+    if let Some(name) = env::args().skip_while(|arg| arg != "--mode").nth(1) {
+        let mode = GameMode::from_name(&name).unwrap_or_else(|| panic!("unknown mode {}", name));
+        config.set_mode(mode);
+    }
+    // `--rotation <name>` picks how tetrominos turn and kick, e.g. srs-plus for 180 kicks
+    if let Some(name) = env::args().skip_while(|arg| arg != "--rotation").nth(1) {
+        let rotation = RotationPreset::from_name(&name)
+            .unwrap_or_else(|| panic!("unknown rotation system {}", name));
+        config.set_rotation(rotation);
+    }
+    // `--randomizer <name>` picks how the next tetrominos are picked, e.g. tgm for few repeats
+    if let Some(name) = env::args().skip_while(|arg| arg != "--randomizer").nth(1) {
+        let randomizer = RandomizerPreset::from_name(&name)
+            .unwrap_or_else(|| panic!("unknown randomizer {}", name));
+        config.set_randomizer(randomizer);
+    }
+    // `--timing <name>` picks the delays between tetrominos, e.g. tgm for long ones with initial rotation and hold
+    if let Some(name) = env::args().skip_while(|arg| arg != "--timing").nth(1) {
+        let timing =
+            TimingPreset::from_name(&name).unwrap_or_else(|| panic!("unknown timing {}", name));
+        config.set_entry_delay(timing.entry_delay(*config.ticks()));
+    }
+    // `--pieces <name|file>` picks the pieces dealt, a built-in set like pentomino or a piece file
+    if let Some(source) = env::args().skip_while(|arg| arg != "--pieces").nth(1) {
+        let pieces = PieceSet::load(&source)
+            .unwrap_or_else(|e| panic!("unable to load pieces {}: {}", source, e));
+        config.set_pieces(pieces);
+    }
+    // `--board <w>x<h>[+<buffer>]` sets the board size in cells, e.g. 20x40+8
+    if let Some(size) = env::args().skip_while(|arg| arg != "--board").nth(1) {
+        let parse = |n: &str| {
+            n.parse::<u32>()
+                .unwrap_or_else(|_| panic!("bad board size {}", size))
+        };
+        let (size_only, buffer) = match size.split_once('+') {
+            Some((size_only, buffer)) => (size_only, parse(buffer)),
+            None => (size.as_str(), config.buffer_height()),
+        };
+        let (w, h) = size_only
+            .split_once('x')
+            .unwrap_or_else(|| panic!("bad board size {}", size));
+        config.set_board(parse(w), parse(h), buffer);
+    }
+    // The tick rate has to be right before the context is built
+    if let Some(replay) = &replay {
+        replay.rules().apply(&mut config);
+    }
 
     #[cfg(debug_assertions)]
     let resource_dir: PathBuf = [env!("CARGO_MANIFEST_DIR"), "resources"].iter().collect();
@@ -17,17 +125,53 @@ fn main() {
     let (event_loop, mut ctx) = thomas::ContextBuilder::new()
         .with_title(config.title())
         .with_ticks(*config.ticks())
+        .with_size(*config.w() as u32, *config.h() as u32)
         .with_resource_dir(resource_dir)
         .with_icon(icon_dir)
         .build();
 
-    let mut universe = Universe::new(&mut ctx, config);
-    // We're going to update universe config with window size
-    // TODO support updating window size on resize window
-    universe.config.resize(
+    let (w, h) = (
         ctx.graphics.size.width as f32,
         ctx.graphics.size.height as f32,
     );
 
+    // `--versus` puts two boards side by side, for two players on one keyboard
+    if env::args().any(|arg| arg == "--versus") {
+        let mut versus = Versus::new(&mut ctx, config);
+        versus.resize(w, h);
+        thomas::main::run(event_loop, ctx, versus);
+        return;
+    }
+
+    let mut universe = match replay {
+        Some(replay) => Universe::from_replay(&mut ctx, config, replay),
+        None => {
+            let mut universe = Universe::new(&mut ctx, config);
+            // Games in progress are saved on the way out, and offered for resuming on the next launch
+            if let Some(path) = Save::default_path() {
+                universe = universe.with_save(path);
+            }
+            // Results of games played here go on the high score tables, which replays' don't
+            if let Some(path) = HighScores::default_path() {
+                universe = universe.with_high_scores(path);
+            }
+            universe
+        }
+    };
+    // `--ai` starts out with the AI playing
+    if env::args().any(|arg| arg == "--ai") {
+        universe.set_ai(true);
+    }
+    // We're going to update universe config with window size
+    // TODO support updating window size on resize window
+    universe.config.resize(w, h);
+
     thomas::main::run(event_loop, ctx, universe);
 }
+
+/// The high score tables kept in the platform's data directory
+fn load_high_scores() -> HighScores {
+    let path = HighScores::default_path().expect("no data directory to keep high scores in");
+    HighScores::load_or_default(&path)
+        .unwrap_or_else(|e| panic!("unable to load {}: {}", path.display(), e))
+}
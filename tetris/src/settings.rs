@@ -0,0 +1,327 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::files::{self, FileError};
+use crate::tetris_input::{Bindings, Handling};
+use crate::tetromino::piece_set::PieceSet;
+use crate::universe::color::ColorPalette;
+use crate::universe::entry_delay::EntryDelay;
+use crate::universe::finesse::FinesseSettings;
+use crate::universe::mode::GameMode;
+use crate::universe::randomizer::RandomizerPreset;
+use crate::universe::rotations::RotationPreset;
+use crate::universe::{MIN_BOARD_HEIGHT, MIN_BOARD_WIDTH, MIN_BUFFER_HEIGHT};
+
+// Name of the settings file under the game's config directory
+const SETTINGS_FILE: &str = "settings.toml";
+// First line of every settings file written out
+const HEADER: &str = "# Settings for tetris-wgpu-rs, anything left out keeps its default";
+// Smallest window anything can be made out in
+const MIN_WINDOW_SIZE: u32 = 200;
+
+/// Something wrong with a settings file, e.g. `board.width` being too small
+pub type SettingsError = FileError<toml::de::Error>;
+
+/// Size of the inside of the window, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowSettings {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Size of the board in cells, see `Dimensions`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoardSettings {
+    pub width: u32,
+    pub height: u32,
+    pub buffer: u32,
+}
+
+/// Volumes in percent of the sound files' own volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioSettings {
+    pub music: u32,
+    pub effects: u32,
+}
+
+/// Everything the settings file can set. Anything left out of the file keeps its default
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Ticks per second, which the whole game runs at
+    pub ticks: u32,
+    /// What games are played for, unless picked with `--mode`
+    pub mode: GameMode,
+    /// How tetrominos turn and kick, unless picked with `--rotation`
+    pub rotation: RotationPreset,
+    /// How the next tetrominos are picked, unless picked with `--randomizer`
+    pub randomizer: RandomizerPreset,
+    /// Which pieces are dealt, the name of a built-in set or the path to a piece file, unless picked with `--pieces`
+    pub pieces: String,
+    /// Delays between tetrominos in ticks, unless picked with `--timing`
+    pub timing: EntryDelay,
+    pub window: WindowSettings,
+    pub board: BoardSettings,
+    pub handling: Handling,
+    pub finesse: FinesseSettings,
+    pub keys: Bindings,
+    pub palette: ColorPalette,
+    pub audio: AudioSettings,
+}
+
+impl Settings {
+    /// Where the settings file goes by default, in the platform's config directory
+    pub fn default_path() -> Option<PathBuf> {
+        files::config_dir().map(|dir| dir.join(SETTINGS_FILE))
+    }
+
+    /// Reads settings from a file, writing the defaults out to it first if there isn't one yet
+    pub fn load_or_create(path: impl AsRef<Path>) -> Result<Self, SettingsError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            let settings = Settings::default();
+            settings.save(path)?;
+            return Ok(settings);
+        }
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Writes the settings to a file, creating its directory if it has to
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SettingsError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Settings as they are in a config, e.g. after being changed in game
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            ticks: *config.ticks(),
+            mode: config.mode(),
+            rotation: config.rotation(),
+            randomizer: config.randomizer(),
+            pieces: config.pieces().source().to_string(),
+            timing: config.entry_delay(),
+            window: WindowSettings {
+                width: *config.w() as u32,
+                height: *config.h() as u32,
+            },
+            board: BoardSettings {
+                width: config.board_width(),
+                height: config.board_height(),
+                buffer: config.buffer_height(),
+            },
+            handling: config.handling(),
+            finesse: config.finesse(),
+            keys: config.bindings().clone(),
+            palette: config.palette().clone(),
+            audio: AudioSettings {
+                music: (config.music_volume() * 100.0).round() as u32,
+                effects: (config.effects_volume() * 100.0).round() as u32,
+            },
+        }
+    }
+
+    /// Overwrites the config's settings. Ticks and the window size only take effect before the context is built
+    pub fn apply(&self, config: &mut Config) {
+        config.set_ticks(self.ticks);
+        config.set_mode(self.mode);
+        config.set_rotation(self.rotation);
+        config.set_randomizer(self.randomizer);
+        // Already loaded once by `validate`, so it only fails if the file went away since
+        if let Ok(pieces) = PieceSet::load(&self.pieces) {
+            config.set_pieces(pieces);
+        }
+        config.set_entry_delay(self.timing);
+        config.resize(self.window.width as f32, self.window.height as f32);
+        config.set_board(self.board.width, self.board.height, self.board.buffer);
+        config.set_handling(self.handling);
+        config.set_finesse(self.finesse);
+        config.set_bindings(self.keys.clone());
+        config.set_palette(self.palette.clone());
+        config.set_music_volume(self.audio.music as f32 / 100.0);
+        config.set_effects_volume(self.audio.effects as f32 / 100.0);
+    }
+
+    /// Checks for values that are the right type, but still can't be played with
+    fn validate(&self) -> Result<(), SettingsError> {
+        let at_least = |key: &str, value: u32, min: u32| {
+            if value >= min {
+                Ok(())
+            } else {
+                Err(SettingsError::Invalid {
+                    key: key.to_string(),
+                    message: format!("{} is less than the minimum of {}", value, min),
+                })
+            }
+        };
+        let volume = |key: &str, value: u32| {
+            if value <= 100 {
+                Ok(())
+            } else {
+                Err(SettingsError::Invalid {
+                    key: key.to_string(),
+                    message: format!("{} is more than 100 percent", value),
+                })
+            }
+        };
+        let pieces = PieceSet::load(&self.pieces).map_err(|e| SettingsError::Invalid {
+            key: "pieces".to_string(),
+            message: e.to_string(),
+        })?;
+        at_least("ticks", self.ticks, 1)?;
+        at_least("window.width", self.window.width, MIN_WINDOW_SIZE)?;
+        at_least("window.height", self.window.height, MIN_WINDOW_SIZE)?;
+        // Every piece of the set has to fit across the board
+        at_least(
+            "board.width",
+            self.board.width,
+            MIN_BOARD_WIDTH.max(pieces.widest()),
+        )?;
+        at_least("board.height", self.board.height, MIN_BOARD_HEIGHT)?;
+        at_least("board.buffer", self.board.buffer, MIN_BUFFER_HEIGHT)?;
+        volume("audio.music", self.audio.music)?;
+        volume("audio.effects", self.audio.effects)?;
+        // Colors can only be given to pieces that are in the set
+        match self
+            .palette
+            .piece_names()
+            .find(|name| pieces.find(name).is_none())
+        {
+            Some(name) => Err(SettingsError::Invalid {
+                key: format!("palette.pieces.{}", name),
+                message: format!("there is no {} piece in the {} set", name, pieces.name()),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Settings::default().window
+    }
+}
+
+impl Default for BoardSettings {
+    fn default() -> Self {
+        Settings::default().board
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Settings::default().audio
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        write!(
+            f,
+            "{}",
+            toml::to_string_pretty(self).map_err(|_| fmt::Error)?
+        )
+    }
+}
+
+impl FromStr for Settings {
+    type Err = SettingsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let table: toml::Table = s.parse().map_err(SettingsError::Syntax)?;
+        let settings: Settings = files::deserialize(toml::Value::Table(table))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::universe::simulation::GameAction;
+    use thomas::winit::event::VirtualKeyCode;
+
+    fn invalid_key(s: &str) -> String {
+        match s.parse::<Settings>() {
+            Err(SettingsError::Invalid { key, .. }) => key,
+            other => panic!("expected an invalid key, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut settings = Settings {
+            mode: GameMode::Sprint,
+            randomizer: RandomizerPreset::TgmHistory,
+            ..Settings::default()
+        };
+        settings.board.width = 20;
+        settings.audio.music = 25;
+        assert_eq!(settings.to_string().parse::<Settings>().unwrap(), settings);
+    }
+
+    #[test]
+    fn test_left_out_keys_keep_defaults() {
+        let settings: Settings = "ticks = 120\n[keys]\nhold = [\"Q\"]".parse().unwrap();
+        assert_eq!(settings.ticks, 120);
+        assert_eq!(settings.board, Settings::default().board);
+        assert_eq!(settings.keys.keys(GameAction::Hold), [VirtualKeyCode::Q]);
+        assert_eq!(
+            settings.keys.keys(GameAction::RotateCCW),
+            [VirtualKeyCode::Z]
+        );
+    }
+
+    #[test]
+    fn test_errors_name_the_key() {
+        assert_eq!(invalid_key("ticks = \"fast\""), "ticks");
+        assert_eq!(invalid_key("mode = \"survival\""), "mode");
+        assert_eq!(invalid_key("randomizer = \"bag\""), "randomizer");
+        assert_eq!(invalid_key("[board]\nwidth = 2"), "board.width");
+        assert_eq!(invalid_key("[palette]\nt = \"red\""), "palette.t");
+        assert_eq!(
+            invalid_key("[palette.pieces]\nT = \"red\""),
+            "palette.pieces.T"
+        );
+        assert_eq!(
+            invalid_key("[palette.pieces]\nU = \"FF0000\""),
+            "palette.pieces.U"
+        );
+        assert_eq!(invalid_key("pieces = \"hexomino\""), "pieces");
+        // Pentominoes are as much as 5 cells wide
+        assert_eq!(
+            invalid_key("pieces = \"pentomino\"\n[board]\nwidth = 4"),
+            "board.width"
+        );
+        assert_eq!(invalid_key("[audio]\nmusic = 200"), "audio.music");
+        assert_eq!(invalid_key("[keys]\nhold = [\"Foo\"]"), "keys.hold[0]");
+        // Z can't rotate and hold at once
+        assert_eq!(invalid_key("[keys]\nhold = [\"Z\"]"), "keys");
+        // S is for saving replays, and O for the options menu
+        assert_eq!(invalid_key("[keys]\nhold = [\"S\"]"), "keys");
+        assert_eq!(invalid_key("[keys]\npause = [\"O\"]"), "keys");
+        assert!(matches!(
+            "ticks = ".parse::<Settings>(),
+            Err(SettingsError::Syntax(_))
+        ));
+    }
+}
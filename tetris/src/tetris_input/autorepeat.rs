@@ -0,0 +1,88 @@
+// Repeats for held keys that are timed in milliseconds rather than ticks,
+// so that handling feels the same at any tick rate and can repeat faster than the ticks do
+
+/// How long a held key waits before it starts repeating, and how long between repeats, in milliseconds.
+/// A rate of 0 repeats as far as anything can go as soon as the delay runs out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+    pub delay: f64,
+    pub rate: f64,
+}
+
+/// Counts out the repeats of a single held key
+#[derive(Debug, Default)]
+pub struct Autorepeat {
+    // Milliseconds held, not counting any cut
+    held: f64,
+    // Milliseconds left before holding counts again
+    cut: f64,
+    // Repeats counted out since the key was pressed
+    repeats: u64,
+}
+
+impl Autorepeat {
+    /// Starts counting from the moment the key went down
+    pub fn press(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Stops counting for `ms`, e.g. after a rotation cuts into DAS
+    pub fn cut(&mut self, ms: f64) {
+        self.cut = ms;
+    }
+
+    /// How many times the key repeats over the next `ms` of it being held, at most `reach`
+    pub fn advance(&mut self, ms: f64, timing: Timing, reach: u32) -> u32 {
+        let cut = self.cut.min(ms);
+        self.cut -= cut;
+        self.held += ms - cut;
+        if self.held < timing.delay || self.cut > 0.0 {
+            return 0;
+        }
+        if timing.rate <= 0.0 {
+            // Keeps on going every tick, since whatever stopped it might have moved out of the way
+            return reach;
+        }
+        let due = 1 + ((self.held - timing.delay) / timing.rate) as u64;
+        let repeats = due.saturating_sub(self.repeats);
+        self.repeats = due;
+        repeats.min(reach as u64) as u32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_repeats_by_time_not_ticks() {
+        let timing = Timing {
+            delay: 100.0,
+            rate: 5.0,
+        };
+        let mut repeat = Autorepeat::default();
+        repeat.press();
+        assert_eq!(repeat.advance(0.0, timing, 10), 0);
+        assert_eq!(repeat.advance(99.0, timing, 10), 0);
+        // DAS runs out, then 3 more repeats at 5 ms each over the rest of the tick
+        assert_eq!(repeat.advance(16.0, timing, 10), 4);
+        // Never past the wall
+        assert_eq!(repeat.advance(1000.0, timing, 10), 10);
+
+        repeat.cut(20.0);
+        assert_eq!(repeat.advance(16.0, timing, 10), 0);
+        assert_eq!(repeat.advance(9.0, timing, 10), 1);
+    }
+
+    #[test]
+    fn test_zero_rate_goes_all_the_way() {
+        let timing = Timing {
+            delay: 0.0,
+            rate: 0.0,
+        };
+        let mut repeat = Autorepeat::default();
+        repeat.press();
+        assert_eq!(repeat.advance(0.0, timing, 22), 22);
+        assert_eq!(repeat.advance(16.0, timing, 22), 22);
+    }
+}
@@ -1,76 +1,554 @@
+use std::collections::BTreeMap;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use thomas::{context::Context, winit::event::VirtualKeyCode};
 // Utils for holding a key
 mod utils;
+// Repeats timed in milliseconds
+mod autorepeat;
 
+use crate::config::Config;
+use crate::universe::game::FIRST_LEVEL_FALL;
+use crate::universe::kept_for;
+use crate::universe::simulation::GameAction;
+use autorepeat::{Autorepeat, Timing};
 use utils::*;
 // The framework that keyboard input and keys are built on
 
-pub trait InputInterface {
-    fn receive_key(&mut self, ctx: &mut Context);
+// Held down, these never repeat, e.g. pausing would just flicker between states
+const NEVER: Repeat = Repeat {
+    delay: u32::MAX,
+    rate: u32::MAX,
+};
+
+/// How held keys repeat, in milliseconds so that it feels the same at any tick rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Handling {
+    /// Delayed auto shift, how long a move has to be held before it starts repeating
+    pub das: u32,
+    /// Auto repeat rate, how long between moves once it does. 0 goes straight to the wall
+    pub arr: u32,
+    /// Soft drop factor, how many times faster than the first level's gravity soft dropping goes.
+    /// 0 is infinite, dropping straight to the floor without locking
+    pub sdf: u32,
+    /// DAS cut delay, how long moving stops repeating for after a rotation
+    pub dcd: u32,
+}
+
+impl Handling {
+    fn shift_timing(&self) -> Timing {
+        Timing {
+            delay: self.das as f64,
+            rate: self.arr as f64,
+        }
+    }
+
+    /// Soft dropping repeats from the moment it's pressed, at a multiple of the first level's gravity
+    fn soft_drop_timing(&self, ticks_per_second: u32) -> Timing {
+        if self.sdf == 0 {
+            return Timing {
+                delay: 0.0,
+                rate: 0.0,
+            };
+        }
+        let gravity = FIRST_LEVEL_FALL as f64 * 1000.0 / ticks_per_second.max(1) as f64;
+        let rate = gravity / self.sdf as f64;
+        Timing { delay: rate, rate }
+    }
+}
+
+impl Default for Handling {
+    /// About 8 ticks of DAS and 4 ticks of ARR at 60 ticks a second
+    fn default() -> Self {
+        Self {
+            das: 133,
+            arr: 67,
+            sdf: 13,
+            dcd: 0,
+        }
+    }
+}
+
+/// Which keys stand for which action.
+/// An action can have any number of keys, but a key stands for one action at most
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bindings {
+    keys: BTreeMap<GameAction, Vec<VirtualKeyCode>>,
 }
+
+impl Bindings {
+    fn from_pairs(pairs: &[(GameAction, &[VirtualKeyCode])]) -> Self {
+        Self {
+            keys: pairs
+                .iter()
+                .map(|(action, keys)| (*action, keys.to_vec()))
+                .collect(),
+        }
+    }
+
+    /// Keys for the left board in versus, which plays on the left side of the keyboard
+    pub fn left_player() -> Self {
+        Self::from_pairs(&[
+            (GameAction::MoveLeft, &[VirtualKeyCode::A]),
+            (GameAction::MoveRight, &[VirtualKeyCode::D]),
+            (GameAction::SoftDrop, &[VirtualKeyCode::S]),
+            (GameAction::HardDrop, &[VirtualKeyCode::W]),
+            (GameAction::RotateCW, &[VirtualKeyCode::E]),
+            (GameAction::RotateCCW, &[VirtualKeyCode::Q]),
+            (GameAction::Rotate180, &[VirtualKeyCode::F]),
+            (GameAction::Hold, &[VirtualKeyCode::Tab]),
+            (GameAction::Pause, &[VirtualKeyCode::P]),
+            (GameAction::Restart, &[VirtualKeyCode::R]),
+        ])
+    }
+
+    /// Keys for the right board in versus, which plays with the arrows and the keys around them
+    pub fn right_player() -> Self {
+        Self::from_pairs(&[
+            (GameAction::MoveLeft, &[VirtualKeyCode::Left]),
+            (GameAction::MoveRight, &[VirtualKeyCode::Right]),
+            (GameAction::SoftDrop, &[VirtualKeyCode::Down]),
+            (GameAction::HardDrop, &[VirtualKeyCode::Up]),
+            (GameAction::RotateCW, &[VirtualKeyCode::Period]),
+            (GameAction::RotateCCW, &[VirtualKeyCode::Comma]),
+            (GameAction::Rotate180, &[VirtualKeyCode::M]),
+            (GameAction::Hold, &[VirtualKeyCode::Slash]),
+            (GameAction::Pause, &[VirtualKeyCode::P]),
+            (GameAction::Restart, &[VirtualKeyCode::R]),
+        ])
+    }
+
+    /// Which action a key stands for, if any
+    pub fn action_for(&self, key: VirtualKeyCode) -> Option<GameAction> {
+        self.keys
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    /// Get the keys bound to an action
+    pub fn keys(&self, action: GameAction) -> &[VirtualKeyCode] {
+        self.keys.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    /// Adds `key` to an action's keys, taking it off whichever action it stood for before
+    pub fn bind(&mut self, action: GameAction, key: VirtualKeyCode) {
+        for keys in self.keys.values_mut() {
+            keys.retain(|&k| k != key);
+        }
+        self.keys.entry(action).or_default().push(key);
+    }
+
+    /// Takes every key off an action, leaving it unbound
+    pub fn unbind(&mut self, action: GameAction) {
+        self.keys.insert(action, Vec::new());
+    }
+
+    /// A key that's bound to two different actions, along with both of them
+    fn conflict(&self) -> Option<(VirtualKeyCode, GameAction, GameAction)> {
+        self.keys.iter().find_map(|(action, keys)| {
+            keys.iter().find_map(|&key| {
+                self.keys
+                    .iter()
+                    .find(|(other, keys)| *other != action && keys.contains(&key))
+                    .map(|(other, _)| (key, *action, *other))
+            })
+        })
+    }
+
+    /// A key that's bound to an action but kept for the window, along with the action and what it's kept for
+    fn command(&self) -> Option<(VirtualKeyCode, GameAction, &'static str)> {
+        self.keys.iter().find_map(|(action, keys)| {
+            keys.iter()
+                .find_map(|&key| kept_for(key).map(|command| (key, *action, command)))
+        })
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self::from_pairs(&[
+            (GameAction::MoveLeft, &[VirtualKeyCode::Left]),
+            (GameAction::MoveRight, &[VirtualKeyCode::Right]),
+            (GameAction::SoftDrop, &[VirtualKeyCode::Down]),
+            (GameAction::HardDrop, &[VirtualKeyCode::Space]),
+            (
+                GameAction::RotateCW,
+                &[VirtualKeyCode::X, VirtualKeyCode::Up],
+            ),
+            (GameAction::RotateCCW, &[VirtualKeyCode::Z]),
+            (GameAction::Rotate180, &[VirtualKeyCode::V]),
+            (
+                GameAction::Hold,
+                &[VirtualKeyCode::C, VirtualKeyCode::LShift],
+            ),
+            (GameAction::Pause, &[VirtualKeyCode::P]),
+            (GameAction::Restart, &[VirtualKeyCode::R]),
+        ])
+    }
+}
+
+/// A table of action names to the keys bound to them, e.g. `hold = ["C", "LShift"]`
+impl Serialize for Bindings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.keys.iter().map(|(action, keys)| (action.name(), keys)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Bindings {
+    /// Actions left out keep their default keys, so that rebinding one action doesn't unbind the rest
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut bindings = Bindings::default();
+        for (name, keys) in BTreeMap::<String, Vec<VirtualKeyCode>>::deserialize(deserializer)? {
+            let action = GameAction::from_name(&name)
+                .ok_or_else(|| de::Error::custom(format!("unknown action `{}`", name)))?;
+            bindings.keys.insert(action, keys);
+        }
+        if let Some((key, action, other)) = bindings.conflict() {
+            return Err(de::Error::custom(format!(
+                "{:?} is bound to both {} and {}",
+                key,
+                action.name(),
+                other.name()
+            )));
+        }
+        match bindings.command() {
+            Some((key, action, command)) => Err(de::Error::custom(format!(
+                "{:?} is bound to {}, but it's kept for {}",
+                key,
+                action.name(),
+                command
+            ))),
+            None => Ok(bindings),
+        }
+    }
+}
+
 // Our implementation of tetrominos
 pub struct TetrominoControls {
-    // Not sure if fallrate really fits the agenda here
-    controlled_keys: Vec<ControlledKey>,
-    queue: Vec<VirtualKeyCode>,
+    // Keys for everything but moving and soft dropping, which are timed
+    controlled_keys: Vec<(ControlledKey, GameAction)>,
+    // Directions held, the one pressed last at the end. It's the one that moves
+    shifting: Vec<GameAction>,
+    shift: Autorepeat,
+    shift_timing: Timing,
+    soft_dropping: bool,
+    soft_drop: Autorepeat,
+    soft_drop_timing: Timing,
+    // Milliseconds moving stops repeating for after a rotation
+    das_cut: f64,
+    // Milliseconds a tick lasts
+    tick_ms: f64,
+    // Most moves and soft drops that could ever happen in one go, i.e. across and down the whole board
+    reach: (u32, u32),
+    // Keys for the window rather than the game
+    command_keys: Vec<ControlledKey>,
+    // Actions queued up since the queue was last cleared
+    queue: Vec<GameAction>,
+    // Those of them that came from a key going down, rather than one being held
+    presses: Vec<GameAction>,
+    commands: Vec<VirtualKeyCode>,
+    // Which action each key stands for
+    bindings: Bindings,
 }
 
 // This implementation isn't gonna work, if we have for example more functions that we want the keys to do than move the tetromino
 impl TetrominoControls {
+    /// Controls that play by `bindings`, repeating held keys by the config's handling
+    pub fn new(bindings: Bindings, config: &Config) -> Self {
+        let handling = config.handling();
+        let ticks_per_second = (*config.ticks()).max(1);
+        // Keys pressed on the same tick queue up in the order their actions are carried out.
+        // Only moving and soft dropping repeat, holding anything else down does it once per press
+        let controlled_keys = GameAction::ALL
+            .iter()
+            .filter(|action| !Self::timed(**action))
+            .flat_map(|&action| {
+                bindings.keys(action).iter().map(move |&key| {
+                    let controlled_key = ControlledKey {
+                        key,
+                        repeat: NEVER,
+                        ..Default::default()
+                    };
+                    (controlled_key, action)
+                })
+            })
+            .collect();
+        TetrominoControls {
+            controlled_keys,
+            shifting: Vec::new(),
+            shift: Autorepeat::default(),
+            shift_timing: handling.shift_timing(),
+            soft_dropping: false,
+            soft_drop: Autorepeat::default(),
+            soft_drop_timing: handling.soft_drop_timing(ticks_per_second),
+            das_cut: handling.dcd as f64,
+            tick_ms: 1000.0 / ticks_per_second as f64,
+            reach: (
+                config.board_width(),
+                config.board_height() + config.buffer_height(),
+            ),
+            command_keys: Vec::new(),
+            queue: Vec::new(),
+            presses: Vec::new(),
+            commands: Vec::new(),
+            bindings,
+        }
+    }
+
+    /// Also queues up `keys` that are for the window rather than the game, e.g. saving a replay.
+    /// They never repeat, and keys already bound to an action are left to it
+    pub fn with_commands(mut self, keys: &[VirtualKeyCode]) -> Self {
+        for &key in keys {
+            if self.bindings.action_for(key).is_none() {
+                self.command_keys.push(ControlledKey {
+                    key,
+                    // Has to be let go of first, in case it's still held from whatever rebuilt the controls
+                    buffer: Buffer::Opened(0),
+                    repeat: NEVER,
+                    ..Default::default()
+                });
+            }
+        }
+        self
+    }
+
     pub fn clear_queue(&mut self) {
         self.queue.clear();
+        self.presses.clear();
+        self.commands.clear();
     }
 
-    pub fn get_queue(&self) -> &[VirtualKeyCode] {
+    /// Get the actions queued up, in the order they're carried out
+    pub fn actions(&self) -> &[GameAction] {
         &self.queue
     }
 
+    /// Get the actions queued up by a key going down, leaving out held keys repeating
+    pub fn presses(&self) -> &[GameAction] {
+        &self.presses
+    }
+
+    /// Whether the window command `key` is queued up
+    pub fn command(&self, key: VirtualKeyCode) -> bool {
+        self.commands.contains(&key)
+    }
+
+    /// Whether `action` repeats by time rather than ticks
+    fn timed(action: GameAction) -> bool {
+        matches!(
+            action,
+            GameAction::MoveLeft | GameAction::MoveRight | GameAction::SoftDrop
+        )
+    }
+
+    /// Whether any key bound to `action` is down
+    fn held(&self, ctx: &mut Context, action: GameAction) -> bool {
+        self.bindings
+            .keys(action)
+            .iter()
+            .any(|&key| ctx.keyboard.is_pressed(key))
+    }
+
+    /// Queues up moves for whichever direction was pressed last, now that `ms` more have passed
+    fn tick_shift(&mut self, ctx: &mut Context, ms: f64) {
+        let was = self.shifting.last().copied();
+        let held = [GameAction::MoveLeft, GameAction::MoveRight].map(|d| (d, self.held(ctx, d)));
+        self.shifting
+            .retain(|d| held.iter().any(|&(h, down)| h == *d && down));
+        for (direction, down) in held {
+            if down && !self.shifting.contains(&direction) {
+                self.shifting.push(direction);
+                self.presses.push(direction);
+            }
+        }
+        let Some(&direction) = self.shifting.last() else {
+            return;
+        };
+        // Letting go of the last direction pressed hands it back to the other one, as if it was just pressed
+        let moves = if was != Some(direction) {
+            self.shift.press();
+            1 + self.shift.advance(0.0, self.shift_timing, self.reach.0)
+        } else {
+            self.shift.advance(ms, self.shift_timing, self.reach.0)
+        };
+        for _ in 0..moves.min(self.reach.0) {
+            self.queue.push(direction);
+        }
+    }
+
+    /// Queues up soft drops, now that `ms` more have passed
+    fn tick_soft_drop(&mut self, ctx: &mut Context, ms: f64) {
+        let down = self.held(ctx, GameAction::SoftDrop);
+        let drops = match (self.soft_dropping, down) {
+            (_, false) => 0,
+            (false, true) => {
+                self.presses.push(GameAction::SoftDrop);
+                self.soft_drop.press();
+                1 + self
+                    .soft_drop
+                    .advance(0.0, self.soft_drop_timing, self.reach.1)
+            }
+            (true, true) => self
+                .soft_drop
+                .advance(ms, self.soft_drop_timing, self.reach.1),
+        };
+        self.soft_dropping = down;
+        for _ in 0..drops.min(self.reach.1) {
+            self.queue.push(GameAction::SoftDrop);
+        }
+    }
+
     pub fn tick(&mut self, ctx: &mut Context) {
-        for controlled_key in self.controlled_keys.iter_mut() {
+        let mut actions = Vec::new();
+        for (controlled_key, action) in self.controlled_keys.iter_mut() {
+            // Keys that were up have nothing to repeat
+            let fresh = matches!(controlled_key.buffer, Buffer::Closed);
+            if controlled_key.tick(ctx) {
+                actions.push(*action);
+                if fresh {
+                    self.presses.push(*action);
+                }
+            }
+        }
+        let rotated = actions.iter().any(|action| {
+            matches!(
+                action,
+                GameAction::RotateCW | GameAction::RotateCCW | GameAction::Rotate180
+            )
+        });
+        if rotated && self.das_cut > 0.0 {
+            self.shift.cut(self.das_cut);
+        }
+        // Moves and soft drops come first, as they do in `GameAction::ALL`
+        self.tick_shift(ctx, self.tick_ms);
+        self.tick_soft_drop(ctx, self.tick_ms);
+        self.queue.extend(actions);
+        for controlled_key in self.command_keys.iter_mut() {
             if controlled_key.tick(ctx) {
-                self.queue.push(controlled_key.key)
+                self.commands.push(controlled_key.key)
             }
         }
     }
 }
 
-impl Default for TetrominoControls {
+/// Keys for watching a replay rather than playing
+pub struct PlaybackControls {
+    // Pauses or resumes playback
+    toggle: ControlledKey,
+    // Advances a paused replay by a single tick
+    step: ControlledKey,
+}
+
+impl PlaybackControls {
+    /// Whether playback should be paused or resumed this tick
+    pub fn toggle(&mut self, ctx: &mut Context) -> bool {
+        self.toggle.tick(ctx)
+    }
+
+    /// Whether a paused replay should advance a tick
+    pub fn step(&mut self, ctx: &mut Context) -> bool {
+        self.step.tick(ctx)
+    }
+
+    /// Whether the replay should play faster than normal
+    pub fn fast_forward(&self, ctx: &mut Context) -> bool {
+        ctx.keyboard.is_pressed(VirtualKeyCode::Right)
+    }
+}
+
+impl Default for PlaybackControls {
     fn default() -> Self {
-        let controlled_keys = vec![
-            ControlledKey {
-                key: VirtualKeyCode::Left,
-                repeat: Repeat { delay: 8, rate: 4 },
-                ..Default::default()
-            },
-            ControlledKey {
-                key: VirtualKeyCode::Right,
-                repeat: Repeat { delay: 8, rate: 4 },
+        PlaybackControls {
+            toggle: ControlledKey {
+                key: VirtualKeyCode::Space,
+                repeat: NEVER,
                 ..Default::default()
             },
-            ControlledKey {
+            // Holding it down steps through ticks, just slower than playing them
+            step: ControlledKey {
                 key: VirtualKeyCode::Down,
-                repeat: Repeat { delay: 0, rate: 4 },
-                ..Default::default()
-            },
-            ControlledKey {
-                key: VirtualKeyCode::Z,
-                repeat: Repeat { delay: 8, rate: 8 },
-                ..Default::default()
-            },
-            ControlledKey {
-                key: VirtualKeyCode::C,
-                repeat: Repeat { delay: 8, rate: 8 },
+                repeat: Repeat { delay: 8, rate: 4 },
                 ..Default::default()
             },
-            ControlledKey {
-                key: VirtualKeyCode::Space,
-                repeat: Repeat { delay: 8, rate: 8 },
+        }
+    }
+}
+
+/// Keys for moving around a menu, arrows to pick and change entries
+pub struct MenuControls {
+    previous: ControlledKey,
+    next: ControlledKey,
+    decrease: ControlledKey,
+    increase: ControlledKey,
+    // Leaves the menu
+    close: ControlledKey,
+}
+
+impl MenuControls {
+    /// Menu controls opened by pressing `close`, which has to be let go of before it can close the menu
+    pub fn new(close: VirtualKeyCode) -> Self {
+        let arrow = |key| ControlledKey {
+            key,
+            repeat: Repeat { delay: 8, rate: 4 },
+            ..Default::default()
+        };
+        MenuControls {
+            previous: arrow(VirtualKeyCode::Up),
+            next: arrow(VirtualKeyCode::Down),
+            decrease: arrow(VirtualKeyCode::Left),
+            increase: arrow(VirtualKeyCode::Right),
+            close: ControlledKey {
+                key: close,
+                buffer: Buffer::Opened(0),
+                repeat: NEVER,
                 ..Default::default()
             },
-        ];
-        TetrominoControls {
-            controlled_keys,
-            queue: Vec::new(),
         }
     }
+
+    /// Which way the selection moves this tick, -1 for up and 1 for down
+    pub fn select(&mut self, ctx: &mut Context) -> i32 {
+        self.next.tick(ctx) as i32 - self.previous.tick(ctx) as i32
+    }
+
+    /// Which way the selected entry changes this tick, -1 for left and 1 for right
+    pub fn change(&mut self, ctx: &mut Context) -> i32 {
+        self.increase.tick(ctx) as i32 - self.decrease.tick(ctx) as i32
+    }
+
+    /// Whether the menu should close this tick
+    pub fn close(&mut self, ctx: &mut Context) -> bool {
+        self.close.tick(ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_binding_moves_keys_between_actions() {
+        let mut bindings = Bindings::default();
+        bindings.bind(GameAction::Hold, VirtualKeyCode::Up);
+        // Up is still for rotating in the default bindings, as well as X
+        assert_eq!(
+            bindings.action_for(VirtualKeyCode::Up),
+            Some(GameAction::Hold)
+        );
+        assert_eq!(bindings.keys(GameAction::RotateCW), [VirtualKeyCode::X]);
+        assert_eq!(
+            bindings.keys(GameAction::Hold),
+            [
+                VirtualKeyCode::C,
+                VirtualKeyCode::LShift,
+                VirtualKeyCode::Up
+            ]
+        );
+        assert_eq!(bindings.conflict(), None);
+
+        bindings.unbind(GameAction::Hold);
+        assert_eq!(bindings.action_for(VirtualKeyCode::C), None);
+    }
 }
@@ -2,18 +2,13 @@ use thomas::{context::Context, winit::event::VirtualKeyCode};
 // Should be some sort of component architecture
 // Wonder if we should make a tetromino util, call that from here
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub enum KeyboardState {
+    #[default]
     Initiation,
     Held,
 }
 
-impl Default for KeyboardState {
-    fn default() -> Self {
-        Self::Initiation
-    }
-}
-
 pub enum Buffer {
     Opened(u32),
     Closed,
@@ -7,7 +7,7 @@ mod rotations {
     use super::*;
 
     /// Based on
-    /// ```rust
+    /// ```ignore
     /// fn pos_neg_modulus(&mut self, x: u32, m: u32) -> u32 {
     ///     (x % m + m) % m
     /// }
@@ -40,4 +40,4 @@ mod rotations {
             CircularNum { rn: 0, max: 4 }
         }
     }
-}
\ No newline at end of file
+}
@@ -20,4 +20,4 @@ impl Coord {
     pub fn mut_y(&mut self) -> &mut u32 {
         &mut self.y
     }
-}
\ No newline at end of file
+}
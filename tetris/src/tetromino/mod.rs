@@ -1,14 +1,18 @@
 pub mod circular_num;
 pub mod collisions;
 pub mod coord;
+pub mod piece_set;
 pub mod tetromino_type;
 
+use std::rc::Rc;
+
 use circular_num::*;
 use coord::*;
+use piece_set::Piece;
 use tetromino_type::*;
-use thomas::context::Context;
+use thomas::{context::Context, graphics::frontend::color::Color};
 
-use super::universe::{color::ColorPalette, Dimensions};
+use super::universe::{color::ColorPalette, playfield::Playfield, Dimensions};
 use super::Config;
 
 pub enum MoveDirection {
@@ -22,20 +26,23 @@ pub enum MoveDirection {
 #[derive(Clone)]
 pub struct Tetromino {
     coords: Vec<Coord>,
-    tetromino_type: TetrominoType,
+    // What the tetromino is, shared with every other tetromino of its type
+    piece: Rc<Piece>,
     rotation_state: CircularNum,
+    // Whether it's already kicked up off the floor, which rotation systems that allow it only do once a tetromino
+    floor_kicked: bool,
 }
 
 impl Tetromino {
-    /// Generates a tetromino, given a set of coords, a type
+    /// Generates a tetromino, given a set of coords, a piece
     /// The center of the tetromino, as well as the location it should be spawned in
     pub fn spawn_tetromino(
         // List of coords
         reference_coords: Vec<Coord>,
         // Real center is where to spawn the tetromino
         spawn_coords: Coord,
-        // Type of tetromino
-        tetromino_type: TetrominoType,
+        // Piece the tetromino is
+        piece: Rc<Piece>,
     ) -> Tetromino {
         // Generate real coords from reference coords
         let coords = reference_coords
@@ -52,8 +59,9 @@ impl Tetromino {
 
         Tetromino {
             coords,
-            tetromino_type,
+            piece,
             rotation_state: CircularNum::default(),
+            floor_kicked: false,
         }
     }
 
@@ -70,21 +78,20 @@ impl Tetromino {
         dim: &Dimensions,
         color_palette: &ColorPalette,
     ) {
-        let dy = config.h() / *dim.h() as f32;
-        let dx = config.actual_w() / *dim.w() as f32;
+        let (dx, dy) = (config.cell(), config.cell());
 
-        // For every coord in the tetromino (4 coords in total)
-        for (_idx, coord) in self.coords.iter().enumerate() {
+        // For every coord in the tetromino
+        for coord in self.coords.iter() {
             if coord.y >= *dim.h() {
                 continue;
             }
             // Figure out what this means in terms of real coords
             ctx.graphics.draw_rectangle(
                 config.canvas_l() + coord.x as f32 * dx,
-                config.h() - (coord.y as f32 + 1.0) * dy,
-                dx,
-                dy,
-                color_palette.color_for(self.tetromino_type),
+                config.canvas_b() - (coord.y as f32 + 1.0) * dy,
+                *dx,
+                *dy,
+                color_palette.color_for(&self.piece),
             )
         }
     }
@@ -96,21 +103,37 @@ impl Tetromino {
         dim: &Dimensions,
         color_palette: &ColorPalette,
     ) {
-        let dy: f32 = config.h() / *dim.h() as f32;
-        let dx: f32 = config.actual_w() / *dim.w() as f32;
+        let (dx, dy) = (config.cell(), config.cell());
 
-        // For every coord in the tetromino (4 coords in total)
-        for (_idx, coord) in self.coords.iter().enumerate() {
+        // For every coord in the tetromino
+        for coord in self.coords.iter() {
             if coord.y >= *dim.h() {
                 continue;
             }
             // Figure out what this means in terms of real coords
             ctx.graphics.draw_rectangle(
                 config.canvas_l() + coord.x as f32 * dx,
-                config.h() - (coord.y as f32 + 1.0) * dy,
-                dx,
-                dy,
-                color_palette.color_for(self.tetromino_type).fade(0.4),
+                config.canvas_b() - (coord.y as f32 + 1.0) * dy,
+                *dx,
+                *dy,
+                color_palette.color_for(&self.piece).fade(0.4),
+            )
+        }
+    }
+
+    /// Renders the tetromino outside of the board, e.g. in the next queue,
+    /// with the top left of its bounding box at `(left, top)`
+    pub fn render_preview(&self, ctx: &mut Context, left: f32, top: f32, cell: f32, color: Color) {
+        let min_x = self.coords.iter().map(|c| c.x).min().unwrap_or_default();
+        let max_y = self.coords.iter().map(|c| c.y).max().unwrap_or_default();
+
+        for coord in self.coords.iter() {
+            ctx.graphics.draw_rectangle(
+                left + (coord.x - min_x) as f32 * cell,
+                top + (max_y - coord.y) as f32 * cell,
+                cell,
+                cell,
+                color,
             )
         }
     }
@@ -136,6 +159,11 @@ impl Tetromino {
 
 // Getters and setters
 impl Tetromino {
+    /// Get the row of the tetromino's lowest cell
+    pub fn lowest_y(&self) -> u32 {
+        self.coords.iter().map(|c| c.y).min().unwrap_or_default()
+    }
+
     /// Get a mutable reference to the tetromino's rotation state.
     pub fn rotation_state_mut(&mut self) -> &mut CircularNum {
         &mut self.rotation_state
@@ -143,7 +171,12 @@ impl Tetromino {
 
     /// Get a reference to the tetromino's tetromino type.
     pub fn tetromino_type(&self) -> &TetrominoType {
-        &self.tetromino_type
+        self.piece.tetromino_type()
+    }
+
+    /// Get a reference to the piece the tetromino is
+    pub fn piece(&self) -> &Piece {
+        &self.piece
     }
 
     /// Get a reference to the tetromino's rotation state.
@@ -151,6 +184,16 @@ impl Tetromino {
         &self.rotation_state
     }
 
+    /// Get whether the tetromino has already kicked up off the floor
+    pub fn floor_kicked(&self) -> bool {
+        self.floor_kicked
+    }
+
+    /// Set whether the tetromino has already kicked up off the floor
+    pub fn set_floor_kicked(&mut self, floor_kicked: bool) {
+        self.floor_kicked = floor_kicked;
+    }
+
     pub fn coords(&self) -> &Vec<Coord> {
         &self.coords
     }
@@ -0,0 +1,454 @@
+use std::collections::HashSet;
+use std::fs;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use thomas::graphics::frontend::color::Color;
+
+use super::*;
+use crate::files::{self, FileError};
+use crate::universe::color::hex;
+use crate::universe::{MIN_BOARD_HEIGHT, MIN_BOARD_WIDTH, MIN_BUFFER_HEIGHT};
+
+/// Piece sets that come with the game, by name
+const BUILT_IN: [(&str, &str); 4] = [
+    (
+        "standard",
+        include_str!("../../resources/pieces/standard.toml"),
+    ),
+    (
+        "tromino",
+        include_str!("../../resources/pieces/tromino.toml"),
+    ),
+    (
+        "pentomino",
+        include_str!("../../resources/pieces/pentomino.toml"),
+    ),
+    ("party", include_str!("../../resources/pieces/party.toml")),
+];
+// Names of the guideline tetrominoes, which some rules only make sense for
+const TETROMINOES: [(&str, GuidelineTetromino); 7] = [
+    ("I", GuidelineTetromino::I),
+    ("J", GuidelineTetromino::J),
+    ("L", GuidelineTetromino::L),
+    ("O", GuidelineTetromino::O),
+    ("S", GuidelineTetromino::S),
+    ("T", GuidelineTetromino::T),
+    ("Z", GuidelineTetromino::Z),
+];
+// Cells that make up a tetromino
+const TETROMINO_CELLS: usize = 4;
+
+/// Something wrong with a piece file, e.g. `pieces[2].states` not being playable
+pub type PieceSetError = FileError<toml::de::Error>;
+
+/// One of the seven tetrominoes of the guideline, for rules like T-spins that only make sense for those
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuidelineTetromino {
+    I,
+    J,
+    L,
+    O,
+    S,
+    T,
+    Z,
+}
+
+/// Which kick table a piece turns with. Each rotation system has its own take on each of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum KickTable {
+    /// The table J, L, S, T and Z share
+    #[serde(rename = "jlstz")]
+    Jlstz,
+    /// The I tetromino's own table
+    #[serde(rename = "i")]
+    I,
+    /// Only ever turns in place, e.g. for O which looks the same every way round
+    #[serde(rename = "none")]
+    None,
+}
+
+/// A piece as a set describes it: its cells in every rotation state, where it spawns and how it turns
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Piece {
+    name: String,
+    #[serde(with = "hex")]
+    color: Color,
+    kicks: KickTable,
+    // Cells in each rotation state, clockwise from spawning, inside the box the piece turns about.
+    // The first cell of each is the piece's center
+    states: Vec<Vec<[u32; 2]>>,
+    // Moves the piece from where it would spawn, centered just above the visible board
+    #[serde(default)]
+    spawn: [i32; 2],
+    // Position in the set, filled in once the whole set is read
+    #[serde(skip)]
+    tetromino_type: TetrominoType,
+}
+
+impl Piece {
+    /// Cells in rotation state `rotation_state`, pieces with fewer than 4 states cycling through the ones they have
+    pub fn state(&self, rotation_state: u32) -> &[[u32; 2]] {
+        &self.states[rotation_state as usize % self.states.len()]
+    }
+
+    /// Get the number of cells the piece is made of
+    pub fn cells(&self) -> usize {
+        self.states[0].len()
+    }
+
+    /// The guideline tetromino this piece stands for, going by its name,
+    /// for rules that only make sense for those like T-spins
+    pub fn tetromino(&self) -> Option<GuidelineTetromino> {
+        if self.cells() != TETROMINO_CELLS {
+            return None;
+        }
+        TETROMINOES
+            .iter()
+            .find(|(name, _)| *name == self.name)
+            .map(|&(_, tetromino)| tetromino)
+    }
+
+    /// Width and height of the piece as it spawns, in cells
+    fn spawn_size(&self) -> [u32; 2] {
+        [0, 1].map(|axis| {
+            let cells = self.states[0].iter().map(|cell| cell[axis]);
+            cells.clone().max().unwrap_or_default() - cells.min().unwrap_or_default() + 1
+        })
+    }
+}
+
+// Getters and setters
+impl Piece {
+    /// Get the piece's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the piece's color
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Get the kick table the piece turns with
+    pub fn kicks(&self) -> KickTable {
+        self.kicks
+    }
+
+    /// Get a reference to the piece's tetromino type, its position in the set
+    pub fn tetromino_type(&self) -> &TetrominoType {
+        &self.tetromino_type
+    }
+}
+
+/// What a piece set file holds
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PieceSetFile {
+    name: String,
+    pieces: Vec<Piece>,
+}
+
+/// Every piece a game can deal, read from a built-in set or a piece set file
+#[derive(Debug, Clone, PartialEq)]
+pub struct PieceSet {
+    name: String,
+    // Built-in name or path the set was loaded from, which is enough to load it again
+    source: String,
+    pieces: Vec<Rc<Piece>>,
+}
+
+impl PieceSet {
+    /// Names of the sets that come with the game
+    pub fn built_in() -> impl Iterator<Item = &'static str> {
+        BUILT_IN.iter().map(|(name, _)| *name)
+    }
+
+    /// The seven tetrominoes of the guideline
+    pub fn standard() -> Self {
+        Self::load("standard").unwrap()
+    }
+
+    /// Loads a built-in set by name, or otherwise a piece set file from `source`
+    pub fn load(source: &str) -> Result<Self, PieceSetError> {
+        let mut set: PieceSet = match BUILT_IN.iter().find(|(name, _)| *name == source) {
+            Some((_, file)) => file.parse()?,
+            None => fs::read_to_string(source)?.parse()?,
+        };
+        set.source = source.to_string();
+        Ok(set)
+    }
+
+    /// Every type in the set, in the order the file lists them
+    pub fn types(&self) -> Vec<TetrominoType> {
+        self.pieces.iter().map(|p| *p.tetromino_type()).collect()
+    }
+
+    /// Get the piece of the given type
+    pub fn piece(&self, tetromino_type: TetrominoType) -> &Rc<Piece> {
+        &self.pieces[tetromino_type.index()]
+    }
+
+    /// Finds a piece by its name
+    pub fn find(&self, name: &str) -> Option<&Rc<Piece>> {
+        self.pieces.iter().find(|p| p.name() == name)
+    }
+
+    /// Cells across the widest piece as it spawns
+    pub fn widest(&self) -> u32 {
+        self.pieces
+            .iter()
+            .map(|p| p.spawn_size()[0])
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Cells up the tallest piece as it spawns
+    pub fn tallest(&self) -> u32 {
+        self.pieces
+            .iter()
+            .map(|p| p.spawn_size()[1])
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Spawns a tetromino of the given type, centered on the board and rounding to the left,
+    /// resting one row above the visible board unless the buffer is too short for that.
+    /// Then moved by the piece's own spawn offset
+    pub fn spawn(&self, tetromino_type: TetrominoType, dim: &Dimensions) -> Tetromino {
+        let piece = self.piece(tetromino_type);
+        let reference_coords: Vec<Coord> = piece
+            .state(0)
+            .iter()
+            .map(|&[x, y]| Coord::new(x, y))
+            .collect();
+
+        let min_x = reference_coords
+            .iter()
+            .map(|c| c.x)
+            .min()
+            .unwrap_or_default();
+        let min_y = reference_coords
+            .iter()
+            .map(|c| c.y)
+            .min()
+            .unwrap_or_default();
+        let [left, bottom] = Self::spawn_corner(piece, dim).map(|corner| {
+            u32::try_from(corner).expect("spawn offsets are checked to keep pieces on the board")
+        });
+        // Where the center goes for the bounding box to sit at (left, bottom)
+        let spawn_coords = Coord::new(
+            left + reference_coords[0].x - min_x,
+            bottom + reference_coords[0].y - min_y,
+        );
+        Tetromino::spawn_tetromino(reference_coords, spawn_coords, piece.clone())
+    }
+
+    /// Where the bottom left corner of the piece's box goes as it spawns on `dim`,
+    /// which is off the board if the piece's spawn offset moves it too far
+    fn spawn_corner(piece: &Piece, dim: &Dimensions) -> [i32; 2] {
+        let [width, height] = piece.spawn_size();
+        let left = dim.w().saturating_sub(width) / 2;
+        let bottom = (dim.h() + 1).min((dim.h() + dim.buffer()).saturating_sub(height.max(2)));
+        [left as i32 + piece.spawn[0], bottom as i32 + piece.spawn[1]]
+    }
+
+    /// Checks for pieces that are the right shape of data, but still can't be played with
+    fn validate(file: &PieceSetFile) -> Result<(), PieceSetError> {
+        let invalid = |key: String, message: &str| PieceSetError::Invalid {
+            key,
+            message: message.to_string(),
+        };
+        if file.pieces.is_empty() {
+            return Err(invalid(String::from("pieces"), "there has to be a piece"));
+        }
+        if file.pieces.len() > u8::MAX as usize {
+            return Err(invalid(
+                String::from("pieces"),
+                &format!("there can't be more than {} pieces", u8::MAX),
+            ));
+        }
+        let mut names = HashSet::new();
+        for (i, piece) in file.pieces.iter().enumerate() {
+            if !names.insert(piece.name.as_str()) {
+                return Err(invalid(
+                    format!("pieces[{}].name", i),
+                    &format!("{} is already the name of another piece", piece.name),
+                ));
+            }
+            let key = format!("pieces[{}].states", i);
+            if ![1, 2, 4].contains(&piece.states.len()) {
+                return Err(invalid(key, "there have to be 1, 2 or 4 rotation states"));
+            }
+            for (j, state) in piece.states.iter().enumerate() {
+                let key = format!("{}[{}]", key, j);
+                if state.is_empty() {
+                    return Err(invalid(key, "there has to be a cell"));
+                }
+                if state.len() != piece.states[0].len() {
+                    return Err(invalid(
+                        key,
+                        "every rotation state has to have as many cells",
+                    ));
+                }
+                if state.iter().collect::<HashSet<_>>().len() != state.len() {
+                    return Err(invalid(key, "the same cell is in there twice"));
+                }
+            }
+        }
+        // Boards only ever get bigger than the smallest one the set plays on, leaving more room around where pieces spawn
+        let widest = file.pieces.iter().map(|p| p.spawn_size()[0]).max();
+        let dim = Dimensions::new(
+            MIN_BOARD_WIDTH.max(widest.unwrap_or_default()),
+            MIN_BOARD_HEIGHT,
+            MIN_BUFFER_HEIGHT,
+        );
+        for (i, piece) in file.pieces.iter().enumerate() {
+            let [left, bottom] = Self::spawn_corner(piece, &dim);
+            let [width, height] = piece.spawn_size();
+            if left < 0
+                || bottom < 0
+                || left + width as i32 > *dim.w() as i32
+                || bottom + height as i32 > (dim.h() + dim.buffer()) as i32
+            {
+                return Err(invalid(
+                    format!("pieces[{}].spawn", i),
+                    &format!(
+                        "moves the piece off the smallest board it can be played on, {}x{}+{}",
+                        dim.w(),
+                        dim.h(),
+                        dim.buffer()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Getters and setters
+impl PieceSet {
+    /// Get the set's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the built-in name or path the set was loaded from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get every piece in the set
+    pub fn pieces(&self) -> &[Rc<Piece>] {
+        &self.pieces
+    }
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl FromStr for PieceSet {
+    type Err = PieceSetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let table: toml::Table = s.parse().map_err(PieceSetError::Syntax)?;
+        let file: PieceSetFile = files::deserialize(toml::Value::Table(table))?;
+        Self::validate(&file)?;
+        let pieces = file
+            .pieces
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut piece)| {
+                piece.tetromino_type = TetrominoType::new(i as u8);
+                Rc::new(piece)
+            })
+            .collect();
+        Ok(PieceSet {
+            source: file.name.clone(),
+            name: file.name,
+            pieces,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::universe::playfield::Playfield;
+
+    fn invalid_key(s: &str) -> String {
+        match s.parse::<PieceSet>() {
+            Err(PieceSetError::Invalid { key, .. }) => key,
+            other => panic!("expected an invalid key, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_built_in_sets_spawn_on_the_board() {
+        for name in PieceSet::built_in() {
+            let set = PieceSet::load(name).unwrap();
+            assert_eq!(set.name(), name);
+            let dim = Dimensions::new(set.widest().max(10), 20, 4);
+            let playfield = Playfield::new(&dim);
+            for tetromino_type in set.types() {
+                let spawned = set.spawn(tetromino_type, &dim);
+                assert!(spawned.within_boundary([0, 0], &dim), "{}", name);
+                assert!(!spawned.will_collide_diff(&playfield, [0, 0]));
+                assert_eq!(spawned.lowest_y(), 21);
+            }
+        }
+        let standard = PieceSet::standard();
+        assert_eq!(standard.pieces().len(), 7);
+        assert!(standard.pieces().iter().all(|p| p.tetromino().is_some()));
+        let pentomino = PieceSet::load("pentomino").unwrap();
+        assert_eq!(pentomino.widest(), 5);
+        assert!(pentomino.pieces().iter().all(|p| p.tetromino().is_none()));
+    }
+
+    #[test]
+    fn test_errors_name_the_key() {
+        let piece = |states: &str| {
+            format!(
+                "name = \"test\"\n[[pieces]]\nname = \"A\"\ncolor = \"FFFFFF\"\nkicks = \"jlstz\"\nstates = {}",
+                states
+            )
+        };
+        assert!(piece("[[[0, 0], [1, 0]]]").parse::<PieceSet>().is_ok());
+        assert_eq!(invalid_key("name = \"test\"\npieces = []"), "pieces");
+        assert_eq!(
+            invalid_key(&piece("[[[0, 0]], [[0, 0]], [[0, 0]]]")),
+            "pieces[0].states"
+        );
+        assert_eq!(
+            invalid_key(&piece("[[[0, 0]], [[0, 0], [1, 0]]]")),
+            "pieces[0].states[1]"
+        );
+        assert_eq!(
+            invalid_key(&piece("[[[0, 0], [0, 0]]]")),
+            "pieces[0].states[0]"
+        );
+        assert_eq!(
+            invalid_key(&piece("[[[0, -1]]]")),
+            "pieces[0].states[0][0][1]"
+        );
+        assert_eq!(
+            invalid_key(&piece("[[[0, 0]]]").replace("jlstz", "srs")),
+            "pieces[0].kicks"
+        );
+        // Anywhere the piece stays on the board is fine, anywhere off it isn't
+        let spawning =
+            |offset: &str| format!("{}\nspawn = {}", piece("[[[0, 0], [1, 0]]]"), offset);
+        assert!(spawning("[1, -1]").parse::<PieceSet>().is_ok());
+        assert_eq!(invalid_key(&spawning("[-6, 0]")), "pieces[0].spawn");
+        assert_eq!(invalid_key(&spawning("[0, 9]")), "pieces[0].spawn");
+        assert!(matches!(
+            PieceSet::load("no-such-set.toml"),
+            Err(PieceSetError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::playfield::Playfield;
+use super::rotations::{rotate, rotation_direction::RotationDirection, RotationSystem};
+use super::simulation::{GameAction, Simulation};
+use super::tetromino::{coord::Coord, MoveDirection, Tetromino};
+use super::Dimensions;
+
+// Actions searched from every position, hold and hard drop are handled separately
+const MOVES: [GameAction; 6] = [
+    GameAction::MoveLeft,
+    GameAction::MoveRight,
+    GameAction::RotateCW,
+    GameAction::RotateCCW,
+    GameAction::Rotate180,
+    GameAction::SoftDrop,
+];
+
+/// How much each feature of the board left behind by a placement counts towards its score.
+/// Higher scores are better, so features to avoid have negative weights
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weights {
+    pub aggregate_height: f32,
+    pub lines_cleared: f32,
+    pub holes: f32,
+    pub bumpiness: f32,
+    pub wells: f32,
+}
+
+impl Default for Weights {
+    /// Weights that have been found to play well for a while, with a small penalty for wells
+    fn default() -> Self {
+        Self {
+            aggregate_height: -0.51,
+            lines_cleared: 0.76,
+            holes: -0.36,
+            bumpiness: -0.18,
+            wells: -0.1,
+        }
+    }
+}
+
+impl Weights {
+    /// Scores a playfield that has already had its full rows cleared
+    pub fn evaluate(&self, playfield: &Playfield, lines_cleared: u32) -> f32 {
+        let heights = column_heights(playfield);
+        let aggregate_height: u32 = heights.iter().sum();
+        let bumpiness: u32 = heights.windows(2).map(|w| w[0].abs_diff(w[1])).sum();
+
+        self.aggregate_height * aggregate_height as f32
+            + self.lines_cleared * lines_cleared as f32
+            + self.holes * holes(playfield, &heights) as f32
+            + self.bumpiness * bumpiness as f32
+            + self.wells * wells(&heights) as f32
+    }
+}
+
+/// Height of the highest filled cell in each column, 0 for an empty column
+fn column_heights(playfield: &Playfield) -> Vec<u32> {
+    (0..playfield.w())
+        .map(|x| {
+            (0..playfield.h())
+                .rev()
+                .find(|&y| playfield.is_occupied(x as i32, y as i32))
+                .map_or(0, |y| y + 1)
+        })
+        .collect()
+}
+
+/// Empty cells with something filled above them in the same column
+fn holes(playfield: &Playfield, heights: &[u32]) -> u32 {
+    heights
+        .iter()
+        .enumerate()
+        .map(|(x, &h)| {
+            (0..h)
+                .filter(|&y| !playfield.is_occupied(x as i32, y as i32))
+                .count() as u32
+        })
+        .sum()
+}
+
+/// Total depth of the columns lower than both of their neighbours, walls count as neighbours as high as the other side
+fn wells(heights: &[u32]) -> u32 {
+    (0..heights.len())
+        .map(|x| {
+            let left = if x == 0 { None } else { Some(heights[x - 1]) };
+            let right = heights.get(x + 1).copied();
+            let edge = match (left, right) {
+                (Some(l), Some(r)) => l.min(r),
+                (Some(side), None) | (None, Some(side)) => side,
+                (None, None) => 0,
+            };
+            edge.saturating_sub(heights[x])
+        })
+        .sum()
+}
+
+/// Where a tetromino ends up once it locks, and how to get it there
+#[derive(Clone)]
+pub struct Placement {
+    /// The tetromino as it locks
+    pub tetromino: Tetromino,
+    /// Actions from the focused tetromino, always ending with a hard drop
+    pub actions: Vec<GameAction>,
+    /// How good the board left behind is
+    pub score: f32,
+}
+
+// Rotation and center cell, which together pin down a tetromino of a given type
+fn key(tetromino: &Tetromino) -> (u32, Coord) {
+    (*tetromino.rotation_state().rn(), tetromino.coords()[0])
+}
+
+// Cells a tetromino covers, regardless of which rotation got it there
+fn cells(tetromino: &Tetromino) -> Vec<Coord> {
+    let mut cells = tetromino.coords().clone();
+    cells.sort_by_key(|c| (c.x, c.y));
+    cells
+}
+
+/// Applies a move to a tetromino if it fits, like the simulation would
+fn step(
+    tetromino: &Tetromino,
+    action: GameAction,
+    playfield: &Playfield,
+    dim: &Dimensions,
+    system: &dyn RotationSystem,
+) -> Option<Tetromino> {
+    let shift = |direction| {
+        let dx_dy = Tetromino::get_dxdy(direction);
+        (tetromino.within_boundary(dx_dy, dim) && !tetromino.will_collide_diff(playfield, dx_dy))
+            .then(|| {
+                let mut moved = tetromino.clone();
+                moved.move_by(dx_dy);
+                moved
+            })
+    };
+    match action {
+        GameAction::MoveLeft => shift(MoveDirection::Left),
+        GameAction::MoveRight => shift(MoveDirection::Right),
+        GameAction::SoftDrop => shift(MoveDirection::Down),
+        GameAction::RotateCW => rotate(
+            tetromino,
+            RotationDirection::Clockwise,
+            playfield,
+            dim,
+            system,
+        )
+        .map(|(rotated, _)| rotated),
+        GameAction::RotateCCW => rotate(
+            tetromino,
+            RotationDirection::CounterClockwise,
+            playfield,
+            dim,
+            system,
+        )
+        .map(|(rotated, _)| rotated),
+        GameAction::Rotate180 => rotate(tetromino, RotationDirection::Half, playfield, dim, system)
+            .map(|(rotated, _)| rotated),
+        _ => None,
+    }
+}
+
+/// Drops a tetromino as far as it goes
+fn land(
+    tetromino: &Tetromino,
+    playfield: &Playfield,
+    dim: &Dimensions,
+    system: &dyn RotationSystem,
+) -> Tetromino {
+    let mut landed = tetromino.clone();
+    while let Some(lower) = step(&landed, GameAction::SoftDrop, playfield, dim, system) {
+        landed = lower;
+    }
+    landed
+}
+
+/// Every distinct spot a tetromino can lock into from where it is now, reached by moving, rotating with `system`'s kicks
+/// and soft dropping before a hard drop. Each comes with the fewest actions that get it there, but isn't scored yet
+pub fn placements(
+    tetromino: &Tetromino,
+    playfield: &Playfield,
+    dim: &Dimensions,
+    system: &dyn RotationSystem,
+) -> Vec<Placement> {
+    let mut seen = HashSet::from([key(tetromino)]);
+    let mut landed = HashSet::new();
+    let mut queue = VecDeque::from([(tetromino.clone(), Vec::new())]);
+    let mut placements = Vec::new();
+
+    // Breadth first, so the first path to reach anything is also the shortest
+    while let Some((current, path)) = queue.pop_front() {
+        let landing = land(&current, playfield, dim, system);
+        if landed.insert(cells(&landing)) {
+            let mut actions = path.clone();
+            actions.push(GameAction::HardDrop);
+            placements.push(Placement {
+                tetromino: landing,
+                actions,
+                score: 0.0,
+            });
+        }
+
+        for action in MOVES {
+            if let Some(next) = step(&current, action, playfield, dim, system) {
+                if seen.insert(key(&next)) {
+                    let mut next_path = path.clone();
+                    next_path.push(action);
+                    queue.push_back((next, next_path));
+                }
+            }
+        }
+    }
+    placements
+}
+
+/// The best scoring placement for the focused tetromino, holding first if that does better
+pub fn best_placement(sim: &Simulation, weights: &Weights) -> Option<Placement> {
+    let mut candidates = vec![(sim.focused_tetromino().clone(), false)];
+    if !sim.hold_locked() {
+        // Holding spawns the held tetromino, or the next one if nothing is held yet
+        let swapped = sim.held().or_else(|| sim.next_queue().front().copied());
+        if let Some(swapped) = swapped {
+            let spawned = sim.spawn(swapped);
+            if !spawned.will_collide_diff(sim.playfield(), [0, 0]) {
+                candidates.push((spawned, true));
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .flat_map(|(tetromino, held)| {
+            placements(
+                &tetromino,
+                sim.playfield(),
+                sim.dim(),
+                sim.rotation_system(),
+            )
+            .into_iter()
+            .map(move |mut placement| {
+                if held {
+                    placement.actions.insert(0, GameAction::Hold);
+                }
+                placement
+            })
+        })
+        .map(|mut placement| {
+            let mut playfield = sim.playfield().clone();
+            playfield.lock(&placement.tetromino);
+            let rows = playfield.full_rows();
+            playfield.clear_rows(&rows);
+            placement.score = weights.evaluate(&playfield, rows.len() as u32);
+            placement
+        })
+        // Ties go to whichever was found first, which took fewer actions
+        .fold(None, |best: Option<Placement>, placement| match best {
+            Some(best) if best.score >= placement.score => Some(best),
+            _ => Some(placement),
+        })
+}
+
+/// Plays the game by searching for the best placement of each tetromino and feeding in the actions to get there
+#[derive(Clone)]
+pub struct AiPlayer {
+    weights: Weights,
+    // Ticks between actions, 0 carries out a whole placement in a single tick
+    pace: u32,
+    // Actions left to reach the current placement
+    plan: VecDeque<GameAction>,
+    // Ticks left before the next action
+    wait: u32,
+    // Which tetromino the plan is for, counted by how many locked before it
+    planned_for: Option<u32>,
+    // Where the plan expects the focused tetromino to be by the next action, `None` if there's nothing to check
+    expected: Option<Tetromino>,
+}
+
+impl AiPlayer {
+    pub fn new(weights: Weights, pace: u32) -> Self {
+        Self {
+            weights,
+            pace,
+            plan: VecDeque::new(),
+            wait: 0,
+            planned_for: None,
+            expected: None,
+        }
+    }
+
+    /// Plans the focused tetromino's placement from wherever it is now
+    fn replan(&mut self, sim: &Simulation) {
+        self.plan = best_placement(sim, &self.weights)
+            .map_or_else(|| vec![GameAction::HardDrop], |p| p.actions)
+            .into();
+        self.planned_for = Some(sim.pieces());
+        self.expected = None;
+    }
+
+    /// Actions to feed the simulation this tick
+    pub fn actions(&mut self, sim: &Simulation) -> Vec<GameAction> {
+        if !sim.game().running() {
+            // Whatever comes after a pause, line clear or restart gets planned from scratch
+            self.planned_for = None;
+            return Vec::new();
+        }
+        if self.planned_for != Some(sim.pieces()) {
+            self.replan(sim);
+            self.wait = 0;
+        }
+
+        if self.pace == 0 {
+            return self.plan.drain(..).collect();
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            return Vec::new();
+        }
+        self.wait = self.pace - 1;
+        // Gravity and blocked moves between actions leave the tetromino somewhere the plan never went
+        let focused = sim.focused_tetromino();
+        let off_course = self
+            .expected
+            .as_ref()
+            .is_some_and(|expected| key(expected) != key(focused));
+        if off_course || self.plan.is_empty() {
+            self.replan(sim);
+        }
+        let Some(action) = self.plan.pop_front() else {
+            return Vec::new();
+        };
+        self.expected = match action {
+            // Holding brings in another tetromino, which is planned for afresh once it's in
+            GameAction::Hold => {
+                self.plan.clear();
+                None
+            }
+            _ => step(
+                focused,
+                action,
+                sim.playfield(),
+                sim.dim(),
+                sim.rotation_system(),
+            ),
+        };
+        vec![action]
+    }
+
+    /// Plays `ticks` ticks of a simulation headlessly
+    pub fn play(&mut self, sim: &mut Simulation, ticks: u32) {
+        for _ in 0..ticks {
+            let actions = self.actions(sim);
+            sim.tick(&actions);
+        }
+    }
+
+    /// Get the weights placements are scored with
+    pub fn weights(&self) -> &Weights {
+        &self.weights
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::entry_delay::TimingPreset;
+    use super::super::game::GameState;
+    use super::super::rotations::srs::Srs;
+    use super::super::simulation::Rules;
+    use super::*;
+    use crate::tetromino::piece_set::PieceSet;
+
+    #[test]
+    fn test_placements_cover_every_column() {
+        let dim = Dimensions::new(10, 20, 4);
+        let playfield = Playfield::new(&dim);
+        let pieces = PieceSet::standard();
+        let t = pieces.spawn(*pieces.find("T").unwrap().tetromino_type(), &dim);
+        let placements = placements(&t, &playfield, &dim, &Srs);
+
+        // 4 rotations, 8 or 9 columns each, all resting on the floor
+        assert_eq!(placements.len(), 8 + 9 + 8 + 9);
+        assert!(placements.iter().all(|p| p.tetromino.lowest_y() == 0));
+        assert!(placements
+            .iter()
+            .all(|p| p.actions.last() == Some(&GameAction::HardDrop)));
+    }
+
+    #[test]
+    fn test_ai_plays_without_topping_out() {
+        let rules = Rules {
+            ticks: 10,
+            entry_delay: TimingPreset::Modern.entry_delay(10),
+            ..Rules::default()
+        };
+        let mut sim = Simulation::new(rules, Some(3));
+        let mut ai = AiPlayer::new(Weights::default(), 0);
+        ai.play(&mut sim, 400);
+
+        assert!(matches!(
+            sim.game().state(),
+            GameState::Playing | GameState::LineClearDelay(_) | GameState::Entry(_)
+        ));
+        assert!(sim.game().lines_cleared() >= 20);
+    }
+
+    #[test]
+    fn test_ai_replans_once_the_tetromino_moves_under_it() {
+        let mut sim = Simulation::new(Rules::default(), Some(5));
+        let mut ai = AiPlayer::new(Weights::default(), 2);
+        while !sim.game().running() {
+            sim.tick(&[]);
+        }
+        let actions = ai.actions(&sim);
+        sim.tick(&actions);
+        // Knocked a couple of columns over and down, the way gravity or a blocked move would leave it
+        let focused = sim.focused_tetromino_mut();
+        focused.move_by([if focused.coords()[0].x > 4 { -2 } else { 2 }, -2]);
+        let target = best_placement(&sim, ai.weights()).unwrap().tetromino;
+
+        let pieces = sim.pieces();
+        while sim.pieces() == pieces {
+            let actions = ai.actions(&sim);
+            sim.tick(&actions);
+        }
+        assert!(target
+            .coords()
+            .iter()
+            .all(|c| sim.playfield().is_occupied(c.x as i32, c.y as i32)));
+    }
+}
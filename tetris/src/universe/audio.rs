@@ -6,11 +6,16 @@ use thomas::{context::Context, rodio::Sink};
 pub struct Audio {
     // Plays in the background for the enter game
     pub sink: Option<Sink>,
+    // Whether this plays the music at all, only one of the boards does in versus
+    pub music: bool,
 }
 
 impl Audio {
     /// Currently context isn't used but it probably will in the future
     pub fn new(_ctx: &mut Context) -> Self {
-        Self { sink: None }
+        Self {
+            sink: None,
+            music: true,
+        }
     }
 }
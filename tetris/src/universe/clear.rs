@@ -0,0 +1,195 @@
+use std::fmt;
+
+use super::playfield::Playfield;
+use super::rotations::rotation_direction::RotationDirection;
+use super::rotations::RotationSystem;
+use super::Dimensions;
+use crate::tetromino::piece_set::GuidelineTetromino;
+use crate::tetromino::Tetromino;
+
+/// Kind of T-spin a lock was
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TSpin {
+    None,
+    Mini,
+    Full,
+}
+
+/// Everything the last lock cleared, which is what scoring is based on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineClear {
+    pub lines: u32,
+    pub t_spin: TSpin,
+    // Whether the board was left completely empty
+    pub perfect_clear: bool,
+}
+
+impl LineClear {
+    /// Whether the lock is worth anything at all
+    pub fn is_empty(&self) -> bool {
+        self.lines == 0 && self.t_spin == TSpin::None
+    }
+
+    /// Tetrises and spins that clear lines, which keep a back-to-back chain going
+    pub fn is_difficult(&self) -> bool {
+        self.lines >= 4 || (self.lines > 0 && self.t_spin != TSpin::None)
+    }
+}
+
+impl fmt::Display for LineClear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Pieces bigger than tetrominoes can clear more than 4 lines at once
+        let lines = match self.lines {
+            0 => String::new(),
+            1 => String::from("SINGLE"),
+            2 => String::from("DOUBLE"),
+            3 => String::from("TRIPLE"),
+            4 => String::from("TETRIS"),
+            5 => String::from("PENTRIS"),
+            lines => format!("{} LINES", lines),
+        };
+        let spin = match self.t_spin {
+            TSpin::None => "",
+            TSpin::Mini => "MINI T-SPIN ",
+            TSpin::Full => "T-SPIN ",
+        };
+        let perfect_clear = if self.perfect_clear {
+            " PERFECT CLEAR"
+        } else {
+            ""
+        };
+        // A spin that clears nothing is just "T-SPIN"
+        write!(
+            f,
+            "{}",
+            format!("{}{}{}", spin, lines, perfect_clear).trim()
+        )
+    }
+}
+
+/// Checks a T that was just rotated into place against the 3-corner rule.
+/// At least 3 of the 4 cells diagonal to its center must be filled (walls and floor count),
+/// and unless `system` counts the kick it turned `direction` with as a full spin,
+/// both corners on the side it points to must be filled for a full T-spin rather than a mini one
+pub fn t_spin(
+    tetromino: &Tetromino,
+    (direction, kick): (RotationDirection, usize),
+    system: &dyn RotationSystem,
+    playfield: &Playfield,
+    dim: &Dimensions,
+) -> TSpin {
+    if tetromino.piece().tetromino() != Some(GuidelineTetromino::T) {
+        return TSpin::None;
+    }
+
+    let center = tetromino.coords()[0];
+    let (cx, cy) = (center.x as i32, center.y as i32);
+    let filled = |dx: i32, dy: i32| {
+        let (x, y) = (cx + dx, cy + dy);
+        x < 0 || x >= *dim.w() as i32 || y < 0 || playfield.is_occupied(x, y)
+    };
+
+    // Corners on the side the T points to, in order of rotation state (spawn, right, 180, left)
+    let front = match tetromino.rotation_state().rn() {
+        0 => [(-1, 1), (1, 1)],
+        1 => [(1, 1), (1, -1)],
+        2 => [(-1, -1), (1, -1)],
+        _ => [(-1, 1), (-1, -1)],
+    };
+    let corners = [(-1, 1), (1, 1), (-1, -1), (1, -1)]
+        .iter()
+        .filter(|&&(dx, dy)| filled(dx, dy))
+        .count();
+
+    let front_filled = front.iter().all(|&(dx, dy)| filled(dx, dy));
+
+    if corners < 3 {
+        TSpin::None
+    } else if front_filled || system.full_spin_kick(direction, kick) {
+        TSpin::Full
+    } else {
+        TSpin::Mini
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::rotations::srs::{Srs, SrsPlus};
+    use super::*;
+    use crate::tetromino::{coord::Coord, piece_set::PieceSet};
+
+    // Turning in place
+    const IN_PLACE: (RotationDirection, usize) = (RotationDirection::Clockwise, 0);
+
+    fn block(playfield: &mut Playfield, x: u32, y: u32) {
+        playfield.lock(&Tetromino::spawn_tetromino(
+            vec![Coord::new(x, y)],
+            Coord::new(x, y),
+            PieceSet::standard().find("I").unwrap().clone(),
+        ));
+    }
+
+    /// T pointing down with its center at (1, 1)
+    fn t_pointing_down() -> Tetromino {
+        let mut t = Tetromino::spawn_tetromino(
+            vec![
+                Coord::new(1, 1),
+                Coord::new(0, 1),
+                Coord::new(2, 1),
+                Coord::new(1, 0),
+            ],
+            Coord::new(1, 1),
+            PieceSet::standard().find("T").unwrap().clone(),
+        );
+        t.rotation_state_mut().increment(2);
+        t
+    }
+
+    #[test]
+    fn test_t_spin_double_slot() {
+        let dim = Dimensions::new(4, 4, 4);
+        let mut playfield = Playfield::new(&dim);
+        // Both bottom corners filled plus the overhang
+        block(&mut playfield, 0, 0);
+        block(&mut playfield, 2, 0);
+        block(&mut playfield, 0, 2);
+        assert_eq!(
+            t_spin(&t_pointing_down(), IN_PLACE, &Srs, &playfield, &dim),
+            TSpin::Full
+        );
+    }
+
+    #[test]
+    fn test_mini_unless_last_kick() {
+        let dim = Dimensions::new(4, 4, 4);
+        let mut playfield = Playfield::new(&dim);
+        // Only one of the corners the T points to is filled
+        block(&mut playfield, 0, 0);
+        block(&mut playfield, 0, 2);
+        block(&mut playfield, 2, 2);
+        let spin = |rotation, system: &dyn RotationSystem| {
+            t_spin(&t_pointing_down(), rotation, system, &playfield, &dim)
+        };
+        assert_eq!(spin((RotationDirection::Clockwise, 1), &Srs), TSpin::Mini);
+        // The last quarter turn kick is a full spin however the corners are
+        assert_eq!(spin((RotationDirection::Clockwise, 4), &Srs), TSpin::Full);
+        assert_eq!(
+            spin((RotationDirection::Clockwise, 4), &SrsPlus),
+            TSpin::Full
+        );
+        // But the SRS+ 180 kick of the same index is just a shift
+        assert_eq!(spin((RotationDirection::Half, 4), &SrsPlus), TSpin::Mini);
+    }
+
+    #[test]
+    fn test_two_corners_is_no_spin() {
+        let dim = Dimensions::new(4, 4, 4);
+        let mut playfield = Playfield::new(&dim);
+        block(&mut playfield, 0, 0);
+        block(&mut playfield, 2, 0);
+        assert_eq!(
+            t_spin(&t_pointing_down(), IN_PLACE, &Srs, &playfield, &dim),
+            TSpin::None
+        );
+    }
+}
@@ -1,32 +1,53 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
 use thomas::graphics::frontend::color::Color;
 
-use crate::tetromino::tetromino_type::TetrominoType;
+use super::playfield::Cell;
+use crate::tetromino::piece_set::{Piece, PieceSet};
 
+/// Colors everything is drawn in, written as hex codes in the settings file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ColorPalette {
+    #[serde(with = "hex")]
     line: Color,
+    #[serde(with = "hex")]
     grid: Color,
-    j: Color,
-    l: Color,
-    s: Color,
-    t: Color,
-    z: Color,
-    i: Color,
-    o: Color,
+    // Tetrominos that can't be used right now, e.g. a held tetromino after holding
+    #[serde(with = "hex")]
+    locked: Color,
+    // Garbage rows, and the meter of garbage about to rise
+    #[serde(with = "hex")]
+    garbage: Color,
+    #[serde(with = "hex")]
+    attack: Color,
+    // Colors for pieces by name, over the ones their piece set gives them
+    #[serde(with = "hex::by_name", skip_serializing_if = "BTreeMap::is_empty")]
+    pieces: BTreeMap<String, Color>,
 }
 
 impl ColorPalette {
-    pub fn color_for(&self, tetrimino_type: TetrominoType) -> Color {
-        match tetrimino_type {
-            TetrominoType::I => self.i,
-            TetrominoType::J => self.j,
-            TetrominoType::L => self.l,
-            TetrominoType::O => self.o,
-            TetrominoType::S => self.s,
-            TetrominoType::T => self.t,
-            TetrominoType::Z => self.z,
+    pub fn color_for(&self, piece: &Piece) -> Color {
+        self.pieces
+            .get(piece.name())
+            .copied()
+            .unwrap_or_else(|| piece.color())
+    }
+
+    /// Colors a locked cell, `pieces` being the set whatever locked there came from
+    pub fn color_for_cell(&self, cell: Cell, pieces: &PieceSet) -> Color {
+        match cell {
+            Cell::Tetromino(tetromino_type) => self.color_for(pieces.piece(tetromino_type)),
+            Cell::Garbage => self.garbage,
         }
     }
 
+    /// Get the names of the pieces the color palette colors itself
+    pub fn piece_names(&self) -> impl Iterator<Item = &str> {
+        self.pieces.keys().map(String::as_str)
+    }
+
     /// Get a color palette's line color.
     pub fn line(&self) -> Color {
         self.line
@@ -37,6 +58,15 @@ impl ColorPalette {
         self.grid
     }
 
+    /// Get a color palette's locked color.
+    pub fn locked(&self) -> Color {
+        self.locked
+    }
+
+    /// Get a color palette's incoming garbage color.
+    pub fn attack(&self) -> Color {
+        self.attack
+    }
 }
 
 impl Default for ColorPalette {
@@ -44,13 +74,77 @@ impl Default for ColorPalette {
         ColorPalette {
             grid: Color::from_hex("211A1E").unwrap(),
             line: Color::from_hex("3A5683").unwrap(),
-            j: Color::from_hex("5BC0EB").unwrap(),
-            l: Color::from_hex("FDE74C").unwrap(),
-            s: Color::from_hex("9BC53D").unwrap(),
-            t: Color::from_hex("C3423F").unwrap(),
-            z: Color::from_hex("4C6085").unwrap(),
-            i: Color::from_hex("34344A").unwrap(),
-            o: Color::from_hex("D4BEBE").unwrap(),
+            locked: Color::from_hex("5C5C5C").unwrap(),
+            garbage: Color::from_hex("8A8A8A").unwrap(),
+            attack: Color::from_hex("E4572E").unwrap(),
+            pieces: BTreeMap::new(),
+        }
+    }
+}
+
+/// Colors as `RRGGBB` hex codes, or `RRGGBBAA` for colors that aren't opaque
+pub(crate) mod hex {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use thomas::graphics::frontend::color::Color;
+
+    // Alpha `Color::from_hex` gives colors without one
+    const OPAQUE: u32 = 256;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hex = format!("{:02X}{:02X}{:02X}", color.r, color.g, color.b);
+        if color.a != OPAQUE {
+            hex.push_str(&format!("{:02X}", color.a));
+        }
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        let digits = hex.trim_start_matches('#');
+        // Anything but ascii would trip up the length check
+        if !digits.is_ascii() {
+            return Err(de::Error::custom(format!("{} is not a hex color", hex)));
+        }
+        Color::from_hex(digits)
+            .map_err(|e| de::Error::custom(format!("{} is not a hex color: {}", hex, e)))
+    }
+
+    /// Colors by name, e.g. for every piece
+    pub mod by_name {
+        use std::collections::BTreeMap;
+
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use thomas::graphics::frontend::color::Color;
+
+        struct Hex(Color);
+
+        impl Serialize for Hex {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                super::serialize(&self.0, serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Hex {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                super::deserialize(deserializer).map(Hex)
+            }
+        }
+
+        pub fn serialize<S: Serializer>(
+            colors: &BTreeMap<String, Color>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_map(colors.iter().map(|(name, &color)| (name, Hex(color))))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<BTreeMap<String, Color>, D::Error> {
+            let colors = BTreeMap::<String, Hex>::deserialize(deserializer)?;
+            Ok(colors
+                .into_iter()
+                .map(|(name, color)| (name, color.0))
+                .collect())
         }
     }
 }
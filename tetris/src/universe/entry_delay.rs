@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use super::rotations::rotation_direction::RotationDirection;
+use super::simulation::GameAction;
+
+// Presets are timed in frames of a 60 Hz game, and scaled to whatever the game ticks at
+const PRESET_FRAME_RATE: u32 = 60;
+
+/// Everything that happens between one tetromino locking and the next one spawning, in ticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EntryDelay {
+    /// Entry delay (ARE) after a lock that doesn't clear any lines
+    pub are: u32,
+    /// Entry delay after a line clear, once the cleared rows are gone
+    pub line_clear_are: u32,
+    /// How long cleared rows are shown before everything above them falls
+    pub line_clear_delay: u32,
+    /// Whether turning or holding during the delays turns or holds the next tetromino as it spawns (IRS/IHS)
+    pub buffer: bool,
+}
+
+impl Default for EntryDelay {
+    fn default() -> Self {
+        TimingPreset::default().entry_delay(PRESET_FRAME_RATE)
+    }
+}
+
+/// Timing of the games other rule sets come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimingPreset {
+    /// Guideline games, the next tetromino spawns straight away and only line clears pause
+    #[default]
+    Modern,
+    /// NES-like, with a short pause after every lock and no initial rotation
+    Classic,
+    /// Like the first Tetris The Grand Master, long pauses with initial rotation and hold
+    Tgm,
+}
+
+impl TimingPreset {
+    pub const ALL: [TimingPreset; 3] = [
+        TimingPreset::Modern,
+        TimingPreset::Classic,
+        TimingPreset::Tgm,
+    ];
+
+    /// Short name used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimingPreset::Modern => "modern",
+            TimingPreset::Classic => "classic",
+            TimingPreset::Tgm => "tgm",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    /// The preset's delays for a game running at `ticks_per_second`
+    pub fn entry_delay(&self, ticks_per_second: u32) -> EntryDelay {
+        let (are, line_clear_are, line_clear_delay, buffer) = match self {
+            TimingPreset::Modern => (0, 0, 18, false),
+            TimingPreset::Classic => (10, 10, 18, false),
+            TimingPreset::Tgm => (30, 30, 41, true),
+        };
+        let ticks = |frames: u32| frames * ticks_per_second / PRESET_FRAME_RATE;
+        EntryDelay {
+            are: ticks(are),
+            line_clear_are: ticks(line_clear_are),
+            line_clear_delay: ticks(line_clear_delay),
+            buffer,
+        }
+    }
+
+    /// The preset timed like `entry_delay` at `ticks_per_second`, if any
+    pub fn find(entry_delay: &EntryDelay, ticks_per_second: u32) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.entry_delay(ticks_per_second) == *entry_delay)
+    }
+}
+
+/// Turns and holds pressed between tetrominos, carried out as the next one spawns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct InitialActions {
+    /// Initial hold (IHS), swapping the tetromino for the held one before it's seen
+    pub(super) hold: bool,
+    /// Initial rotation (IRS), the last way it was turned
+    pub(super) rotation: Option<RotationDirection>,
+}
+
+impl InitialActions {
+    /// Whether `action` is a turn or a hold, the only actions that can be kept for the next tetromino
+    pub(super) fn keeps(action: GameAction) -> bool {
+        matches!(
+            action,
+            GameAction::Hold | GameAction::RotateCW | GameAction::RotateCCW | GameAction::Rotate180
+        )
+    }
+
+    /// Keeps a turn or a hold for the next tetromino, anything else is dropped
+    pub(super) fn buffer(&mut self, action: GameAction) {
+        match action {
+            GameAction::Hold => self.hold = true,
+            GameAction::RotateCW => self.rotation = Some(RotationDirection::Clockwise),
+            GameAction::RotateCCW => self.rotation = Some(RotationDirection::CounterClockwise),
+            GameAction::Rotate180 => self.rotation = Some(RotationDirection::Half),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_presets_scale_with_ticks() {
+        assert_eq!(TimingPreset::Modern.entry_delay(60), EntryDelay::default());
+
+        let tgm = TimingPreset::Tgm.entry_delay(120);
+        assert_eq!((tgm.are, tgm.line_clear_delay), (60, 82));
+        assert_eq!(TimingPreset::find(&tgm, 120), Some(TimingPreset::Tgm));
+        assert_eq!(TimingPreset::find(&tgm, 60), None);
+        assert_eq!(
+            TimingPreset::from_name("classic"),
+            Some(TimingPreset::Classic)
+        );
+    }
+
+    #[test]
+    fn test_buffers_only_turns_and_holds() {
+        let mut initial = InitialActions::default();
+        assert!(!InitialActions::keeps(GameAction::MoveLeft));
+        initial.buffer(GameAction::MoveLeft);
+        assert_eq!(initial, InitialActions::default());
+        // Only the last turn counts
+        initial.buffer(GameAction::RotateCW);
+        initial.buffer(GameAction::RotateCCW);
+        assert_eq!(initial.rotation, Some(RotationDirection::CounterClockwise));
+        assert!(!initial.hold);
+        initial.buffer(GameAction::Hold);
+        assert!(initial.hold);
+    }
+}
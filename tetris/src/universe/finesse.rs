@@ -0,0 +1,270 @@
+use std::collections::{HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use super::playfield::Playfield;
+use super::rotations::rotation_direction::RotationDirection;
+use super::rotations::{rotate, RotationSystem};
+use super::simulation::GameAction;
+use super::Dimensions;
+use crate::tetromino::Tetromino;
+
+// Presses beyond which a placement isn't worth searching for, no board is wide enough to need more
+const MAX_PRESSES: u32 = 8;
+
+/// What to do about finesse faults, i.e. pieces placed with more key presses than they needed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FinesseSettings {
+    /// Flash a warning next to the board on every fault
+    pub flash: bool,
+    /// Put the piece back at the top to try again, rather than locking it
+    pub practice: bool,
+}
+
+impl Default for FinesseSettings {
+    fn default() -> Self {
+        Self {
+            flash: true,
+            practice: false,
+        }
+    }
+}
+
+/// Key presses a piece took to place, against the fewest it could have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Judgement {
+    pub used: u32,
+    pub minimal: u32,
+}
+
+impl Judgement {
+    /// Whether the piece took more presses than it had to
+    pub fn is_fault(&self) -> bool {
+        self.used > self.minimal
+    }
+}
+
+/// Cells of a tetromino with its bottom row at 0, which is all that matters once it's dropped onto an empty board
+fn footprint(tetromino: &Tetromino) -> Vec<[i32; 2]> {
+    let bottom = tetromino.lowest_y() as i32;
+    let mut cells: Vec<[i32; 2]> = tetromino
+        .coords()
+        .iter()
+        .map(|c| [c.x as i32, c.y as i32 - bottom])
+        .collect();
+    cells.sort();
+    cells
+}
+
+/// Fewest key presses that take `spawned` to anywhere it drops into the same cells as `placed`, on an empty board.
+/// A press is a tap or a held move to the wall, or a turn. Turning 180 degrees only counts if `half_turns` is on.
+/// `None` if it can't be done in a handful of presses, e.g. the piece was tucked under something
+pub fn minimal_presses(
+    spawned: &Tetromino,
+    placed: &Tetromino,
+    dim: &Dimensions,
+    system: &dyn RotationSystem,
+    half_turns: bool,
+) -> Option<u32> {
+    let playfield = Playfield::new(dim);
+    let target = footprint(placed);
+    let mut start = spawned.clone();
+    // Away from the ceiling, so that turning is never blocked by it
+    start.move_by([0, -(*dim.h() as i32 / 2)]);
+
+    let mut turns = vec![
+        RotationDirection::Clockwise,
+        RotationDirection::CounterClockwise,
+    ];
+    if half_turns {
+        turns.push(RotationDirection::Half);
+    }
+    let key = |t: &Tetromino| {
+        let mut cells: Vec<[i32; 2]> = t
+            .coords()
+            .iter()
+            .map(|c| [c.x as i32, c.y as i32])
+            .collect();
+        cells.sort();
+        cells
+    };
+    let mut seen = HashSet::from([key(&start)]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((tetromino, presses)) = queue.pop_front() {
+        if footprint(&tetromino) == target {
+            return Some(presses);
+        }
+        if presses == MAX_PRESSES {
+            continue;
+        }
+        let mut next = Vec::new();
+        for dx in [-1, 1] {
+            if tetromino.within_boundary([dx, 0], dim) {
+                let mut tapped = tetromino.clone();
+                tapped.move_by([dx, 0]);
+                let mut held = tapped.clone();
+                while held.within_boundary([dx, 0], dim) {
+                    held.move_by([dx, 0]);
+                }
+                next.push(tapped);
+                next.push(held);
+            }
+        }
+        for &direction in turns.iter() {
+            if let Some((turned, _)) = rotate(&tetromino, direction, &playfield, dim, system) {
+                next.push(turned);
+            }
+        }
+        for tetromino in next {
+            if seen.insert(key(&tetromino)) {
+                queue.push_back((tetromino, presses + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Keeps count of the key presses that went into the focused piece, to judge it by once it locks
+#[derive(Debug, Clone, Default)]
+pub(super) struct FinesseTracker {
+    // Moves and turns pressed since the piece spawned
+    presses: u32,
+    // Whether presses were reported for the piece at all, which they aren't when watching a replay
+    keyed: bool,
+    // Soft dropping opens up placements an empty board doesn't have, so those pieces aren't judged
+    soft_dropped: bool,
+}
+
+impl FinesseTracker {
+    /// Counts key presses towards the focused piece
+    pub(super) fn press(&mut self, presses: &[GameAction]) {
+        self.keyed = true;
+        for action in presses {
+            match action {
+                GameAction::MoveLeft
+                | GameAction::MoveRight
+                | GameAction::RotateCW
+                | GameAction::RotateCCW
+                | GameAction::Rotate180 => self.presses += 1,
+                GameAction::SoftDrop => self.soft_dropped = true,
+                _ => {}
+            }
+        }
+    }
+
+    /// Starts counting over for a piece that just spawned
+    pub(super) fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Judges `placed` against the fewest presses that would have got it there from `spawned`, if it can be judged
+    pub(super) fn judge(
+        &self,
+        spawned: &Tetromino,
+        placed: &Tetromino,
+        dim: &Dimensions,
+        system: &dyn RotationSystem,
+        half_turns: bool,
+    ) -> Option<Judgement> {
+        if !self.keyed || self.soft_dropped {
+            return None;
+        }
+        let minimal = minimal_presses(spawned, placed, dim, system, half_turns)?;
+        Some(Judgement {
+            used: self.presses,
+            minimal,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tetromino::piece_set::PieceSet;
+    use crate::universe::rotations::srs::Srs;
+
+    fn spawned(name: &str, dim: &Dimensions) -> Tetromino {
+        let pieces = PieceSet::standard();
+        let piece = pieces.find(name).unwrap();
+        pieces.spawn(*piece.tetromino_type(), dim)
+    }
+
+    /// `name` moved `dx` columns and turned clockwise `turns` times from where it spawns
+    fn placed(name: &str, dx: i32, turns: u32, dim: &Dimensions) -> Tetromino {
+        let playfield = Playfield::new(dim);
+        let mut tetromino = spawned(name, dim);
+        tetromino.move_by([0, -5]);
+        for _ in 0..turns {
+            tetromino = rotate(
+                &tetromino,
+                RotationDirection::Clockwise,
+                &playfield,
+                dim,
+                &Srs,
+            )
+            .unwrap()
+            .0;
+        }
+        tetromino.move_by([dx, 0]);
+        tetromino
+    }
+
+    #[test]
+    fn test_minimal_presses_match_the_finesse_tables() {
+        let dim = Dimensions::new(10, 20, 4);
+        let minimal = |name: &str, dx: i32, turns: u32, half_turns: bool| {
+            minimal_presses(
+                &spawned(name, &dim),
+                &placed(name, dx, turns, &dim),
+                &dim,
+                &Srs,
+                half_turns,
+            )
+        };
+        // Where it spawns, a tap over, and a hold to the wall
+        assert_eq!(minimal("T", 0, 0, false), Some(0));
+        assert_eq!(minimal("T", -1, 0, false), Some(1));
+        assert_eq!(minimal("T", -3, 0, false), Some(1));
+        // Held to the wall and tapped back beats tapping twice
+        assert_eq!(minimal("T", -2, 0, false), Some(2));
+        assert_eq!(minimal("O", 3, 0, false), Some(2));
+        // Flat T upside down takes two turns, or one with 180s
+        assert_eq!(minimal("T", 0, 2, false), Some(2));
+        assert_eq!(minimal("T", 0, 2, true), Some(1));
+        // Vertical S and Z look the same either way they're turned
+        assert_eq!(minimal("Z", 0, 3, false), Some(1));
+    }
+
+    #[test]
+    fn test_judges_only_keyed_pieces() {
+        let dim = Dimensions::new(10, 20, 4);
+        let (spawn, target) = (spawned("L", &dim), placed("L", -1, 0, &dim));
+        let mut tracker = FinesseTracker::default();
+        // Watching a replay, nobody reported any presses
+        assert_eq!(tracker.judge(&spawn, &target, &dim, &Srs, false), None);
+
+        tracker.press(&[
+            GameAction::MoveLeft,
+            GameAction::MoveLeft,
+            GameAction::MoveRight,
+        ]);
+        let judgement = tracker.judge(&spawn, &target, &dim, &Srs, false).unwrap();
+        assert_eq!(
+            judgement,
+            Judgement {
+                used: 3,
+                minimal: 1
+            }
+        );
+        assert!(judgement.is_fault());
+        tracker.reset();
+        tracker.press(&[GameAction::MoveLeft]);
+        assert!(!tracker
+            .judge(&spawn, &target, &dim, &Srs, false)
+            .unwrap()
+            .is_fault());
+        tracker.press(&[GameAction::SoftDrop, GameAction::RotateCW]);
+        assert_eq!(tracker.judge(&spawn, &target, &dim, &Srs, false), None);
+    }
+}
@@ -1,30 +1,95 @@
-use thomas::context::Context;
+use super::clear::LineClear;
+use super::entry_delay::EntryDelay;
+use super::mode::GameMode;
+use super::scoring::{ScoredLock, ScoringPreset, ScoringRule};
 
-// Single, double, triple, tetris, based off of gameboy
-const SCORE: [u32; 4] = [40, 100, 300, 1200];
 // Speeds for levels 3-20, based off of gameboy
 const FRAMES_PER_FALL: [u32; 21] = [
     53, 49, 45, 41, 37, 33, 28, 22, 17, 11, 10, 9, 8, 7, 6, 6, 5, 5, 4, 4, 3,
 ];
+/// Ticks a tetromino takes to fall a row at the first level, which soft dropping speeds up from
+pub const FIRST_LEVEL_FALL: u32 = FRAMES_PER_FALL[0];
 const LVL_CAP: u32 = 20;
 const LINES_PER_LEVEL: u32 = 10;
+// Seconds counted down before the first tetromino spawns
+const COUNTDOWN_SECONDS: u32 = 3;
+// Seconds "GAME OVER" or the finish is shown before the results
+const GAME_OVER_SECONDS: u32 = 2;
+
+/// Where a game is at. Every state but `Playing` freezes the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    /// "3, 2, 1, GO" before the first tetromino spawns, holding the ticks left
+    Countdown(u32),
+    Playing,
+    /// Frozen by the player, until resumed back into whatever it was paused from
+    Paused,
+    /// Cleared rows are shown before everything above them falls, holding the ticks left
+    LineClearDelay(u32),
+    /// Entry delay (ARE) before the next tetromino spawns, holding the ticks left
+    Entry(u32),
+    /// Topped out, holding the ticks left before the results show up
+    GameOver(u32),
+    /// Reached the mode's goal, holding the ticks left before the results show up
+    Finished(u32),
+    /// Final score, lines and level, until the game is restarted
+    Results,
+}
 
 pub struct Game {
     // Internal game tick
     ticks: u32,
-    // Game running
-    running: bool,
+    // Where the game is at
+    state: GameState,
+    // What resuming goes back to, since the delays between tetrominos can be paused too
+    paused_from: GameState,
+    // Ticks per second, which every delay is based off of
+    ticks_per_second: u32,
+    // Ticks spent playing, including the delays between tetrominos but excluding pauses and countdowns
+    elapsed: u32,
     // Score
     lines_cleared: u32,
     // level
     level: u32,
     // score
     score: u32,
+    // What the last scoring lock cleared
+    last_clear: Option<LineClear>,
+    // Line clearing locks in a row, minus the first one
+    combo: Option<u32>,
+    // Difficult clears in a row, minus the first one
+    back_to_back: Option<u32>,
+    // Decides what everything is worth
+    rule: Box<dyn ScoringRule>,
+    // What the game is played for
+    mode: GameMode,
+    // How long the board freezes for between tetrominos
+    entry_delay: EntryDelay,
 }
 impl Game {
-    /// Get a reference to the game's running.
-    pub fn running(&self) -> &bool {
-        &self.running
+    /// Get the game's state
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    /// Whether tetrominos can be moved and fall right now
+    pub fn running(&self) -> bool {
+        self.state == GameState::Playing
+    }
+
+    /// Get the number of ticks spent playing
+    pub fn elapsed(&self) -> u32 {
+        self.elapsed
+    }
+
+    /// Get the game's ticks per second
+    pub fn ticks_per_second(&self) -> u32 {
+        self.ticks_per_second
+    }
+
+    /// Get the # of lines cleared so far
+    pub fn lines_cleared(&self) -> u32 {
+        self.lines_cleared
     }
 
     /// Get a reference to the game's level.
@@ -36,56 +101,193 @@ impl Game {
     pub fn score(&self) -> &u32 {
         &self.score
     }
+
+    /// Get what the last scoring lock cleared
+    pub fn last_clear(&self) -> Option<LineClear> {
+        self.last_clear
+    }
+
+    /// Get the current combo, `None` if the last lock didn't clear anything
+    pub fn combo(&self) -> Option<u32> {
+        self.combo
+    }
+
+    /// Get the current back-to-back chain, `None` if the last clear wasn't difficult
+    pub fn back_to_back(&self) -> Option<u32> {
+        self.back_to_back
+    }
+
+    /// Get the game's mode
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Whether the mode's line goal or time limit has been reached
+    pub fn goal_reached(&self) -> bool {
+        self.mode
+            .line_goal()
+            .is_some_and(|goal| self.lines_cleared >= goal)
+            || self.time_left() == Some(0)
+    }
+
+    /// Ticks of play left before the mode's time limit, if it has one
+    pub fn time_left(&self) -> Option<u32> {
+        self.mode
+            .time_limit()
+            .map(|seconds| (seconds * self.ticks_per_second).saturating_sub(self.elapsed))
+    }
 }
 
 impl Game {
-    /// Sets running state to false
-    pub fn pause(&mut self) {
-        self.running = false;
+    /// Pauses a game in play, including between tetrominos, or resumes a paused one.
+    /// Returns whether the game is paused afterwards
+    pub fn toggle_pause(&mut self) -> bool {
+        match self.state {
+            GameState::Playing | GameState::LineClearDelay(_) | GameState::Entry(_) => {
+                self.paused_from = self.state;
+                self.state = GameState::Paused;
+            }
+            GameState::Paused => self.state = self.paused_from,
+            _ => {}
+        }
+        self.state == GameState::Paused
     }
-    /// Sets running state to true
-    /// TODO `p` button to pause
-    pub fn _resume(&mut self) {
-        self.running = true;
+
+    /// The stack reached the top, which ends the game
+    pub fn top_out(&mut self) {
+        self.state = GameState::GameOver(self.ticks_per_second * GAME_OVER_SECONDS);
+    }
+
+    /// Ends the game as won, e.g. because the opponent topped out
+    pub fn finish(&mut self) {
+        self.state = GameState::Finished(self.ticks_per_second * GAME_OVER_SECONDS);
     }
 
-    /// Updates score, # of lines cleared, and level
-    pub fn update(&mut self, lines_cleared: u32, ctx: &mut Context) {
+    /// Ends the game on the mode's goal, if it's been reached during play
+    fn check_goal(&mut self) {
+        let playing = matches!(
+            self.state,
+            GameState::Playing | GameState::LineClearDelay(_) | GameState::Entry(_)
+        );
+        if playing && self.goal_reached() {
+            self.finish();
+        }
+    }
+
+    /// Updates score, # of lines cleared, and level after a lock.
+    /// Returns whether the level went up
+    pub fn update(&mut self, clear: LineClear) -> bool {
+        // Any lock that doesn't clear a line breaks the combo
+        self.combo = match (clear.lines, self.combo) {
+            (0, _) => None,
+            (_, combo) => Some(combo.map_or(0, |c| c + 1)),
+        };
+        // Plain locks that don't clear anything aren't worth anything
+        if clear.is_empty() {
+            return false;
+        }
+        // Easy line clears break the back-to-back chain, spins without lines neither extend nor break it
+        let back_to_back = clear.is_difficult() && self.back_to_back.is_some();
+        if clear.is_difficult() {
+            self.back_to_back = Some(self.back_to_back.map_or(0, |b2b| b2b + 1));
+        } else if clear.lines > 0 {
+            self.back_to_back = None;
+        }
         // First update score
-        self.update_score(lines_cleared);
+        self.score += self.rule.lock(&ScoredLock {
+            clear,
+            level: self.level,
+            combo: self.combo,
+            back_to_back,
+        });
         // Next update lines cleared
-        self.lines_cleared += lines_cleared;
-        // Next update levels, based on lines cleared
-        self.update_level(ctx);
+        self.lines_cleared += clear.lines;
+        // Next update levels, based on lines cleared, if the mode speeds up at all
+        let leveled_up = self.mode.levels_up() && self.update_level();
+        self.last_clear = Some(clear);
+        self.check_goal();
+        leveled_up
     }
 
-    /// Should never get called with 0 probably
-    fn update_score(&mut self, lines_cleared: u32) {
-        // Update score depending on lines cleared
-        self.score += SCORE[(lines_cleared - 1) as usize];
+    /// Freezes the board between a lock that cleared `lines` and the next tetromino spawning, if the game's still in play.
+    /// Cleared rows are shown for the line clear delay first, then the entry delay runs out.
+    /// Returns whether the next tetromino has to wait, rather than spawning straight away
+    pub fn delay_entry(&mut self, lines: u32) -> bool {
+        if self.state != GameState::Playing {
+            return false;
+        }
+        let delay = self.entry_delay;
+        self.state = match lines {
+            0 if delay.are > 0 => GameState::Entry(delay.are),
+            0 => GameState::Playing,
+            _ if delay.line_clear_delay > 0 => GameState::LineClearDelay(delay.line_clear_delay),
+            _ if delay.line_clear_are > 0 => GameState::Entry(delay.line_clear_are),
+            _ => GameState::Playing,
+        };
+        self.state != GameState::Playing
     }
 
-    /// Changes level based on self. num of lines cleared
-    fn update_level(&mut self, ctx: &mut Context) {
+    /// Changes level based on self. num of lines cleared, returning whether it went up
+    fn update_level(&mut self) -> bool {
         // level goes up every 10 lines, capped at 20
         let prev = self.level;
         self.level =
             ((self.lines_cleared as f32 / LINES_PER_LEVEL as f32).floor() as u32).min(LVL_CAP);
-        if self.level > prev {
-            thomas::audio::play_once_vorbis(ctx, "lvl_up.ogg").expect("unable to level up");
-        }
+        self.level > prev
     }
 
     pub fn fast_move_down_score(&mut self) {
-        self.score += self.level + 1
+        self.score += self.rule.soft_drop(self.level)
     }
 
     pub fn hard_move_down_score(&mut self, lines: u32) {
-        self.score += (self.level + 2) * lines;
+        self.score += self.rule.hard_drop(self.level, lines);
     }
 
-    /// Increments tick by 1
+    /// Advances the game by a tick, moving on to the next state once the current one runs out
     pub fn tick(&mut self) {
+        // The clock keeps running while cleared rows are shown and the next tetromino waits to spawn
+        if matches!(
+            self.state,
+            GameState::Playing | GameState::LineClearDelay(_) | GameState::Entry(_)
+        ) {
+            self.elapsed += 1;
+        }
+        self.state = match self.state {
+            GameState::Countdown(0) | GameState::Entry(0) => GameState::Playing,
+            // The entry delay starts as the cleared rows go, this tick being the first of it
+            GameState::LineClearDelay(0) => match self.entry_delay.line_clear_are {
+                0 => GameState::Playing,
+                are => GameState::Entry(are - 1),
+            },
+            GameState::Countdown(ticks) => GameState::Countdown(ticks - 1),
+            GameState::LineClearDelay(ticks) => GameState::LineClearDelay(ticks - 1),
+            GameState::Entry(ticks) => GameState::Entry(ticks - 1),
+            GameState::GameOver(0) | GameState::Finished(0) => GameState::Results,
+            GameState::GameOver(ticks) => GameState::GameOver(ticks - 1),
+            GameState::Finished(ticks) => GameState::Finished(ticks - 1),
+            GameState::Playing => {
+                self.tick_fall();
+                GameState::Playing
+            }
+            state => state,
+        };
+        // Time limits run out during play
+        self.check_goal();
+    }
+
+    /// What the countdown shows right now, `None` once it's over
+    pub fn countdown_text(&self) -> Option<String> {
+        match self.state {
+            GameState::Countdown(ticks) => Some((ticks / self.ticks_per_second + 1).to_string()),
+            // "GO" stays up for the first second of play
+            _ if self.elapsed < self.ticks_per_second && self.running() => Some(String::from("GO")),
+            _ => None,
+        }
+    }
+
+    /// Increments the fall tick by 1
+    fn tick_fall(&mut self) {
         if self.ticks >= FRAMES_PER_FALL[self.level as usize] {
             self.reset_ticks();
         }
@@ -100,18 +302,160 @@ impl Game {
 
     /// Determines whether to fall tetrimino
     pub fn should_fall(&mut self) -> bool {
-        self.ticks % FRAMES_PER_FALL[self.level as usize] == 0
+        self.ticks
+            .is_multiple_of(FRAMES_PER_FALL[self.level as usize])
     }
 }
 
-impl Default for Game {
-    fn default() -> Self {
+impl Game {
+    /// Starts a fresh game of `mode` scored by `rule`, counting down before play starts
+    pub fn new(rule: Box<dyn ScoringRule>, mode: GameMode, ticks_per_second: u32) -> Self {
+        let ticks_per_second = ticks_per_second.max(1);
         Game {
             ticks: 0,
-            running: true,
+            state: GameState::Countdown(ticks_per_second * COUNTDOWN_SECONDS - 1),
+            paused_from: GameState::Playing,
+            ticks_per_second,
+            elapsed: 0,
             lines_cleared: 0,
             level: 0,
             score: 0,
+            last_clear: None,
+            combo: None,
+            back_to_back: None,
+            rule,
+            mode,
+            entry_delay: EntryDelay::default(),
+        }
+    }
+
+    /// Freezes the board for `entry_delay` between tetrominos, rather than the default
+    pub fn with_entry_delay(mut self, entry_delay: EntryDelay) -> Self {
+        self.entry_delay = entry_delay;
+        self
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(ScoringPreset::default().rule(), GameMode::default(), 60)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::clear::TSpin;
+    use super::*;
+
+    const TETRIS: LineClear = LineClear {
+        lines: 4,
+        t_spin: TSpin::None,
+        perfect_clear: false,
+    };
+
+    fn run(game: &mut Game, ticks: u32) {
+        for _ in 0..ticks {
+            game.tick();
         }
     }
+
+    #[test]
+    fn test_countdown_then_play() {
+        let mut game = Game::new(ScoringPreset::default().rule(), GameMode::default(), 10);
+        assert_eq!(game.countdown_text().as_deref(), Some("3"));
+        run(&mut game, 10);
+        assert_eq!(game.countdown_text().as_deref(), Some("2"));
+        run(&mut game, 20);
+        assert!(game.running());
+        assert_eq!(game.countdown_text().as_deref(), Some("GO"));
+        run(&mut game, 10);
+        assert_eq!(game.countdown_text(), None);
+    }
+
+    #[test]
+    fn test_pause_and_top_out() {
+        let mut game = Game::new(ScoringPreset::default().rule(), GameMode::default(), 10);
+        // Can't pause before the game's started
+        assert!(!game.toggle_pause());
+        run(&mut game, 30);
+        assert!(game.toggle_pause());
+        run(&mut game, 5);
+        assert_eq!(game.elapsed(), 0);
+        assert!(!game.toggle_pause());
+
+        game.top_out();
+        assert!(!game.toggle_pause());
+        run(&mut game, 21);
+        assert_eq!(game.state(), GameState::Results);
+    }
+
+    #[test]
+    fn test_modes_finish() {
+        let mut sprint = Game::new(ScoringPreset::default().rule(), GameMode::Sprint, 10);
+        run(&mut sprint, 30);
+        for _ in 0..9 {
+            sprint.update(TETRIS);
+            run(&mut sprint, 5);
+        }
+        assert!(sprint.running());
+        // Sprints stay at the first level's speed
+        assert_eq!(*sprint.level(), 0);
+        sprint.update(TETRIS);
+        assert!(matches!(sprint.state(), GameState::Finished(_)));
+
+        let mut ultra = Game::new(ScoringPreset::default().rule(), GameMode::Ultra, 10);
+        run(&mut ultra, 30 + 1199);
+        assert_eq!(ultra.time_left(), Some(1));
+        run(&mut ultra, 1);
+        assert!(ultra.goal_reached());
+        run(&mut ultra, 21);
+        assert_eq!(ultra.state(), GameState::Results);
+    }
+
+    #[test]
+    fn test_clock_runs_through_delays() {
+        let delay = EntryDelay {
+            are: 2,
+            line_clear_are: 3,
+            line_clear_delay: 18,
+            ..EntryDelay::default()
+        };
+        let mut game = Game::new(ScoringPreset::default().rule(), GameMode::Sprint, 10)
+            .with_entry_delay(delay);
+        // Four ticks into play after the countdown
+        run(&mut game, 34);
+        assert_eq!(game.elapsed(), 4);
+        game.update(TETRIS);
+        assert!(game.delay_entry(TETRIS.lines));
+        let mut delayed = 0;
+        while !game.running() {
+            game.tick();
+            delayed += 1;
+        }
+        // Every tick of showing the cleared rows and waiting to spawn counts
+        assert!(delayed > 18 + 3);
+        assert_eq!(game.elapsed(), 4 + delayed);
+    }
+
+    #[test]
+    fn test_pause_between_tetrominos() {
+        let delay = EntryDelay {
+            line_clear_delay: 5,
+            ..EntryDelay::default()
+        };
+        let mut game = Game::new(ScoringPreset::default().rule(), GameMode::Sprint, 10)
+            .with_entry_delay(delay);
+        run(&mut game, 30);
+        game.update(TETRIS);
+        game.delay_entry(TETRIS.lines);
+        run(&mut game, 2);
+        let during = game.state();
+        assert!(matches!(during, GameState::LineClearDelay(_)));
+        assert!(game.toggle_pause());
+        run(&mut game, 20);
+        assert_eq!(game.elapsed(), 2);
+        // Resuming picks the line clear back up where it was
+        assert!(!game.toggle_pause());
+        assert_eq!(game.state(), during);
+    }
 }
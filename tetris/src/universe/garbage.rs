@@ -0,0 +1,59 @@
+use super::clear::{LineClear, TSpin};
+
+// Garbage sent for clearing 0-4 lines without a spin
+const LINES_ATTACK: [u32; 5] = [0, 0, 1, 2, 4];
+// Extra garbage for each combo, indexed by the combo and capped at the last entry
+const COMBO_ATTACK: [u32; 11] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+// Extra garbage for leaving the board empty
+const PERFECT_CLEAR_ATTACK: u32 = 10;
+
+/// Lines of garbage a lock sends to the opponent, before any of it is cancelled.
+/// `back_to_back` is whether the lock continued a back-to-back chain
+pub fn attack(clear: &LineClear, combo: Option<u32>, back_to_back: bool) -> u32 {
+    if clear.lines == 0 {
+        return 0;
+    }
+    let base = match clear.t_spin {
+        TSpin::None => LINES_ATTACK[clear.lines.min(4) as usize],
+        // Mini T-spins send a line less than they clear
+        TSpin::Mini => clear.lines - 1,
+        TSpin::Full => clear.lines * 2,
+    };
+    let combo = combo.map_or(0, |c| {
+        COMBO_ATTACK[(c as usize).min(COMBO_ATTACK.len() - 1)]
+    });
+    let perfect_clear = if clear.perfect_clear {
+        PERFECT_CLEAR_ATTACK
+    } else {
+        0
+    };
+    base + combo + back_to_back as u32 + perfect_clear
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn clear(lines: u32, t_spin: TSpin) -> LineClear {
+        LineClear {
+            lines,
+            t_spin,
+            perfect_clear: false,
+        }
+    }
+
+    #[test]
+    fn test_attack_table() {
+        assert_eq!(attack(&clear(1, TSpin::None), Some(0), false), 0);
+        assert_eq!(attack(&clear(2, TSpin::None), Some(0), false), 1);
+        assert_eq!(attack(&clear(4, TSpin::None), Some(0), false), 4);
+        assert_eq!(attack(&clear(4, TSpin::None), Some(0), true), 5);
+        assert_eq!(attack(&clear(2, TSpin::Full), Some(0), false), 4);
+        assert_eq!(attack(&clear(1, TSpin::Mini), Some(0), false), 0);
+        // Spins without lines don't send anything
+        assert_eq!(attack(&clear(0, TSpin::Full), None, false), 0);
+        // Combos add up, up to a cap
+        assert_eq!(attack(&clear(1, TSpin::None), Some(4), false), 2);
+        assert_eq!(attack(&clear(1, TSpin::None), Some(40), false), 5);
+    }
+}
@@ -0,0 +1,753 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use thomas::context::Context;
+use thomas::winit::event::VirtualKeyCode;
+
+use super::entry_delay::{EntryDelay, TimingPreset};
+use super::game::GameState;
+use super::lock_delay::LockReset;
+use super::mode::{format_time, GameMode};
+use super::randomizer::RandomizerPreset;
+use super::rotations::RotationPreset;
+use super::scoring::ScoringPreset;
+use super::simulation::{Rules, Simulation};
+use super::Universe;
+use crate::files::{self, FileError};
+use crate::tetris_input::MenuControls;
+
+// Name of the high scores file under the game's data directory
+const SCORES_FILE: &str = "high-scores.json";
+// Entries kept per table
+const TABLE_SIZE: usize = 10;
+// Longest name that can be typed in
+const MAX_NAME: usize = 10;
+// Opens and closes the high score tables
+pub(super) const HIGH_SCORES_KEY: VirtualKeyCode = VirtualKeyCode::H;
+
+/// Something wrong with a high scores file, e.g. `tables[0].key.mode` not being a mode
+pub type HighScoresError = FileError<serde_json::Error>;
+
+/// A single result on a high score table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    /// Time spent playing, in milliseconds
+    pub duration: u64,
+    /// When the game was played, in seconds since the Unix epoch
+    pub date: u64,
+    /// Seed the game started from, which its replay would play back from
+    pub seed: u64,
+}
+
+impl Entry {
+    /// The result of the game `sim` just finished, played on `date`
+    pub fn of(sim: &Simulation, name: String, date: u64) -> Self {
+        let game = sim.game();
+        Self {
+            name,
+            score: *game.score(),
+            lines: game.lines_cleared(),
+            level: *game.level(),
+            duration: game.elapsed() as u64 * 1000 / game.ticks_per_second() as u64,
+            date,
+            seed: sim.seed(),
+        }
+    }
+}
+
+/// The rules a table's games were played under, everything that changes how well a game can go.
+/// Results only go up against others played under exactly the same ones
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableKey {
+    pub mode: GameMode,
+    pub scoring: ScoringPreset,
+    /// Built-in name or path of the piece set dealt
+    pub pieces: String,
+    /// Board size in cells, see `Dimensions`
+    pub width: u32,
+    pub height: u32,
+    pub buffer: u32,
+    pub rotation: RotationPreset,
+    pub randomizer: RandomizerPreset,
+    /// Ticks per second, which the delays are counted in
+    pub ticks: u32,
+    pub timing: EntryDelay,
+    /// Ticks a tetromino can sit on the stack before it locks
+    pub lock_delay: u32,
+    pub lock_reset: LockReset,
+    /// Upcoming tetrominos shown in the next queue
+    pub previews: usize,
+}
+
+impl TableKey {
+    /// The table results of games played under `rules` go on
+    pub fn of(rules: &Rules) -> Self {
+        Self {
+            mode: rules.mode,
+            scoring: rules.scoring,
+            pieces: rules.pieces.source().to_string(),
+            width: rules.width,
+            height: rules.height,
+            buffer: rules.buffer,
+            rotation: rules.rotation,
+            randomizer: rules.randomizer,
+            ticks: rules.ticks,
+            timing: rules.entry_delay,
+            lock_delay: rules.lock_delay,
+            lock_reset: rules.lock_reset,
+            previews: rules.previews,
+        }
+    }
+
+    /// Whether the table is for the same rules as `other` apart from the mode and scoring
+    fn same_rules(&self, other: &TableKey) -> bool {
+        *self
+            == TableKey {
+                mode: self.mode,
+                scoring: self.scoring,
+                ..other.clone()
+            }
+    }
+
+    /// Everything but the mode and scoring, e.g. `standard 10x20+4 srs 7-bag modern lock 30 move 15 5 previews 60 ticks`
+    fn rules_name(&self) -> String {
+        let timing = match TimingPreset::find(&self.timing, self.ticks) {
+            Some(preset) => preset.name().to_string(),
+            None => format!(
+                "are {}/{}/{}{}",
+                self.timing.are,
+                self.timing.line_clear_are,
+                self.timing.line_clear_delay,
+                if self.timing.buffer { " buffered" } else { "" }
+            ),
+        };
+        let lock_reset = match self.lock_reset {
+            LockReset::Move { max_resets } => format!("move {}", max_resets),
+            LockReset::Step => String::from("step"),
+            LockReset::None => String::from("no reset"),
+        };
+        format!(
+            "{} {}x{}+{} {} {} {} lock {} {} {} previews {} ticks",
+            self.pieces,
+            self.width,
+            self.height,
+            self.buffer,
+            self.rotation.name(),
+            self.randomizer.name(),
+            timing,
+            self.lock_delay,
+            lock_reset,
+            self.previews,
+            self.ticks
+        )
+    }
+}
+
+/// The best results of games played under the same rules, best first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Table {
+    pub key: TableKey,
+    pub entries: Vec<Entry>,
+}
+
+impl Table {
+    fn new(key: TableKey) -> Self {
+        Self {
+            key,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Whether `entry` counts at all. Sprints only count once the lines are all cleared
+    fn counts(&self, entry: &Entry) -> bool {
+        match (self.key.mode, self.key.mode.line_goal()) {
+            (GameMode::Sprint, Some(goal)) => entry.lines >= goal,
+            _ => true,
+        }
+    }
+
+    /// Which of two entries ranks higher: the fastest for a sprint, and the highest score for anything else
+    fn rank(&self, a: &Entry, b: &Entry) -> Ordering {
+        match self.key.mode {
+            GameMode::Sprint => a.duration.cmp(&b.duration),
+            _ => b.score.cmp(&a.score),
+        }
+    }
+
+    /// Where `entry` would place, if it makes the table at all. Ties go to whoever got there first
+    fn place(&self, entry: &Entry) -> Option<usize> {
+        if !self.counts(entry) {
+            return None;
+        }
+        let place = self
+            .entries
+            .iter()
+            .take_while(|other| self.rank(other, entry) != Ordering::Greater)
+            .count();
+        (place < TABLE_SIZE).then_some(place)
+    }
+
+    /// Puts `entry` in its place, returning where that is if it made the table
+    fn insert(&mut self, entry: Entry) -> Option<usize> {
+        let place = self.place(&entry)?;
+        self.entries.insert(place, entry);
+        self.entries.truncate(TABLE_SIZE);
+        Some(place)
+    }
+}
+
+/// High score tables for every set of rules that's been played, kept as JSON
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HighScores {
+    tables: Vec<Table>,
+    /// Name typed in for the last high score, offered again for the next one
+    last_name: String,
+}
+
+impl HighScores {
+    /// Where high scores are kept by default, in the platform's data directory
+    pub fn default_path() -> Option<PathBuf> {
+        files::data_dir().map(|dir| dir.join(SCORES_FILE))
+    }
+
+    /// Reads high scores from a file, starting from empty tables if there isn't one yet
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<Self, HighScoresError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(HighScores::default());
+        }
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Writes the high scores to a file, creating its directory if it has to
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), HighScoresError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Get the entries of a table, best first
+    pub fn table(&self, key: &TableKey) -> &[Entry] {
+        self.tables
+            .iter()
+            .find(|table| table.key == *key)
+            .map_or(&[], |table| &table.entries)
+    }
+
+    fn table_mut(&mut self, key: &TableKey) -> &mut Table {
+        let index = match self.tables.iter().position(|table| table.key == *key) {
+            Some(index) => index,
+            None => {
+                self.tables.push(Table::new(key.clone()));
+                self.tables.len() - 1
+            }
+        };
+        &mut self.tables[index]
+    }
+
+    /// Where `entry` would place on its table, if it makes it at all
+    pub fn place(&self, key: &TableKey, entry: &Entry) -> Option<usize> {
+        match self.tables.iter().find(|table| table.key == *key) {
+            Some(table) => table.place(entry),
+            None => Table::new(key.clone()).place(entry),
+        }
+    }
+
+    /// Adds `entry` to its table, returning where it placed if it made it
+    pub fn insert(&mut self, key: &TableKey, entry: Entry) -> Option<usize> {
+        self.table_mut(key).insert(entry)
+    }
+
+    /// Adds every entry of `other` that isn't already on the tables, e.g. from a teammate's export.
+    /// Entries only go on the table for the same rules. Returns how many made it
+    pub fn merge(&mut self, other: HighScores) -> usize {
+        let mut added = 0;
+        for table in other.tables {
+            let ours = self.table_mut(&table.key);
+            for entry in table.entries {
+                if !ours.entries.contains(&entry) && ours.insert(entry).is_some() {
+                    added += 1;
+                }
+            }
+        }
+        added
+    }
+}
+
+// Getters and setters
+impl HighScores {
+    /// Get the name typed in for the last high score
+    pub fn last_name(&self) -> &str {
+        &self.last_name
+    }
+}
+
+impl fmt::Display for HighScores {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}",
+            serde_json::to_string_pretty(self).map_err(|_| fmt::Error)?
+        )
+    }
+}
+
+impl FromStr for HighScores {
+    type Err = HighScoresError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut deserializer = serde_json::Deserializer::from_str(s);
+        let mut scores: HighScores = files::deserialize(&mut deserializer)?;
+        deserializer.end().map_err(HighScoresError::Syntax)?;
+        // Files written by hand, or merged together elsewhere, might not be in order
+        for table in scores.tables.iter_mut() {
+            let mut entries = std::mem::take(&mut table.entries);
+            entries.sort_by(|a, b| table.rank(a, b));
+            entries.retain(|entry| table.counts(entry));
+            entries.truncate(TABLE_SIZE);
+            table.entries = entries;
+        }
+        Ok(scores)
+    }
+}
+
+/// Formats seconds since the Unix epoch as `yyyy-mm-dd`, in UTC
+pub fn format_date(seconds: u64) -> String {
+    // Days since 0000-03-01, so that leap days fall at the end of the year
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Every table that can be browsed, in the order they're flipped through: one for each mode and scoring rule
+/// under the rules being played, then any others that have entries. Zen never ends, so it has none
+fn browsable(rules: &Rules, scores: &HighScores) -> Vec<TableKey> {
+    let current = TableKey::of(rules);
+    let mut keys: Vec<TableKey> = GameMode::ALL
+        .into_iter()
+        .filter(|mode| *mode != GameMode::Zen)
+        .flat_map(|mode| {
+            ScoringPreset::ALL.map(|scoring| TableKey {
+                mode,
+                scoring,
+                ..current.clone()
+            })
+        })
+        .collect();
+    // Grouped by rules, in the order they were first played
+    let mut others: Vec<&TableKey> = scores
+        .tables
+        .iter()
+        .map(|table| &table.key)
+        .filter(|key| !key.same_rules(&current) && key.mode != GameMode::Zen)
+        .collect();
+    let played = others.clone();
+    others.sort_by_key(|key| played.iter().position(|other| other.same_rules(key)));
+    keys.extend(others.into_iter().cloned());
+    keys
+}
+
+/// The character typed by a key, for the keys that can go in a name
+fn typed(key: VirtualKeyCode) -> Option<char> {
+    let name = format!("{:?}", key);
+    let name = name.strip_prefix("Key").unwrap_or(&name);
+    match key {
+        VirtualKeyCode::Space => Some(' '),
+        _ if name.len() == 1 => name.chars().next(),
+        _ => None,
+    }
+}
+
+/// High scores as the window keeps them: the tables, where they're written to, and whatever's being done with them
+pub(super) struct ScoreKeeper {
+    scores: HighScores,
+    path: PathBuf,
+    // Name being typed in for the result that just made a table, along with where it placed
+    naming: Option<(String, usize)>,
+    // Which of the browsable tables is open, if any
+    browsing: Option<usize>,
+    controls: MenuControls,
+    // Whether the session's result has had its chance at the tables yet
+    offered: bool,
+    // What happened to the last attempt at reading or writing the file
+    status: Option<String>,
+}
+
+impl ScoreKeeper {
+    /// Reads the tables from `path`. A file that can't be read is left alone, rather than written over
+    fn load(path: PathBuf) -> Option<Self> {
+        let scores = match HighScores::load_or_default(&path) {
+            Ok(scores) => scores,
+            Err(e) => {
+                eprintln!("Couldn't read high scores from {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        Some(Self {
+            scores,
+            path,
+            naming: None,
+            browsing: None,
+            controls: MenuControls::new(HIGH_SCORES_KEY),
+            offered: false,
+            status: None,
+        })
+    }
+
+    /// Get the name being typed in for a new high score, along with where it placed
+    pub(super) fn naming(&self) -> Option<(&str, usize)> {
+        self.naming
+            .as_ref()
+            .map(|(name, place)| (name.as_str(), *place))
+    }
+
+    /// Get which of the browsable tables is open
+    pub(super) fn browsing(&self) -> Option<usize> {
+        self.browsing
+    }
+
+    /// Whether the high score tables are taking the keyboard instead of the game
+    pub(super) fn busy(&self) -> bool {
+        self.naming.is_some() || self.browsing.is_some()
+    }
+}
+
+impl Universe {
+    /// Keeps high scores in `path`, prompting for a name whenever a result makes the tables
+    pub fn with_high_scores(mut self, path: PathBuf) -> Self {
+        self.high_scores = ScoreKeeper::load(path);
+        self
+    }
+
+    /// Opens the high score tables, on the one for the game being played
+    pub(super) fn open_high_scores(&mut self) {
+        let Some(keeper) = self.high_scores.as_mut() else {
+            return;
+        };
+        let current = TableKey::of(self.sim.rules());
+        let table = browsable(self.sim.rules(), &keeper.scores)
+            .iter()
+            .position(|key| *key == current)
+            .unwrap_or_default();
+        keeper.controls = MenuControls::new(HIGH_SCORES_KEY);
+        keeper.browsing = Some(table);
+    }
+
+    /// Offers the session's result to the tables once the results show up, and forgets about it on restarting
+    pub(super) fn offer_high_score(&mut self) {
+        let Some(keeper) = self.high_scores.as_mut() else {
+            return;
+        };
+        if self.sim.game().state() != GameState::Results {
+            keeper.offered = false;
+            return;
+        }
+        if keeper.offered {
+            return;
+        }
+        keeper.offered = true;
+        // Only results the player got themselves count
+        if self.ai.is_some() {
+            return;
+        }
+        let key = TableKey::of(self.sim.rules());
+        let entry = Entry::of(&self.sim, String::new(), 0);
+        if let Some(place) = keeper.scores.place(&key, &entry) {
+            // Whatever was typed last time is probably who's playing
+            keeper.naming = Some((keeper.scores.last_name().to_string(), place));
+        }
+    }
+
+    /// Reads the keyboard for the name being typed or the table being browsed
+    pub(super) fn tick_high_scores(&mut self, ctx: &mut Context) {
+        let Some(keeper) = self.high_scores.as_mut() else {
+            return;
+        };
+        if let Some((name, _)) = keeper.naming.as_mut() {
+            match ctx.keyboard.take_pressed() {
+                Some(VirtualKeyCode::Return) if !name.trim().is_empty() => self.record_high_score(),
+                Some(VirtualKeyCode::Back) => {
+                    name.pop();
+                }
+                Some(key) => {
+                    if let Some(c) = typed(key).filter(|_| name.chars().count() < MAX_NAME) {
+                        name.push(c);
+                    }
+                }
+                None => {}
+            }
+            return;
+        }
+
+        let Some(browsing) = keeper.browsing else {
+            return;
+        };
+        if keeper.controls.close(ctx) {
+            keeper.browsing = None;
+            return;
+        }
+        let tables = browsable(self.sim.rules(), &keeper.scores).len() as i32;
+        let change = keeper.controls.change(ctx) + keeper.controls.select(ctx);
+        keeper.browsing = Some((browsing as i32 + change).rem_euclid(tables) as usize);
+    }
+
+    /// Puts the session's result on its table under the name typed in, and writes the tables out
+    fn record_high_score(&mut self) {
+        let key = TableKey::of(self.sim.rules());
+        let Some(keeper) = self.high_scores.as_mut() else {
+            return;
+        };
+        let Some((name, _)) = keeper.naming.take() else {
+            return;
+        };
+        let name = name.trim().to_string();
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        keeper.scores.last_name = name.clone();
+        keeper.scores.insert(&key, Entry::of(&self.sim, name, date));
+        keeper.status = keeper
+            .scores
+            .save(&keeper.path)
+            .err()
+            .map(|e| format!("Couldn't save high scores: {}", e));
+        // Straight to the table it made
+        self.open_high_scores();
+    }
+
+    /// Renders the name being typed in for a new high score over the results
+    pub(super) fn render_naming(&self, ctx: &mut Context, name: &str, place: usize) {
+        self.render_centered(ctx, "NEW HIGH SCORE", 60.0, -150.0);
+        self.render_centered(ctx, &format!("#{}", place + 1), 40.0, -90.0);
+        self.render_centered(ctx, &format!("name: {}_", name), 30.0, -20.0);
+        self.render_centered(ctx, "Type a name, then press enter", 20.0, 40.0);
+    }
+
+    /// Renders the open high score table, best first
+    pub(super) fn render_high_scores(&self, ctx: &mut Context, keeper: &ScoreKeeper, table: usize) {
+        let key = &browsable(self.sim.rules(), &keeper.scores)[table];
+        self.render_centered(ctx, "HIGH SCORES", 50.0, -320.0);
+        self.render_centered(
+            ctx,
+            &format!("{} / {}", key.mode.name(), key.scoring.name()),
+            30.0,
+            -265.0,
+        );
+        self.render_centered(ctx, &key.rules_name(), 20.0, -232.0);
+        let entries = keeper.scores.table(key);
+        if entries.is_empty() {
+            self.render_centered(ctx, "Nothing yet", 24.0, -200.0);
+        }
+        for (i, entry) in entries.iter().enumerate() {
+            let headline = match key.mode {
+                GameMode::Sprint => format_time(entry.duration as u32, 1000),
+                _ => entry.score.to_string(),
+            };
+            self.render_centered(
+                ctx,
+                &format!(
+                    "{:>2}. {:<10} {:>9}  {} lines  lvl {}  {}",
+                    i + 1,
+                    entry.name,
+                    headline,
+                    entry.lines,
+                    entry.level,
+                    format_date(entry.date)
+                ),
+                22.0,
+                -200.0 + i as f32 * 30.0,
+            );
+        }
+        self.render_centered(ctx, "left/right: change table  \"h\": close", 20.0, 150.0);
+        if let Some(status) = &keeper.status {
+            self.render_centered(ctx, status, 20.0, 185.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(mode: GameMode, scoring: ScoringPreset) -> TableKey {
+        TableKey {
+            mode,
+            scoring,
+            ..TableKey::of(&Rules::default())
+        }
+    }
+
+    fn entry(name: &str, score: u32, duration: u64) -> Entry {
+        Entry {
+            name: name.to_string(),
+            score,
+            lines: 40,
+            level: 4,
+            duration,
+            date: 1_700_000_000,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn test_tables_keep_the_best() {
+        let mut scores = HighScores::default();
+        let marathon = key(GameMode::Marathon, ScoringPreset::Guideline);
+        for score in 0..15 {
+            scores.insert(&marathon, entry("A", score * 100, 0));
+        }
+        let table = scores.table(&marathon);
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert_eq!(table[0].score, 1400);
+        assert_eq!(table[TABLE_SIZE - 1].score, 500);
+        assert_eq!(scores.place(&marathon, &entry("B", 400, 0)), None);
+        // Ties go to whoever was there first
+        assert_eq!(scores.insert(&marathon, entry("B", 1400, 0)), Some(1));
+        // Other scoring rules have tables of their own
+        assert!(scores
+            .table(&key(GameMode::Marathon, ScoringPreset::Nes))
+            .is_empty());
+
+        // Sprints go by time, and only count once finished
+        let sprint = key(GameMode::Sprint, ScoringPreset::Guideline);
+        assert_eq!(scores.insert(&sprint, entry("A", 0, 90_000)), Some(0));
+        assert_eq!(scores.insert(&sprint, entry("B", 0, 80_000)), Some(0));
+        let unfinished = Entry {
+            lines: 39,
+            ..entry("C", 0, 1)
+        };
+        assert_eq!(scores.insert(&sprint, unfinished), None);
+    }
+
+    #[test]
+    fn test_tables_keep_rules_apart() {
+        let mut scores = HighScores::default();
+        let standard = key(GameMode::Sprint, ScoringPreset::Guideline);
+        let wide = TableKey {
+            width: 20,
+            ..standard.clone()
+        };
+        let tgm = TableKey {
+            timing: TimingPreset::Tgm.entry_delay(standard.ticks),
+            ..standard.clone()
+        };
+        // Every rule that changes how a game goes gets a table of its own
+        let others = [
+            TableKey {
+                randomizer: RandomizerPreset::Uniform,
+                ..standard.clone()
+            },
+            TableKey {
+                lock_delay: 15,
+                ..standard.clone()
+            },
+            TableKey {
+                lock_reset: LockReset::Step,
+                ..standard.clone()
+            },
+            TableKey {
+                previews: 1,
+                ..standard.clone()
+            },
+        ];
+        scores.insert(&wide, entry("A", 0, 20_000));
+        assert!(scores.table(&standard).is_empty());
+        assert_eq!(scores.place(&standard, &entry("B", 0, 90_000)), Some(0));
+        assert_eq!(scores.place(&tgm, &entry("B", 0, 90_000)), Some(0));
+
+        assert_eq!(
+            standard.rules_name(),
+            "standard 10x20+4 srs 7-bag modern lock 30 move 15 5 previews 60 ticks"
+        );
+        assert_eq!(
+            wide.rules_name(),
+            "standard 20x20+4 srs 7-bag modern lock 30 move 15 5 previews 60 ticks"
+        );
+
+        // The rules being played come first, then any others played before
+        let tables = browsable(&Rules::default(), &scores);
+        let modes = GameMode::ALL.len() - 1;
+        assert_eq!(tables.len(), modes * ScoringPreset::ALL.len() + 1);
+        assert!(tables.contains(&standard));
+        assert_eq!(tables.last(), Some(&wide));
+
+        let mut apart = HighScores::default();
+        for other in others.iter() {
+            apart.insert(other, entry("C", 0, 10_000));
+            assert_eq!(apart.table(other).len(), 1);
+        }
+        assert!(apart.table(&standard).is_empty());
+    }
+
+    #[test]
+    fn test_export_import_and_merge() {
+        let ultra = key(GameMode::Ultra, ScoringPreset::Nes);
+        let mut ours = HighScores::default();
+        ours.insert(&ultra, entry("A", 300, 0));
+        let mut theirs = HighScores::default();
+        theirs.insert(&ultra, entry("A", 300, 0));
+        theirs.insert(&ultra, entry("B", 500, 0));
+        // Played on a different board, so it goes on a table of its own
+        let small = TableKey {
+            height: 10,
+            ..ultra.clone()
+        };
+        theirs.insert(&small, entry("C", 900, 0));
+
+        let imported: HighScores = theirs.to_string().parse().unwrap();
+        assert_eq!(imported, theirs);
+        // The entry both sides have doesn't count twice
+        assert_eq!(ours.merge(imported), 2);
+        let names = |key: &TableKey| -> Vec<String> {
+            ours.table(key).iter().map(|e| e.name.clone()).collect()
+        };
+        assert_eq!(names(&ultra), ["B", "A"]);
+        assert_eq!(names(&small), ["C"]);
+
+        match r#"{"tables": [{"key": {"mode": "survival"}, "entries": []}]}"#.parse::<HighScores>()
+        {
+            Err(HighScoresError::Invalid { key, .. }) => assert_eq!(key, "tables[0].key.mode"),
+            other => panic!("expected an invalid key, got {:?}", other),
+        }
+        assert!(matches!(
+            "{".parse::<HighScores>(),
+            Err(HighScoresError::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_700_000_000), "2023-11-14");
+    }
+}
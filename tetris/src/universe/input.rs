@@ -1,51 +1,68 @@
-use thomas::context::Context;
-use thomas::winit::event::VirtualKeyCode;
-
-use crate::tetris_input::InputInterface;
-
-use super::Universe;
 use super::rotations::rotation_direction::RotationDirection;
-use super::tetromino::{Tetromino, MoveDirection};
+use super::simulation::{GameAction, Simulation};
+use super::tetromino::{MoveDirection, Tetromino};
 
-impl InputInterface for Universe {
-    fn receive_key(&mut self, ctx: &mut Context) {
-        for i in 0..self.tetromino_controls.get_queue().len() {
-            match self.tetromino_controls.get_queue()[i] {
-                VirtualKeyCode::Left => {
-                    let dx_dy = Tetromino::get_dxdy(MoveDirection::Left);
-                    if self.focused_tetromino.within_boundary(dx_dy, &self.dim)
-                        && !self
-                            .focused_tetromino
-                            .will_collide_diff(&self.stagnant_tetrominos, dx_dy)
-                    {
-                        self.focused_tetromino.move_by(dx_dy)
-                    }
-                }
-                VirtualKeyCode::Right => {
-                    let dx_dy = Tetromino::get_dxdy(MoveDirection::Right);
-                    if self.focused_tetromino.within_boundary(dx_dy, &self.dim)
-                        && !self
-                            .focused_tetromino
-                            .will_collide_diff(&self.stagnant_tetrominos, dx_dy)
-                    {
-                        self.focused_tetromino.move_by(dx_dy)
-                    }
+impl Simulation {
+    /// Carries out a single action
+    pub(super) fn apply(&mut self, action: GameAction) {
+        match action {
+            // Restarting is always an option, and starts a new countdown
+            GameAction::Restart => self.restart(),
+            GameAction::Pause => self.toggle_pause(),
+            // Left for the next lock to pick up, whenever that is
+            GameAction::Retry => self.retry = true,
+            // Turning and holding between tetrominos carries over to the next one (IRS/IHS)
+            _ if self.buffering() => self.buffer(action),
+            // Nothing else does anything unless the game is in play,
+            // which also drops whatever's left in the tick after a hard drop locks into a line clear
+            _ if !self.game().running() => {}
+            GameAction::MoveLeft => self.shift_focused(MoveDirection::Left),
+            GameAction::MoveRight => self.shift_focused(MoveDirection::Right),
+            GameAction::SoftDrop => {
+                // Soft drop doesn't lock, it leaves that to the lock delay
+                let fell = self.fall_focused();
+                if fell {
+                    self.game_mut().fast_move_down_score()
                 }
-                VirtualKeyCode::Down => {
-                    self.fall_focused(ctx);
-                    self.game.fast_move_down_score()
+            }
+            GameAction::RotateCCW => {
+                self.rotate_focused(RotationDirection::CounterClockwise);
+            }
+            GameAction::RotateCW => {
+                self.rotate_focused(RotationDirection::Clockwise);
+            }
+            GameAction::Rotate180 => {
+                self.rotate_focused(RotationDirection::Half);
+            }
+            GameAction::Hold => self.hold(),
+            GameAction::HardDrop => {
+                // Earlier actions this tick may have moved the tetromino since the ghost was placed
+                self.full_fall_focused();
+                let lines = self.focused_tetromino().coords()[0].y - self.ghost().coords()[0].y;
+                *self.focused_tetromino_mut() = self.ghost().clone();
+                // Dropping from a height after rotating isn't a spin
+                if lines > 0 {
+                    self.last_rotation = None;
                 }
-                VirtualKeyCode::Z => self.rotate_focused(RotationDirection::CounterClockwise),
-                VirtualKeyCode::C => self.rotate_focused(RotationDirection::Clockwise),
-                VirtualKeyCode::Space => {
-                    let lines = self.focused_tetromino.coords()[0].y - self.ghost.coords()[0].y;
-                    self.focused_tetromino = self.ghost.clone();
-                    self.fall_focused(ctx);
-                    self.game.hard_move_down_score(lines);
+                // Hard drop locks straight away, and only scores if it did
+                if self.generate_new_tetromino() {
+                    self.game_mut().hard_move_down_score(lines);
                 }
-                _ => {}
             }
         }
-        self.tetromino_controls.clear_queue();
+    }
+
+    /// Moves the focused tetromino a column over, if there's room
+    fn shift_focused(&mut self, direction: MoveDirection) {
+        let dx_dy = Tetromino::get_dxdy(direction);
+        if self.focused_tetromino().within_boundary(dx_dy, self.dim())
+            && !self
+                .focused_tetromino()
+                .will_collide_diff(self.playfield(), dx_dy)
+        {
+            self.focused_tetromino_mut().move_by(dx_dy);
+            self.last_rotation = None;
+            self.lock_delay.on_move();
+        }
     }
 }
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+
+/// What restarts the lock delay once a tetromino touches down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockReset {
+    /// Guideline "move reset". Successful moves and rotations restart the delay, up to `max_resets` times.
+    /// Falling below the lowest row reached so far gives every reset back
+    Move { max_resets: u32 },
+    /// Classic "step reset", only falling a row restarts the delay
+    Step,
+    /// The delay is never restarted, each tetromino only gets one delay's worth of time on the ground
+    None,
+}
+
+impl Default for LockReset {
+    fn default() -> Self {
+        Self::Move { max_resets: 15 }
+    }
+}
+
+/// Counts down how long the focused tetromino can sit on the stack before it locks
+pub struct LockDelay {
+    // Ticks a tetromino can spend on the ground
+    delay: u32,
+    reset: LockReset,
+    // Ticks spent on the ground so far
+    ticks: u32,
+    // Move resets used so far
+    resets: u32,
+    // Lowest row the tetromino has reached
+    lowest_y: u32,
+    grounded: bool,
+}
+
+impl LockDelay {
+    pub fn new(delay: u32, reset: LockReset) -> Self {
+        Self {
+            delay,
+            reset,
+            ticks: 0,
+            resets: 0,
+            lowest_y: u32::MAX,
+            grounded: false,
+        }
+    }
+
+    /// Starts over for a freshly spawned tetromino, whose lowest cell sits on `lowest_y`
+    pub fn on_spawn(&mut self, lowest_y: u32) {
+        self.ticks = 0;
+        self.resets = 0;
+        self.lowest_y = lowest_y;
+        self.grounded = false;
+    }
+
+    /// The tetromino fell a row, so that its lowest cell now sits on `lowest_y`
+    pub fn on_fall(&mut self, lowest_y: u32) {
+        match self.reset {
+            LockReset::Move { .. } => {
+                if lowest_y < self.lowest_y {
+                    self.ticks = 0;
+                    self.resets = 0;
+                }
+            }
+            LockReset::Step => self.ticks = 0,
+            LockReset::None => {}
+        }
+        self.lowest_y = self.lowest_y.min(lowest_y);
+    }
+
+    /// The tetromino moved or rotated successfully
+    pub fn on_move(&mut self) {
+        if let LockReset::Move { max_resets } = self.reset {
+            if self.grounded && self.resets < max_resets {
+                self.ticks = 0;
+                self.resets += 1;
+            }
+        }
+    }
+
+    /// Advances the delay by a tick, returning whether the tetromino should lock now
+    pub fn tick(&mut self, grounded: bool) -> bool {
+        self.grounded = grounded;
+        if !grounded {
+            // Lifting off the stack (e.g. sliding off a ledge) stops the clock, except
+            // without resets where the time on the ground is spent for good
+            if self.reset != LockReset::None {
+                self.ticks = 0;
+            }
+            return false;
+        }
+
+        self.ticks += 1;
+        let out_of_resets =
+            matches!(self.reset, LockReset::Move { max_resets } if self.resets >= max_resets);
+        self.ticks >= self.delay || out_of_resets
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_locks_after_delay() {
+        let mut lock_delay = LockDelay::new(3, LockReset::default());
+        lock_delay.on_spawn(20);
+        assert!(!lock_delay.tick(false));
+        assert!(!lock_delay.tick(true));
+        assert!(!lock_delay.tick(true));
+        assert!(lock_delay.tick(true));
+    }
+
+    #[test]
+    fn test_move_reset_is_capped() {
+        let mut lock_delay = LockDelay::new(3, LockReset::Move { max_resets: 2 });
+        lock_delay.on_spawn(0);
+        for _ in 0..2 {
+            assert!(!lock_delay.tick(true));
+            assert!(!lock_delay.tick(true));
+            lock_delay.on_move();
+        }
+        // Out of resets, so the tetromino locks as soon as it's on the ground
+        assert!(lock_delay.tick(true));
+    }
+
+    #[test]
+    fn test_step_and_no_reset() {
+        let mut step = LockDelay::new(2, LockReset::Step);
+        step.on_spawn(5);
+        assert!(!step.tick(true));
+        step.on_move();
+        step.on_fall(4);
+        assert!(!step.tick(true));
+        assert!(step.tick(true));
+
+        let mut none = LockDelay::new(2, LockReset::None);
+        none.on_spawn(5);
+        assert!(!none.tick(true));
+        none.on_fall(4);
+        assert!(!none.tick(false));
+        assert!(none.tick(true));
+    }
+}
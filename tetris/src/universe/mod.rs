@@ -1,40 +1,112 @@
+pub mod ai;
 mod audio;
+pub mod clear;
 pub mod color;
-mod game;
+pub mod entry_delay;
+pub mod finesse;
+pub mod game;
+pub mod garbage;
+pub mod high_scores;
 mod input;
-mod rotations;
-
-use std::collections::HashSet;
+pub mod lock_delay;
+pub mod mode;
+mod options;
+pub mod playfield;
+pub mod randomizer;
+pub mod replay;
+pub mod rotations;
+pub mod save;
+pub mod scoring;
+pub mod simulation;
+pub mod stats;
+pub mod versus;
+
+use std::path::PathBuf;
 
 use rotations::rotation_direction::RotationDirection;
-use tetromino::tetromino_type::TetrominoType;
 
+use ai::{AiPlayer, Weights};
 use color::ColorPalette;
-use game::Game;
+use game::GameState;
+use high_scores::{ScoreKeeper, HIGH_SCORES_KEY};
+use mode::{format_time, GameMode};
+use options::{OptionsMenu, OPTIONS_KEY};
+use replay::ReplayPlayer;
+use save::{Save, SaveError};
+use simulation::{Event, GameAction, Rules, Simulation};
+use stats::{EXPORT_KEY, STATS_KEY};
 
-use thomas::{context::Context, rodio::Source};
+use thomas::{context::Context, rodio::Source, winit::event::VirtualKeyCode};
 
 use self::audio::Audio;
 
 use super::*;
 
-const INITIAL_WIDTH: u32 = 10;
-const INITIAL_HEIGHT: u32 = 20;
+// Smallest board every tetromino can spawn and turn around on, bigger pieces widen the board to fit
+pub(crate) const MIN_BOARD_WIDTH: u32 = 4;
+pub(crate) const MIN_BOARD_HEIGHT: u32 = 4;
+// Tetrominos spawn into the buffer, which has to fit the 2 rows they take up
+pub(crate) const MIN_BUFFER_HEIGHT: u32 = 2;
+// Size of a next queue cell, relative to a board cell
+const PREVIEW_SCALE: f32 = 0.6;
+// Ticks between the AI's actions, so that it can be followed
+const AI_PACE: u32 = 3;
+const SAVE_KEY: VirtualKeyCode = VirtualKeyCode::S;
+const AI_KEY: VirtualKeyCode = VirtualKeyCode::A;
+// Keys for the window rather than the game: saving replays, the AI, the options menu, high scores and statistics
+const COMMAND_KEYS: [VirtualKeyCode; 6] = [
+    SAVE_KEY,
+    AI_KEY,
+    OPTIONS_KEY,
+    HIGH_SCORES_KEY,
+    STATS_KEY,
+    EXPORT_KEY,
+];
+
+/// What `key` is kept for if it's one of the command keys, which can't also be bound to an action
+pub(crate) fn kept_for(key: VirtualKeyCode) -> Option<&'static str> {
+    match key {
+        SAVE_KEY => Some("saving replays"),
+        AI_KEY => Some("the AI"),
+        OPTIONS_KEY => Some("the options menu"),
+        HIGH_SCORES_KEY => Some("high scores"),
+        STATS_KEY => Some("statistics"),
+        EXPORT_KEY => Some("exporting statistics"),
+        _ => None,
+    }
+}
 
+/// Runs a `Simulation` in a window, turning keys into actions and events into sounds
 pub struct Universe {
-    // Board
-    dim: Dimensions,
-    // Player controlled tetrimino
-    focused_tetromino: Tetromino,
-    ghost: Tetromino,
-    // Tetriminos on board
-    stagnant_tetrominos: Vec<Tetromino>,
+    // The game itself
+    sim: Simulation,
     // Controls for tetrimino
     tetromino_controls: TetrominoControls,
+    // Replay being played back instead of the keyboard, if any
+    playback: Option<ReplayPlayer>,
+    playback_controls: PlaybackControls,
+    // What happened to the last attempt at saving the recording
+    replay_status: Option<String>,
+    // Whether the results show every statistic rather than the summary
+    show_stats: bool,
+    // What happened to the last attempt at exporting the statistics
+    stats_status: Option<String>,
+    // Ticks left of flashing a finesse fault
+    finesse_flash: u32,
+    // Plays instead of the keyboard while it's on
+    ai: Option<AiPlayer>,
+    // Whether the recording can be saved. Versus boards can't, since garbage from the other board isn't recorded
+    replays: bool,
+    // Where the game in progress is saved when the window closes, if anywhere
+    save_path: Option<PathBuf>,
+    // Save from last time waiting on whether to resume it, or why it can't be
+    offered_save: Option<Result<Save, SaveError>>,
+    // High score tables results are entered into, if any
+    high_scores: Option<ScoreKeeper>,
+    // Settings that can be changed in game, while it's open
+    options: Option<OptionsMenu>,
     // Static color palette for game
     color_palette: ColorPalette,
-    // Game mechanics
-    game: Game,
     // Sound system
     audio: Audio,
     pub config: Config,
@@ -42,175 +114,135 @@ pub struct Universe {
 
 impl thomas::Runnable for Universe {
     fn tick(&mut self, ctx: &mut Context) {
-        if !self.game.running() {
-            self.game_over(ctx);
-            return;
-        }
-        self.game_over(ctx);
-
-        // Set level of the game
-        self.game.tick();
-
-        // update preview/ghost
-        self.full_fall_focused();
+        self.update(ctx);
+    }
 
-        self.tetromino_controls.tick(ctx);
-        self.receive_key(ctx);
-
-        // Literally just move current .y down
-        // Falls at the rate of 6 per second
-
-        if self.game.should_fall() {
-            self.fall_focused(ctx);
-            // I guess we'll just scan for audio here
-            if self.audio.sink.is_none() {
-                let d = thomas::audio::configure_audio_vorbis(ctx, "music.ogg")
-                    .expect("Troulbe loading file")
-                    .stoppable()
-                    .repeat_infinite();
-                self.audio.sink =
-                    Some(thomas::audio::play_source(ctx, d).expect("Trouble playing file"));
-            }
+    fn exit(&mut self, _ctx: &mut Context) {
+        // Nowhere left to show it on screen, the window's closing
+        if let Err(e) = self.save_game() {
+            eprintln!("Couldn't save the game: {}", e);
         }
+    }
 
-        let mut levels: HashMap<u32, u32> = HashMap::new();
+    fn render(&self, ctx: &mut Context) {
+        // Clear background
+        ctx.graphics.clear_background(self.color_palette.grid());
 
-        // Setup hash
-        // We should probably store the hashmap, this way we won't have to update it every tick
-        for tetromino in self.stagnant_tetrominos.iter() {
-            for coord in tetromino.coords() {
-                // Store the number of tetris parts in each y level
-                let e = levels.entry(coord.y).or_insert(0);
-                *e += 1;
-            }
+        // Nothing's been played yet while a save is on offer
+        if let Some(offered) = &self.offered_save {
+            self.render_offered_save(ctx, offered);
+            return;
         }
 
-        // filter out hash for levels that we need
-        let levels = levels
-            .iter()
-            .filter_map(|l| if *l.1 == self.dim.w { Some(*l.0) } else { None })
-            .collect::<HashSet<u32>>();
-
-        // Nothing to do if there aren't any full rows
-        if levels.is_empty() {
+        // The options menu covers everything else
+        if let Some(menu) = &self.options {
+            self.render_options(ctx, menu);
             return;
         }
-        // we should play a sound effect here since it means we cleared a row
-        thomas::audio::play_once_vorbis(ctx, "line_clear.ogg").expect("unable to play audio");
 
-        // ...Otherwise, if there is a full row...
-
-        // Delete all stagnant tetriminos at these specific y levels
-        let mut i = 0;
-        while i != self.stagnant_tetrominos.len() {
-            let mut j = 0;
-            while j != self.stagnant_tetrominos[i].coords().len() {
-                if levels.contains(&self.stagnant_tetrominos[i].coords()[j].y) {
-                    self.stagnant_tetrominos[i].coords_mut().remove(j);
-                } else {
-                    j += 1;
-                }
+        // As do the high score tables, and typing a name into them
+        if let Some(keeper) = &self.high_scores {
+            if let Some((name, place)) = keeper.naming() {
+                self.render_naming(ctx, name, place);
+                return;
             }
-            // No memory leaks thank you
-            if self.stagnant_tetrominos[i].coords().is_empty() {
-                self.stagnant_tetrominos.remove(i);
-            } else {
-                i += 1;
+            if let Some(table) = keeper.browsing() {
+                self.render_high_scores(ctx, keeper, table);
+                return;
             }
         }
 
-        // Then prepare to move the other tetriminos down (gravity)
-        let mut diff = [0; 24];
-        levels
-            .iter()
-            .for_each(|&l| Universe::change_arr_from_idx(&mut diff, l, 1));
-
-        // Finally,if something happened try to move pieces down if they need to be moved
-        // fk, we're iterating over stagnant tetrominos like 3 times. We honestly only need to really do it twice if we store the hashmap
-        // If we implemented it with an array we would only need to iterate over the board once
-        for i in 0..self.stagnant_tetrominos.len() {
-            for j in 0..self.stagnant_tetrominos[i].coords().len() {
-                // TODO this line has the possbility of crashing, since if we have a line tetromino,
-                // it might end up such that we're above 20
-                // best way to deal with this is to init the size of the array as 24, like the official tetris guidelines say
-                self.stagnant_tetrominos[i].coords_mut()[j].y -=
-                    diff[self.stagnant_tetrominos[i].coords()[j].y as usize];
-            }
-        }
-
-        self.game.update(levels.len() as u32, ctx);
-    }
-
-    fn render(&self, ctx: &mut Context) {
-        // Clear background
-        ctx.graphics.clear_background(self.color_palette.grid());
-
         // Render grid
         self.render_grid(ctx);
 
-        // Render the focused tetrimino
-        self.focused_tetromino()
-            .render(ctx, &self.config, &self.dim, &self.color_palette);
+        match self.sim.game().state() {
+            GameState::Playing | GameState::GameOver(_) => {
+                self.render_board(ctx, true);
+            }
+            // Nothing's spawned yet, or the next tetromino is waiting for the delays to run out
+            GameState::Countdown(_)
+            | GameState::LineClearDelay(_)
+            | GameState::Entry(_)
+            | GameState::Finished(_) => {
+                self.render_board(ctx, false);
+            }
+            // The board is hidden while paused, so that pausing can't be used to plan ahead
+            GameState::Paused => {
+                self.render_centered(ctx, "PAUSED", 100.0, 0.0);
+                let resume = format!("Press \"{}\" to resume", self.key_names(GameAction::Pause));
+                self.render_centered(ctx, &resume, 20.0, 80.0);
+            }
+            GameState::Results if self.show_stats => {
+                self.render_stats(ctx);
+            }
+            GameState::Results => {
+                self.render_results(ctx);
+            }
+        }
 
-        // And every other tetrimino
-        self.stagnant_tetrominos().iter().for_each(|t| {
-            t.render(ctx, &self.config, &self.dim, &self.color_palette);
-        });
+        match self.sim.game().state() {
+            GameState::GameOver(_) => {
+                ctx.graphics.draw_text(
+                    "GAME",
+                    self.config.hud_l(),
+                    self.config.h() / 2.0,
+                    self.color_palette.line().into(),
+                    100.0,
+                );
+                ctx.graphics.draw_text(
+                    "OVER",
+                    self.config.slot_l() + self.config.slot_w() * 0.75,
+                    self.config.h() / 2.0,
+                    self.color_palette.line().into(),
+                    100.0,
+                );
+            }
+            GameState::Finished(_) => {
+                let text = match self.sim.game().mode() {
+                    GameMode::Ultra => "TIME UP",
+                    _ => "FINISH",
+                };
+                self.render_centered(ctx, text, 100.0, 0.0);
+            }
+            GameState::Results => {}
+            _ => self.render_hud(ctx),
+        }
 
-        // Render the ghost
-        self.ghost()
-            .render_alpha(ctx, &self.config, &self.dim, &self.color_palette);
+        if let Some(player) = &self.playback {
+            self.render_playback(ctx, player);
+        }
 
-        // If game is in an 'over' state
-        if !self.game.running() {
-            ctx.graphics.draw_text(
-                "GAME",
-                150.0,
-                self.config.h() / 2.0,
-                self.color_palette.line().into(),
-                100.0,
-            );
-            ctx.graphics.draw_text(
-                "OVER",
-                self.config.w() - 400.0,
-                self.config.h() / 2.0,
-                self.color_palette.line().into(),
-                100.0,
-            );
-            ctx.graphics.draw_text(
-                "Press \"r\" to restart",
-                150.0,
-                self.config.h() / 2.0,
-                self.color_palette.line().into(),
-                20.0,
-            );
-        } else {
-            // Display level
-            ctx.graphics.draw_text(
-                &format!("LEVEL: {}", self.game.level()),
-                150.0,
-                150.0,
-                self.color_palette.line().into(),
-                50.0,
-            );
-            // Display score
-            ctx.graphics.draw_text(
-                &format!("score: {}", self.game.score()),
-                150.0,
-                200.0,
-                self.color_palette.line().into(),
-                30.0,
-            )
+        // "3, 2, 1, GO" over the board
+        if let Some(countdown) = self.sim.game().countdown_text() {
+            self.render_centered(ctx, &countdown, 100.0, 0.0);
         }
     }
 }
 
+/// Size of the board in cells
 pub struct Dimensions {
     pub w: u32,
+    // Visible rows
     pub h: u32,
+    // Hidden rows above the visible ones, where tetrominos spawn
+    pub buffer: u32,
 }
 
 impl Dimensions {
+    /// A `w` by `h` board with `buffer` hidden rows above it, grown to the smallest board that can be played on
+    pub fn new(w: u32, h: u32, buffer: u32) -> Self {
+        Self {
+            w: w.max(MIN_BOARD_WIDTH),
+            h: h.max(MIN_BOARD_HEIGHT),
+            buffer: buffer.max(MIN_BUFFER_HEIGHT),
+        }
+    }
+
+    /// Get a reference to the dimensions's buffer height.
+    pub fn buffer(&self) -> &u32 {
+        &self.buffer
+    }
+
     /// Get a reference to the dimensions's w.
     pub fn w(&self) -> &u32 {
         &self.w
@@ -224,116 +256,523 @@ impl Dimensions {
 
 impl Universe {
     pub fn new(ctx: &mut Context, config: Config) -> Self {
+        let controls = TetrominoControls::new(config.bindings().clone(), &config)
+            .with_commands(&COMMAND_KEYS);
+        Self::with_controls(ctx, config, controls)
+    }
+
+    /// A board for versus, played with its own key map in its own share of the window
+    pub(super) fn versus(ctx: &mut Context, config: Config, controls: TetrominoControls) -> Self {
+        let mut universe = Self::with_controls(ctx, config, controls);
+        universe.replays = false;
+        universe
+    }
+
+    /// A board played with `controls`, whatever keys they read
+    fn with_controls(ctx: &mut Context, config: Config, controls: TetrominoControls) -> Self {
         Universe {
-            dim: Dimensions {
-                w: INITIAL_WIDTH,
-                h: INITIAL_HEIGHT,
-            },
-            focused_tetromino: TetrominoType::generate_tetromino_rand(),
-            ghost: TetrominoType::generate_tetromino_rand(),
-            stagnant_tetrominos: vec![],
-            tetromino_controls: TetrominoControls::default(),
-            color_palette: ColorPalette::default(),
-            game: Game::default(),
+            sim: Simulation::new(Rules::from_config(&config), config.seed()),
+            tetromino_controls: controls,
+            playback: None,
+            playback_controls: PlaybackControls::default(),
+            replay_status: None,
+            show_stats: false,
+            stats_status: None,
+            finesse_flash: 0,
+            ai: None,
+            replays: true,
+            save_path: None,
+            offered_save: None,
+            high_scores: None,
+            options: None,
+            color_palette: config.palette().clone(),
             audio: Audio::new(ctx),
             config,
         }
     }
 
-    fn within_boundary(&self) -> bool {
-        self.focused_tetromino
-            .within_boundary(Tetromino::get_dxdy(MoveDirection::Down), &self.dim)
+    /// Reads the keyboard and plays out a tick, returning everything that happened in it
+    pub(super) fn update(&mut self, ctx: &mut Context) -> Vec<Event> {
+        if self.playback.is_some() {
+            self.tick_playback(ctx);
+            return Vec::new();
+        }
+        // Nothing starts until the player decides on the save from last time
+        if self.offered_save.is_some() {
+            self.tick_offered_save(ctx);
+            return Vec::new();
+        }
+        // The game freezes while the options menu is open
+        if self.options.is_some() {
+            self.tick_options(ctx);
+            return Vec::new();
+        }
+        if self.high_scores.as_ref().is_some_and(ScoreKeeper::busy) {
+            self.tick_high_scores(ctx);
+            return Vec::new();
+        }
+
+        self.tetromino_controls.tick(ctx);
+        // Saving replays is up to the window, the simulation doesn't know about files
+        let save = self.tetromino_controls.command(SAVE_KEY);
+        if self.tetromino_controls.command(AI_KEY) {
+            self.set_ai(self.ai.is_none());
+        }
+        if self.tetromino_controls.command(OPTIONS_KEY) {
+            self.tetromino_controls.clear_queue();
+            self.open_options();
+            return Vec::new();
+        }
+        // Looking at the tables pauses the game, the same as the options menu
+        if self.tetromino_controls.command(HIGH_SCORES_KEY) && self.high_scores.is_some() {
+            self.tetromino_controls.clear_queue();
+            self.open_high_scores();
+            return Vec::new();
+        }
+        let mut actions = self.tetromino_controls.actions().to_vec();
+        let mut presses = self.tetromino_controls.presses().to_vec();
+        self.tetromino_controls.clear_queue();
+
+        // The AI does the playing, but pausing and restarting are still up to the keyboard
+        if let Some(ai) = &mut self.ai {
+            actions.retain(|a| matches!(a, GameAction::Pause | GameAction::Restart));
+            // Every one of its actions is as good as a key press
+            presses = ai.actions(&self.sim);
+            actions.extend(&presses);
+        }
+        self.sim.press(&presses);
+        // Practice is for the player, the AI would just be held up by it
+        let practice = self.config.finesse().practice && self.ai.is_none();
+        let bindings = self.config.bindings();
+        let half_turns = !bindings.keys(GameAction::Rotate180).is_empty();
+        self.sim.set_finesse(practice, half_turns);
+
+        if save && self.replays && self.sim.game().state() == GameState::Results {
+            self.replay_status = Some(match self.save_replay() {
+                Ok(path) => format!("Saved replay to {}", path.display()),
+                Err(e) => format!("Couldn't save replay: {}", e),
+            });
+        }
+        if self.sim.game().state() == GameState::Results {
+            if self.tetromino_controls.command(STATS_KEY) {
+                self.show_stats = !self.show_stats;
+            }
+            if self.tetromino_controls.command(EXPORT_KEY) {
+                self.stats_status = Some(match self.export_stats() {
+                    Ok(path) => format!("Exported statistics to {}", path.display()),
+                    Err(e) => format!("Couldn't export statistics: {}", e),
+                });
+            }
+        }
+        let events = self.step(ctx, &actions);
+        self.offer_high_score();
+        events
+    }
+
+    /// Turns the AI on or off, it picks up from wherever the game is
+    pub fn set_ai(&mut self, on: bool) {
+        self.ai = on.then(|| AiPlayer::new(Weights::default(), AI_PACE));
     }
 
-    fn generate_new_tetromino(&mut self, ctx: &mut Context) {
-        // Generate a new current, swap it with the current current,
-        // then solidifying it by pushing it into stagnant tetrominos
-        let mut t = TetrominoType::generate_tetromino_rand();
-        std::mem::swap(&mut self.focused_tetromino, &mut t);
-        self.stagnant_tetrominos.push(t);
+    /// Plays out a tick of the simulation, then reacts to whatever happened in it
+    fn step(&mut self, ctx: &mut Context, actions: &[GameAction]) -> Vec<Event> {
+        self.finesse_flash = self.finesse_flash.saturating_sub(1);
+        let events = self.sim.tick(actions);
+        for &event in events.iter() {
+            self.handle_event(ctx, event);
+        }
 
-        // Play a sound effect
-        thomas::audio::play_once_vorbis(ctx, "hard_drop.ogg").expect("Playback error");
+        // Music starts with the first tick of play
+        if self.audio.music && self.sim.game().running() && self.audio.sink.is_none() {
+            let d = thomas::audio::configure_audio_vorbis(ctx, "music.ogg")
+                .expect("Troulbe loading file")
+                .stoppable()
+                .repeat_infinite();
+            let sink = thomas::audio::play_source(ctx, d).expect("Trouble playing file");
+            sink.set_volume(self.config.music_volume());
+            self.audio.sink = Some(sink);
+        }
+        events
+    }
 
-        // If it generates into a piece, game ova
-        if self
-            .focused_tetromino
-            .will_collide_towards(&self.stagnant_tetrominos, MoveDirection::None)
-        {
-            // Game over
-            self.game.pause();
+    /// Plays sounds for, and otherwise reacts to, something that happened in the simulation
+    fn handle_event(&mut self, ctx: &mut Context, event: Event) {
+        let volume = self.config.effects_volume();
+        match event {
+            Event::Locked => {
+                thomas::audio::play_once_vorbis_with_volume(ctx, "hard_drop.ogg", volume)
+                    .expect("Playback error")
+            }
+            Event::Cleared(clear) if clear.lines > 0 => {
+                thomas::audio::play_once_vorbis_with_volume(ctx, "line_clear.ogg", volume)
+                    .expect("unable to play audio")
+            }
+            Event::LevelUp(_) => {
+                thomas::audio::play_once_vorbis_with_volume(ctx, "lvl_up.ogg", volume)
+                    .expect("unable to level up")
+            }
+            Event::Paused => {
+                if let Some(sink) = &self.audio.sink {
+                    sink.pause();
+                }
+            }
+            Event::Resumed => {
+                if let Some(sink) = &self.audio.sink {
+                    sink.play();
+                }
+            }
+            // Flashes for a second
+            Event::FinesseFault(_) if self.config.finesse().flash => {
+                self.finesse_flash = *self.config.ticks();
+            }
+            Event::Restarted => {
+                self.replay_status = None;
+                self.show_stats = false;
+                self.stats_status = None;
+                self.finesse_flash = 0;
+            }
+            _ => {}
         }
     }
 
-    fn fall_focused(&mut self, ctx: &mut Context) {
-        if self.within_boundary()
-            && !self
-                .focused_tetromino
-                .will_collide_towards(&self.stagnant_tetrominos, MoveDirection::Down)
-        {
-            self.focused_tetromino
-                .move_by(Tetromino::get_dxdy(MoveDirection::Down));
-        } else {
-            self.generate_new_tetromino(ctx);
+    /// Renders the playfield and the pieces around it, `focused` being whether there's a focused tetromino to show
+    fn render_board(&self, ctx: &mut Context, focused: bool) {
+        if focused {
+            // Render the focused tetrimino
+            self.sim.focused_tetromino().render(
+                ctx,
+                &self.config,
+                self.sim.dim(),
+                &self.color_palette,
+            );
+        }
+
+        // And every locked cell
+        self.sim.playfield().render(
+            ctx,
+            &self.config,
+            self.sim.dim(),
+            &self.color_palette,
+            self.sim.piece_set(),
+        );
+        self.render_clearing_rows(ctx);
+
+        if focused {
+            // Render the ghost
+            self.sim
+                .ghost()
+                .render_alpha(ctx, &self.config, self.sim.dim(), &self.color_palette);
         }
+
+        // Garbage waiting to rise, up the left edge of the board
+        self.render_garbage_meter(ctx);
+        // Render upcoming tetrominos to the right of the board
+        self.render_next_queue(ctx);
+        // And the held tetromino to the left of it
+        self.render_hold(ctx);
     }
 
-    /// Implmentation of hard drop preview
-    pub fn full_fall_focused(&mut self) {
-        self.ghost = self.focused_tetromino.clone();
-
-        // While it's within boundary and won't collide with anything
-        // TODO I swear I have no clue wtf I'm looking at but
-        while self
-            .ghost
-            .within_boundary(Tetromino::get_dxdy(MoveDirection::Down), &self.dim)
-            && !self
-                .ghost
-                .will_collide_towards(&self.stagnant_tetrominos, MoveDirection::Down)
-        {
-            // Move it down
-            self.ghost.move_by(Tetromino::get_dxdy(MoveDirection::Down));
+    /// Renders the rows being cleared as bars of light that shrink into the middle over the line clear delay
+    fn render_clearing_rows(&self, ctx: &mut Context) {
+        let GameState::LineClearDelay(left) = self.sim.game().state() else {
+            return;
+        };
+        let total = self.sim.rules().entry_delay.line_clear_delay.max(1);
+        let shown = left as f32 / total as f32;
+        let size = *self.config.cell();
+        let w = self.sim.dim().w as f32 * size;
+        for &y in self.sim.clearing() {
+            if y >= self.sim.dim().h {
+                continue;
+            }
+            let top = self.config.canvas_b() - (y as f32 + 1.0) * size;
+            ctx.graphics.draw_rectangle(
+                *self.config.canvas_l(),
+                top,
+                w,
+                size,
+                self.color_palette.grid(),
+            );
+            ctx.graphics.draw_rectangle(
+                self.config.canvas_l() + w * (1.0 - shown) / 2.0,
+                top,
+                w * shown,
+                size,
+                self.color_palette.line(),
+            );
         }
     }
 
-    fn clear(&mut self) {
-        self.stagnant_tetrominos.clear();
+    /// Renders the level, score, and whatever the last locks cleared, plus how far along the mode is
+    fn render_hud(&self, ctx: &mut Context) {
+        let game = self.sim.game();
+        // Display the mode, and the goal it's going for
+        let progress = match (game.mode().line_goal(), game.time_left()) {
+            (Some(goal), _) => format!("lines: {}/{}", game.lines_cleared(), goal),
+            (None, Some(left)) => format!("left: {}", format_time(left, game.ticks_per_second())),
+            (None, None) => format!("lines: {}", game.lines_cleared()),
+        };
+        ctx.graphics.draw_text(
+            &game.mode().name().to_uppercase(),
+            self.config.hud_l(),
+            50.0,
+            self.color_palette.line().into(),
+            30.0,
+        );
+        ctx.graphics.draw_text(
+            &progress,
+            self.config.hud_l(),
+            90.0,
+            self.color_palette.line().into(),
+            20.0,
+        );
+        // Sprints are against the clock
+        if game.mode() == GameMode::Sprint {
+            ctx.graphics.draw_text(
+                &format_time(game.elapsed(), game.ticks_per_second()),
+                self.config.hud_l(),
+                115.0,
+                self.color_palette.line().into(),
+                20.0,
+            );
+        }
+        // Display level
+        ctx.graphics.draw_text(
+            &format!("LEVEL: {}", self.sim.game().level()),
+            self.config.hud_l(),
+            150.0,
+            self.color_palette.line().into(),
+            50.0,
+        );
+        // Display score
+        ctx.graphics.draw_text(
+            &format!("score: {}", self.sim.game().score()),
+            self.config.hud_l(),
+            200.0,
+            self.color_palette.line().into(),
+            30.0,
+        );
+        // Display whatever the last lock cleared, e.g. T-SPIN DOUBLE
+        if let Some(clear) = self.sim.game().last_clear() {
+            ctx.graphics.draw_text(
+                &clear.to_string(),
+                self.config.hud_l(),
+                250.0,
+                self.color_palette.line().into(),
+                30.0,
+            );
+        }
+        // Display the combo and back-to-back chain while they're going
+        if let Some(combo) = self.sim.game().combo().filter(|&c| c > 0) {
+            ctx.graphics.draw_text(
+                &format!("COMBO {}", combo),
+                self.config.hud_l(),
+                300.0,
+                self.color_palette.line().into(),
+                30.0,
+            );
+        }
+        if let Some(b2b) = self.sim.game().back_to_back().filter(|&b| b > 0) {
+            ctx.graphics.draw_text(
+                &format!("B2B x{}", b2b),
+                self.config.hud_l(),
+                350.0,
+                self.color_palette.line().into(),
+                30.0,
+            );
+        }
+        if self.ai.is_some() {
+            ctx.graphics.draw_text(
+                "AUTOPLAY",
+                self.config.hud_l(),
+                400.0,
+                self.color_palette.line().into(),
+                30.0,
+            );
+        }
+        self.render_live_stats(ctx);
     }
 
-    fn game_over(&mut self, ctx: &mut Context) {
-        if ctx.keyboard.r {
-            // Clear board
-            self.clear();
-            // Create new game instance
-            self.game = Game::default();
+    /// Renders the final score, lines and level of a finished game
+    fn render_results(&self, ctx: &mut Context) {
+        let game = self.sim.game();
+        self.render_centered(ctx, "RESULTS", 60.0, -150.0);
+        // What the mode was played for: the time for a sprint, the score for an ultra
+        let headline = match game.mode() {
+            GameMode::Sprint if game.goal_reached() => {
+                format!(
+                    "SPRINT {}",
+                    format_time(game.elapsed(), game.ticks_per_second())
+                )
+            }
+            GameMode::Ultra => format!("ULTRA {}", game.score()),
+            GameMode::Marathon if game.goal_reached() => String::from("MARATHON COMPLETE"),
+            mode => mode.name().to_uppercase(),
+        };
+        self.render_centered(ctx, &headline, 40.0, -105.0);
+        self.render_centered(
+            ctx,
+            &format!("score: {}", self.sim.game().score()),
+            30.0,
+            -60.0,
+        );
+        self.render_centered(
+            ctx,
+            &format!("lines: {}", self.sim.game().lines_cleared()),
+            30.0,
+            -20.0,
+        );
+        self.render_centered(
+            ctx,
+            &format!("level: {}", self.sim.game().level()),
+            30.0,
+            20.0,
+        );
+        self.render_centered(
+            ctx,
+            &format!(
+                "time: {}",
+                format_time(game.elapsed(), game.ticks_per_second())
+            ),
+            30.0,
+            60.0,
+        );
+        let restart = format!(
+            "Press \"{}\" to restart",
+            self.key_names(GameAction::Restart)
+        );
+        self.render_centered(ctx, &restart, 20.0, 140.0);
+        if self.playback.is_none() && self.replays {
+            let status = match &self.replay_status {
+                Some(status) => status.clone(),
+                None => format!("Press \"{:?}\" to save the replay", SAVE_KEY),
+            };
+            self.render_centered(ctx, &status, 20.0, 180.0);
+        }
+        if self.high_scores.is_some() {
+            let high_scores = format!("Press \"{:?}\" for high scores", HIGH_SCORES_KEY);
+            self.render_centered(ctx, &high_scores, 20.0, 220.0);
         }
+        let status = match &self.stats_status {
+            Some(status) => status.clone(),
+            None => format!(
+                "Press \"{:?}\" for statistics, \"{:?}\" to export them as CSV",
+                STATS_KEY, EXPORT_KEY
+            ),
+        };
+        self.render_centered(ctx, &status, 20.0, 260.0);
+    }
+
+    /// Names the keys bound to `action` as they're written in the settings, e.g. `X Up`
+    fn key_names(&self, action: GameAction) -> String {
+        let keys = self.config.bindings().keys(action);
+        let keys: Vec<String> = keys.iter().map(|key| format!("{:?}", key)).collect();
+        keys.join(" ")
     }
 
-    /// The most complicated function ever
-    /// Basically increase array value from [idx..len] += diff
-    pub fn change_arr_from_idx(arr: &mut [u32], idx: u32, diff: u32) {
-        arr.iter_mut().skip(idx as usize).for_each(|n| *n += diff);
+    /// Renders text roughly centered over the board, `dy` pixels below the middle of the screen
+    fn render_centered(&self, ctx: &mut Context, text: &str, size: f32, dy: f32) {
+        // Glyphs are about half as wide as they are tall
+        let w = text.len() as f32 * size * 0.5;
+        ctx.graphics.draw_text(
+            text,
+            self.config.canvas_l() + (self.config.actual_w() - w) / 2.0,
+            self.config.h() / 2.0 + dy,
+            self.color_palette.line().into(),
+            size,
+        );
+    }
+
+    /// Renders the next queue as miniature tetrominos in the right letterbox
+    fn render_next_queue(&self, ctx: &mut Context) {
+        let cell = self.config.cell() * PREVIEW_SCALE;
+        let left = self.config.canvas_r() + cell * 2.0;
+
+        ctx.graphics.draw_text(
+            "NEXT",
+            left,
+            cell * 2.0,
+            self.color_palette.line().into(),
+            30.0,
+        );
+        let pieces = self.sim.piece_set();
+        // Room for the tallest piece of the set, plus 1 cell of space between each
+        let spacing = pieces.tallest() as f32 + 1.0;
+        for (i, &tetromino_type) in self.sim.next_queue().iter().enumerate() {
+            let top = cell * (4.0 + i as f32 * spacing);
+            self.sim.spawn(tetromino_type).render_preview(
+                ctx,
+                left,
+                top,
+                cell,
+                self.color_palette.color_for(pieces.piece(tetromino_type)),
+            );
+        }
+    }
+
+    /// Renders incoming garbage as a bar up the left edge of the board, a cell tall per line
+    fn render_garbage_meter(&self, ctx: &mut Context) {
+        let lines = self.sim.incoming_garbage().min(self.sim.dim().h);
+        if lines == 0 {
+            return;
+        }
+        let dy = *self.config.cell();
+        let w = dy * 0.25;
+        ctx.graphics.draw_rectangle(
+            self.config.canvas_l() - w * 1.5,
+            self.config.canvas_b() - lines as f32 * dy,
+            w,
+            lines as f32 * dy,
+            self.color_palette.attack(),
+        );
+    }
+
+    /// Renders the held tetromino in the left letterbox, greyed out while hold is locked
+    fn render_hold(&self, ctx: &mut Context) {
+        let cell = self.config.cell() * PREVIEW_SCALE;
+        let pieces = self.sim.piece_set();
+        // Room for the widest piece of the set, plus 2 cells of space between it and the board
+        let left = self.config.canvas_l() - cell * (pieces.widest() as f32 + 2.0);
+
+        ctx.graphics.draw_text(
+            "HOLD",
+            left,
+            cell * 2.0,
+            self.color_palette.line().into(),
+            30.0,
+        );
+        if let Some(held) = self.sim.held() {
+            let color = if self.sim.hold_locked() {
+                self.color_palette.locked()
+            } else {
+                self.color_palette.color_for(pieces.piece(held))
+            };
+            self.sim
+                .spawn(held)
+                .render_preview(ctx, left, cell * 4.0, cell, color);
+        }
     }
 
     /// Renders the 10x20 grid that tetrominos spawn on oo
     fn render_grid(&self, ctx: &mut Context) {
-        // Spawn tetrminoes at up to level 22
-        // Only show 10x20 grid
+        // Spawn tetrminoes in the buffer
+        // Only show the visible rows
 
-        let dx = *self.config.actual_w() / self.dim.w as f32;
+        let dx = *self.config.cell();
+        let top = self.config.canvas_b() - dx * self.sim.dim().h as f32;
 
-        [0.0, self.dim.w as f32].iter().for_each(|x| {
+        [0.0, self.sim.dim().w as f32].iter().for_each(|x| {
             let current_x = x * dx + self.config.canvas_l();
             ctx.graphics.draw_line(
                 current_x,
-                0_f32,
+                top,
                 current_x,
-                *self.config.h(),
+                *self.config.canvas_b(),
                 4.0,
                 self.color_palette.line(),
             )
         });
-        // (0..=self.dim.w).into_iter().for_each(|x| {
+        // (0..=self.sim.dim().w).into_iter().for_each(|x| {
         //     // For every implement of x, draw from the ground to the ceiling
         //     let current_x = x as f32 * dx + self.config.canvas_l();
         //     ctx.graphics.draw_line(
@@ -365,27 +804,13 @@ impl Universe {
 
 // Getters and setters
 impl Universe {
-    /// Get a reference to the universe's current.
-    pub fn focused_tetromino(&self) -> &Tetromino {
-        &self.focused_tetromino
-    }
-
-    /// Get a mutable reference to the universe's current.
-    pub fn focused_tetromino_mut(&mut self) -> &mut Tetromino {
-        &mut self.focused_tetromino
-    }
-
-    /// Get a reference to the universe's ghost.
-    pub fn ghost(&self) -> &Tetromino {
-        &self.ghost
-    }
-
-    /// Get a reference to the universe's stagnant tetrominos.
-    pub fn stagnant_tetrominos(&self) -> &Vec<Tetromino> {
-        &self.stagnant_tetrominos
+    /// Get a reference to the universe's simulation
+    pub fn sim(&self) -> &Simulation {
+        &self.sim
     }
 
-    pub fn stagnant_tetrominos_mut(&mut self) -> &mut Vec<Tetromino> {
-        &mut self.stagnant_tetrominos
+    /// Get a mutable reference to the universe's simulation
+    pub fn sim_mut(&mut self) -> &mut Simulation {
+        &mut self.sim
     }
 }
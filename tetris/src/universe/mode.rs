@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+// Lines to clear in a sprint
+const SPRINT_LINES: u32 = 40;
+// Lines to clear in a marathon
+const MARATHON_LINES: u32 = 150;
+// Seconds an ultra lasts
+const ULTRA_SECONDS: u32 = 120;
+
+/// What a game is played for, and what ends it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    /// Clear 150 lines, speeding up every level
+    #[default]
+    Marathon,
+    /// Clear 40 lines as fast as possible
+    Sprint,
+    /// Score as much as possible in 2 minutes
+    Ultra,
+    /// Play forever, topping out only clears the board
+    Zen,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::Marathon,
+        GameMode::Sprint,
+        GameMode::Ultra,
+        GameMode::Zen,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Marathon => "marathon",
+            GameMode::Sprint => "sprint",
+            GameMode::Ultra => "ultra",
+            GameMode::Zen => "zen",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// Lines that finish the game once cleared, if any
+    pub fn line_goal(&self) -> Option<u32> {
+        match self {
+            GameMode::Marathon => Some(MARATHON_LINES),
+            GameMode::Sprint => Some(SPRINT_LINES),
+            GameMode::Ultra | GameMode::Zen => None,
+        }
+    }
+
+    /// Seconds of play that finish the game, if any
+    pub fn time_limit(&self) -> Option<u32> {
+        match self {
+            GameMode::Ultra => Some(ULTRA_SECONDS),
+            _ => None,
+        }
+    }
+
+    /// Whether reaching the top ends the game
+    pub fn can_top_out(&self) -> bool {
+        *self != GameMode::Zen
+    }
+
+    /// Whether the level, and with it the speed, goes up with lines cleared.
+    /// Every other mode stays at the first level's speed
+    pub fn levels_up(&self) -> bool {
+        *self == GameMode::Marathon
+    }
+}
+
+/// Formats ticks as `m:ss.cc`
+pub fn format_time(ticks: u32, ticks_per_second: u32) -> String {
+    let centis = ticks as u64 * 100 / ticks_per_second.max(1) as u64;
+    format!(
+        "{}:{:02}.{:02}",
+        centis / 6000,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_names_round_trip() {
+        for mode in GameMode::ALL {
+            assert_eq!(GameMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(GameMode::from_name("survival"), None);
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0, 60), "0:00.00");
+        assert_eq!(format_time(60 * 83 + 30, 60), "1:23.50");
+    }
+}
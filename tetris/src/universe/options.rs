@@ -0,0 +1,329 @@
+use thomas::{context::Context, winit::event::VirtualKeyCode};
+
+use super::entry_delay::TimingPreset;
+use super::game::GameState;
+use super::mode::GameMode;
+use super::randomizer::RandomizerPreset;
+use super::rotations::RotationPreset;
+use super::simulation::{GameAction, Rules, Simulation};
+use super::Universe;
+use crate::settings::Settings;
+use crate::tetris_input::{Bindings, MenuControls, TetrominoControls};
+
+// Opens and closes the options menu
+pub(super) const OPTIONS_KEY: VirtualKeyCode = VirtualKeyCode::O;
+// How much a press changes DAS, ARR or DCD by, in milliseconds
+const HANDLING_STEP: i32 = 1;
+// How much a press changes a volume by
+const VOLUME_STEP: f32 = 0.1;
+
+/// Everything that can be changed in game
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    Mode,
+    Rotation,
+    Randomizer,
+    Timing,
+    Das,
+    Arr,
+    Sdf,
+    Dcd,
+    Music,
+    Effects,
+    FinesseFlash,
+    FinessePractice,
+    /// The keys bound to an action
+    Key(GameAction),
+}
+
+/// Every entry, in the order it's listed
+fn entries() -> Vec<Entry> {
+    [
+        Entry::Mode,
+        Entry::Rotation,
+        Entry::Randomizer,
+        Entry::Timing,
+        Entry::Das,
+        Entry::Arr,
+        Entry::Sdf,
+        Entry::Dcd,
+        Entry::Music,
+        Entry::Effects,
+        Entry::FinesseFlash,
+        Entry::FinessePractice,
+    ]
+    .into_iter()
+    .chain(GameAction::ALL.into_iter().map(Entry::Key))
+    .collect()
+}
+
+/// Settings that can be changed in game, every change being written back to the settings file
+pub struct OptionsMenu {
+    controls: MenuControls,
+    // Index into `entries()`
+    selected: usize,
+    // Whether the next key pressed gets bound to the selected action
+    capturing: bool,
+    // Key that was just bound, the menu ignores everything until it's let go of
+    releasing: Option<VirtualKeyCode>,
+    // What happened to the last attempt at writing the settings file
+    status: Option<String>,
+}
+
+impl OptionsMenu {
+    fn new() -> Self {
+        Self {
+            controls: MenuControls::new(OPTIONS_KEY),
+            selected: 0,
+            capturing: false,
+            releasing: None,
+            status: None,
+        }
+    }
+}
+
+impl Universe {
+    /// Opens the options menu. The game freezes until it's closed
+    pub(super) fn open_options(&mut self) {
+        if let Some(sink) = &self.audio.sink {
+            sink.pause();
+        }
+        self.options = Some(OptionsMenu::new());
+    }
+
+    /// Reads the menu's keys instead of the game's, applying whatever changed
+    pub(super) fn tick_options(&mut self, ctx: &mut Context) {
+        let Some(menu) = self.options.as_mut() else {
+            return;
+        };
+        let entries = entries();
+        if let Some(key) = menu.releasing {
+            if ctx.keyboard.is_pressed(key) {
+                return;
+            }
+            menu.releasing = None;
+        }
+        if menu.capturing {
+            if let (Some(key), Entry::Key(action)) =
+                (ctx.keyboard.take_pressed(), entries[menu.selected])
+            {
+                menu.capturing = false;
+                // Whatever was pressed is still down, and shouldn't also move through the menu
+                menu.releasing = Some(key);
+                if let Some(command) = super::kept_for(key) {
+                    menu.status = Some(format!("{:?} is kept for {}", key, command));
+                } else {
+                    self.rebind(|bindings| bindings.bind(action, key));
+                }
+            }
+            return;
+        }
+        if menu.controls.close(ctx) {
+            self.close_options();
+            return;
+        }
+        let select = menu.controls.select(ctx);
+        menu.selected = (menu.selected as i32 + select).rem_euclid(entries.len() as i32) as usize;
+        let change = menu.controls.change(ctx);
+        match (entries[menu.selected], change) {
+            (_, 0) => {}
+            // Right waits for a key to add, left takes all of them off
+            (Entry::Key(_), 1) => {
+                ctx.keyboard.take_pressed();
+                menu.capturing = true;
+            }
+            (Entry::Key(action), _) => self.rebind(|bindings| bindings.unbind(action)),
+            (entry, change) => {
+                self.change_option(entry, change);
+                self.set_options_status();
+            }
+        }
+    }
+
+    /// Changes the bindings, and with them the controls
+    fn rebind(&mut self, change: impl FnOnce(&mut Bindings)) {
+        let mut bindings = self.config.bindings().clone();
+        change(&mut bindings);
+        self.config.set_bindings(bindings);
+        self.rebuild_controls();
+        self.set_options_status();
+    }
+
+    /// Builds the game's controls again from the config, e.g. after the handling or bindings changed
+    fn rebuild_controls(&mut self) {
+        self.tetromino_controls =
+            TetrominoControls::new(self.config.bindings().clone(), &self.config)
+                .with_commands(&super::COMMAND_KEYS);
+    }
+
+    /// Saves the settings, showing how that went under the menu
+    fn set_options_status(&mut self) {
+        let status = self.save_settings();
+        if let Some(menu) = self.options.as_mut() {
+            menu.status = Some(status);
+        }
+    }
+
+    /// Closes the options menu, starting a new game if the mode, rotation system, randomizer or timing changed
+    fn close_options(&mut self) {
+        self.options = None;
+        let rules = self.sim.rules();
+        if self.config.mode() != rules.mode
+            || self.config.rotation() != rules.rotation
+            || self.config.randomizer() != rules.randomizer
+            || self.config.entry_delay() != rules.entry_delay
+        {
+            self.sim = Simulation::new(Rules::from_config(&self.config), self.config.seed());
+            self.replay_status = None;
+        }
+        // Unless the game was paused before the menu opened
+        if let Some(sink) = &self.audio.sink {
+            if self.sim.game().state() != GameState::Paused {
+                sink.play();
+            }
+        }
+    }
+
+    /// Moves an entry's value `by` steps up or down
+    fn change_option(&mut self, entry: Entry, by: i32) {
+        let step = |value: u32, step: i32| (value as i32 + by * step).max(0) as u32;
+        let volume = |value: f32| {
+            ((value + by as f32 * VOLUME_STEP).clamp(0.0, 1.0) / VOLUME_STEP).round() * VOLUME_STEP
+        };
+        let mut handling = self.config.handling();
+        match entry {
+            Entry::Mode => {
+                let modes = GameMode::ALL;
+                let i = modes
+                    .iter()
+                    .position(|&m| m == self.config.mode())
+                    .unwrap_or(0);
+                let next = (i as i32 + by).rem_euclid(modes.len() as i32) as usize;
+                self.config.set_mode(modes[next]);
+            }
+            Entry::Rotation => {
+                let presets = RotationPreset::ALL;
+                let i = presets
+                    .iter()
+                    .position(|&r| r == self.config.rotation())
+                    .unwrap_or(0);
+                let next = (i as i32 + by).rem_euclid(presets.len() as i32) as usize;
+                self.config.set_rotation(presets[next]);
+            }
+            Entry::Randomizer => {
+                let presets = RandomizerPreset::ALL;
+                let i = presets
+                    .iter()
+                    .position(|&r| r == self.config.randomizer())
+                    .unwrap_or(0);
+                let next = (i as i32 + by).rem_euclid(presets.len() as i32) as usize;
+                self.config.set_randomizer(presets[next]);
+            }
+            // Delays set by hand in the settings file start over from the first preset
+            Entry::Timing => {
+                let ticks = *self.config.ticks();
+                let presets = TimingPreset::ALL;
+                let i = TimingPreset::find(&self.config.entry_delay(), ticks)
+                    .and_then(|preset| presets.iter().position(|&p| p == preset))
+                    .unwrap_or(0);
+                let next = (i as i32 + by).rem_euclid(presets.len() as i32) as usize;
+                self.config
+                    .set_entry_delay(presets[next].entry_delay(ticks));
+            }
+            Entry::Das => handling.das = step(handling.das, HANDLING_STEP),
+            Entry::Arr => handling.arr = step(handling.arr, HANDLING_STEP),
+            Entry::Sdf => handling.sdf = step(handling.sdf, 1),
+            Entry::Dcd => handling.dcd = step(handling.dcd, HANDLING_STEP),
+            Entry::Music => {
+                self.config
+                    .set_music_volume(volume(self.config.music_volume()));
+                if let Some(sink) = &self.audio.sink {
+                    sink.set_volume(self.config.music_volume());
+                }
+            }
+            Entry::Effects => self
+                .config
+                .set_effects_volume(volume(self.config.effects_volume())),
+            // Either way turns them on or off
+            Entry::FinesseFlash => {
+                let mut finesse = self.config.finesse();
+                finesse.flash = !finesse.flash;
+                self.config.set_finesse(finesse);
+            }
+            Entry::FinessePractice => {
+                let mut finesse = self.config.finesse();
+                finesse.practice = !finesse.practice;
+                self.config.set_finesse(finesse);
+            }
+            // Keys are changed by `rebind` instead
+            Entry::Key(_) => {}
+        }
+        if handling != self.config.handling() {
+            self.config.set_handling(handling);
+            self.rebuild_controls();
+        }
+    }
+
+    /// Writes the settings back to the file they came from, returning how that went
+    fn save_settings(&self) -> String {
+        let Some(path) = self.config.settings_path() else {
+            return String::from("No settings file, changes last until the game closes");
+        };
+        match Settings::from_config(&self.config).save(path) {
+            Ok(()) => format!("Saved to {}", path.display()),
+            Err(e) => format!("Couldn't save settings: {}", e),
+        }
+    }
+
+    /// Renders the menu over where the board would be, the selected entry marked with an arrow
+    pub(super) fn render_options(&self, ctx: &mut Context, menu: &OptionsMenu) {
+        let handling = self.config.handling();
+        let percent = |volume: f32| format!("{}%", (volume * 100.0).round());
+        let on = |on: bool| if on { "on" } else { "off" };
+        let finesse = self.config.finesse();
+        self.render_centered(ctx, "OPTIONS", 50.0, -320.0);
+        for (i, entry) in entries().into_iter().enumerate() {
+            let text = match entry {
+                Entry::Mode => format!("mode: {}", self.config.mode().name()),
+                Entry::Rotation => format!("rotation: {}", self.config.rotation().name()),
+                Entry::Randomizer => format!("randomizer: {}", self.config.randomizer().name()),
+                Entry::Timing => {
+                    let preset =
+                        TimingPreset::find(&self.config.entry_delay(), *self.config.ticks());
+                    format!("timing: {}", preset.map_or("custom", |p| p.name()))
+                }
+                Entry::Das => format!("DAS: {} ms", handling.das),
+                Entry::Arr => format!("ARR: {} ms", handling.arr),
+                Entry::Sdf if handling.sdf == 0 => String::from("SDF: infinite"),
+                Entry::Sdf => format!("SDF: {}x", handling.sdf),
+                Entry::Dcd => format!("DCD: {} ms", handling.dcd),
+                Entry::Music => format!("music: {}", percent(self.config.music_volume())),
+                Entry::Effects => format!("effects: {}", percent(self.config.effects_volume())),
+                Entry::FinesseFlash => format!("flash finesse faults: {}", on(finesse.flash)),
+                Entry::FinessePractice => {
+                    format!("retry finesse faults: {}", on(finesse.practice))
+                }
+                Entry::Key(action) if menu.capturing && i == menu.selected => {
+                    format!("{}: press a key", action.name())
+                }
+                Entry::Key(action) => format!("{}: {}", action.name(), self.key_names(action)),
+            };
+            let marker = if i == menu.selected { "> " } else { "  " };
+            self.render_centered(
+                ctx,
+                &format!("{}{}", marker, text),
+                24.0,
+                -270.0 + i as f32 * 25.0,
+            );
+        }
+        self.render_centered(
+            ctx,
+            "up/down: pick  left/right: change, or unbind/bind keys  \"o\": close",
+            20.0,
+            276.0,
+        );
+        if let Some(status) = &menu.status {
+            self.render_centered(ctx, status, 20.0, 311.0);
+        }
+    }
+}
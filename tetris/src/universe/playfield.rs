@@ -0,0 +1,262 @@
+use thomas::context::Context;
+
+use super::color::ColorPalette;
+use super::Dimensions;
+use crate::config::Config;
+use crate::tetromino::{piece_set::PieceSet, tetromino_type::TetrominoType, Tetromino};
+
+/// What filled a cell of the playfield
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    /// Part of a tetromino that locked there
+    Tetromino(TetrominoType),
+    /// Part of a garbage row sent by an opponent
+    Garbage,
+}
+
+/// Dense grid of every locked cell on the board.
+/// Indexed from the bottom left, so `y` grows upwards just like tetromino coords
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Playfield {
+    w: u32,
+    h: u32,
+    cells: Vec<Option<Cell>>,
+}
+
+impl Playfield {
+    /// Creates an empty playfield covering the visible board as well as the buffer above it
+    pub fn new(dim: &Dimensions) -> Self {
+        let w = *dim.w();
+        let h = dim.h() + dim.buffer();
+        Self {
+            w,
+            h,
+            cells: vec![None; (w * h) as usize],
+        }
+    }
+
+    #[inline]
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.w + x) as usize
+    }
+
+    /// Gets whatever filled `(x, y)`.
+    /// Anything outside of the grid counts as empty, boundaries are checked separately
+    pub fn get(&self, x: i32, y: i32) -> Option<Cell> {
+        if x < 0 || y < 0 || x >= self.w as i32 || y >= self.h as i32 {
+            return None;
+        }
+        self.cells[self.index(x as u32, y as u32)]
+    }
+
+    /// Whether `(x, y)` holds a locked cell
+    #[inline]
+    pub fn is_occupied(&self, x: i32, y: i32) -> bool {
+        self.get(x, y).is_some()
+    }
+
+    /// Solidifies a tetromino into the grid
+    pub fn lock(&mut self, tetromino: &Tetromino) {
+        for coord in tetromino.coords() {
+            if coord.x < self.w && coord.y < self.h {
+                let i = self.index(coord.x, coord.y);
+                self.cells[i] = Some(Cell::Tetromino(*tetromino.tetromino_type()));
+            }
+        }
+    }
+
+    fn row(&self, y: u32) -> &[Option<Cell>] {
+        let start = self.index(0, y);
+        &self.cells[start..start + self.w as usize]
+    }
+
+    /// Whether every cell in row `y` is filled
+    pub fn is_row_full(&self, y: u32) -> bool {
+        self.row(y).iter().all(Option::is_some)
+    }
+
+    /// Every full row, from the bottom up
+    pub fn full_rows(&self) -> Vec<u32> {
+        (0..self.h).filter(|&y| self.is_row_full(y)).collect()
+    }
+
+    /// Removes the given rows, then shifts everything above them down to fill the gap (gravity)
+    pub fn clear_rows(&mut self, rows: &[u32]) {
+        let w = self.w as usize;
+        let mut dst = 0;
+        for src in 0..self.h {
+            if rows.contains(&src) {
+                continue;
+            }
+            if dst != src {
+                let (start, dst_start) = (self.index(0, src), self.index(0, dst));
+                self.cells.copy_within(start..start + w, dst_start);
+            }
+            dst += 1;
+        }
+        // Whatever's left at the top is empty space
+        let top = self.index(0, dst);
+        self.cells[top..].fill(None);
+    }
+
+    /// Pushes everything up by `lines` rows, filling the rows underneath with garbage except for the `hole` column.
+    /// Returns whether anything got pushed out of the top
+    pub fn add_garbage(&mut self, lines: u32, hole: u32) -> bool {
+        let lines = lines.min(self.h);
+        let kept = self.index(0, self.h - lines);
+        let overflow = self.cells[kept..].iter().any(Option::is_some);
+
+        let w = self.w as usize;
+        self.cells.copy_within(..kept, lines as usize * w);
+        for y in 0..lines {
+            for x in 0..self.w {
+                let i = self.index(x, y);
+                self.cells[i] = (x != hole).then_some(Cell::Garbage);
+            }
+        }
+        overflow
+    }
+
+    /// Whether there's nothing left on the board, i.e. a perfect clear
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(Option::is_none)
+    }
+
+    /// Whether there'd be nothing left on the board once `rows` are cleared, i.e. a perfect clear
+    pub fn is_empty_without(&self, rows: &[u32]) -> bool {
+        (0..self.h).all(|y| rows.contains(&y) || self.row(y).iter().all(Option::is_none))
+    }
+
+    /// Empties the whole grid
+    pub fn clear(&mut self) {
+        self.cells.fill(None);
+    }
+
+    /// Renders every locked cell that's within the visible board, colored as the pieces of `pieces`
+    pub fn render(
+        &self,
+        ctx: &mut Context,
+        config: &Config,
+        dim: &Dimensions,
+        color_palette: &ColorPalette,
+        pieces: &PieceSet,
+    ) {
+        let size = *config.cell();
+
+        for y in 0..*dim.h().min(&self.h) {
+            for (x, cell) in self.row(y).iter().enumerate() {
+                if let Some(cell) = cell {
+                    ctx.graphics.draw_rectangle(
+                        config.canvas_l() + x as f32 * size,
+                        config.canvas_b() - (y as f32 + 1.0) * size,
+                        size,
+                        size,
+                        color_palette.color_for_cell(*cell, pieces),
+                    )
+                }
+            }
+        }
+    }
+}
+
+// Getters and setters
+impl Playfield {
+    /// Get the playfield's width
+    pub fn w(&self) -> u32 {
+        self.w
+    }
+
+    /// Get the playfield's height, including the buffer
+    pub fn h(&self) -> u32 {
+        self.h
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tetromino::coord::Coord;
+
+    fn playfield() -> Playfield {
+        Playfield::new(&Dimensions::new(4, 4, 4))
+    }
+
+    /// A tetromino of the standard piece with the given name, covering `coords`
+    fn tetromino(name: &str, coords: Vec<Coord>) -> Tetromino {
+        let spawn = coords[0];
+        Tetromino::spawn_tetromino(
+            coords,
+            spawn,
+            PieceSet::standard().find(name).unwrap().clone(),
+        )
+    }
+
+    fn fill_row(playfield: &mut Playfield, y: u32, name: &str) {
+        let coords = (0..playfield.w()).map(|x| Coord::new(x, y)).collect();
+        playfield.lock(&tetromino(name, coords));
+    }
+
+    fn locked(name: &str) -> Option<Cell> {
+        Some(Cell::Tetromino(
+            *PieceSet::standard().find(name).unwrap().tetromino_type(),
+        ))
+    }
+
+    #[test]
+    fn test_lock_and_collide() {
+        let mut playfield = playfield();
+        assert_eq!(playfield.h(), 4 + 4);
+
+        let dropped = tetromino(
+            "O",
+            vec![
+                Coord::new(0, 0),
+                Coord::new(1, 0),
+                Coord::new(0, 1),
+                Coord::new(1, 1),
+            ],
+        );
+        playfield.lock(&dropped);
+
+        assert_eq!(playfield.get(0, 0), locked("O"));
+        assert_eq!(playfield.get(1, 1), locked("O"));
+        assert_eq!(playfield.get(2, 0), None);
+        assert_eq!(playfield.get(-1, 0), None);
+        assert!(dropped.will_collide_diff(&playfield, [0, 0]));
+        assert!(!dropped.will_collide_diff(&playfield, [2, 0]));
+    }
+
+    #[test]
+    fn test_clear_rows_shifts_down() {
+        let mut playfield = playfield();
+        fill_row(&mut playfield, 0, "I");
+        fill_row(&mut playfield, 2, "I");
+        playfield.lock(&tetromino("T", vec![Coord::new(1, 1)]));
+        playfield.lock(&tetromino("S", vec![Coord::new(3, 3)]));
+
+        let rows = playfield.full_rows();
+        assert_eq!(rows, vec![0, 2]);
+        playfield.clear_rows(&rows);
+
+        assert!(playfield.full_rows().is_empty());
+        assert_eq!(playfield.get(1, 0), locked("T"));
+        assert_eq!(playfield.get(3, 1), locked("S"));
+        assert!((2..playfield.h()).all(|y| (0..4).all(|x| !playfield.is_occupied(x, y as i32))));
+    }
+
+    #[test]
+    fn test_add_garbage_pushes_up() {
+        let mut playfield = playfield();
+        fill_row(&mut playfield, 0, "I");
+
+        assert!(!playfield.add_garbage(2, 1));
+        assert_eq!(playfield.get(0, 0), Some(Cell::Garbage));
+        assert_eq!(playfield.get(1, 0), None);
+        assert_eq!(playfield.get(1, 1), None);
+        assert_eq!(playfield.get(1, 2), locked("I"));
+        assert!(playfield.full_rows() == vec![2]);
+
+        // Rows pushed past the top of the buffer top out
+        assert!(playfield.add_garbage(playfield.h() - 2, 0));
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+
+use rand::{seq::SliceRandom, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::tetromino::piece_set::{GuidelineTetromino, PieceSet};
+use crate::tetromino::tetromino_type::TetrominoType;
+
+/// Decides which tetromino type spawns next.
+/// The rng is passed in rather than owned, so that every randomizer draws from the same source
+pub trait Randomizer {
+    /// Picks the next tetromino type to spawn
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetrominoType;
+
+    /// Forgets everything dealt so far, as if the game just started
+    fn reset(&mut self);
+}
+
+/// Which randomizer a game is played with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RandomizerPreset {
+    #[default]
+    #[serde(rename = "7-bag")]
+    SevenBag,
+    #[serde(rename = "14-bag")]
+    FourteenBag,
+    #[serde(rename = "tgm")]
+    TgmHistory,
+    #[serde(rename = "nes")]
+    Nes,
+    #[serde(rename = "uniform")]
+    Uniform,
+}
+
+impl RandomizerPreset {
+    pub const ALL: [RandomizerPreset; 5] = [
+        RandomizerPreset::SevenBag,
+        RandomizerPreset::FourteenBag,
+        RandomizerPreset::TgmHistory,
+        RandomizerPreset::Nes,
+        RandomizerPreset::Uniform,
+    ];
+
+    /// Builds the randomizer for dealing out the pieces of `pieces`.
+    /// The bags hold one or two of every piece, whatever size the set is
+    pub fn build(&self, pieces: &PieceSet) -> Box<dyn Randomizer> {
+        let types = pieces.types();
+        match self {
+            RandomizerPreset::SevenBag => Box::new(Bag::new(1, types)),
+            RandomizerPreset::FourteenBag => Box::new(Bag::new(2, types)),
+            RandomizerPreset::TgmHistory => Box::new(TgmHistory::new(4, pieces)),
+            RandomizerPreset::Nes => Box::new(Nes::new(types)),
+            RandomizerPreset::Uniform => Box::new(Uniform::new(types)),
+        }
+    }
+
+    /// Short name used in files
+    pub fn name(&self) -> &'static str {
+        match self {
+            RandomizerPreset::SevenBag => "7-bag",
+            RandomizerPreset::FourteenBag => "14-bag",
+            RandomizerPreset::TgmHistory => "tgm",
+            RandomizerPreset::Nes => "nes",
+            RandomizerPreset::Uniform => "uniform",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+}
+
+/// Picks one of `types` with equal chance.
+/// Rolls a `u32`, which draws the same from the rng as rolling for one of the 7 tetrominoes always has
+fn roll(types: &[TetrominoType], rng: &mut dyn RngCore) -> TetrominoType {
+    types[rng.gen_range(0..types.len() as u32) as usize]
+}
+
+/// Samples every type with equal chance, independent of anything that came before
+pub struct Uniform {
+    types: Vec<TetrominoType>,
+}
+
+impl Uniform {
+    pub fn new(types: Vec<TetrominoType>) -> Self {
+        Self { types }
+    }
+}
+
+impl Randomizer for Uniform {
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetrominoType {
+        roll(&self.types, rng)
+    }
+
+    fn reset(&mut self) {}
+}
+
+/// Deals out a shuffled bag holding `copies` of each type, then refills it once it's empty.
+/// One copy of the standard tetrominoes is the Guideline 7-bag, two is the 14-bag
+pub struct Bag {
+    copies: usize,
+    types: Vec<TetrominoType>,
+    bag: Vec<TetrominoType>,
+}
+
+impl Bag {
+    pub fn new(copies: usize, types: Vec<TetrominoType>) -> Self {
+        Self {
+            copies: copies.max(1),
+            types,
+            bag: Vec::new(),
+        }
+    }
+
+    /// Guideline 7-bag
+    pub fn seven() -> Self {
+        Self::new(1, PieceSet::standard().types())
+    }
+
+    /// 14-bag, two of each type per bag
+    pub fn fourteen() -> Self {
+        Self::new(2, PieceSet::standard().types())
+    }
+}
+
+impl Default for Bag {
+    fn default() -> Self {
+        Self::seven()
+    }
+}
+
+impl Randomizer for Bag {
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetrominoType {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend_from_slice(&self.types);
+            }
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().unwrap()
+    }
+
+    fn reset(&mut self) {
+        self.bag.clear();
+    }
+}
+
+/// TGM style randomizer, which remembers the last 4 types and rolls up to `rolls` times
+/// for one that isn't in that history. The last roll is kept no matter what.
+/// The first piece is never an S, Z or O, so a game never starts with an overhang
+pub struct TgmHistory {
+    types: Vec<TetrominoType>,
+    // Types the first piece can be
+    openers: Vec<TetrominoType>,
+    history: VecDeque<TetrominoType>,
+    rolls: u32,
+    first: bool,
+}
+
+impl TgmHistory {
+    pub fn new(rolls: u32, pieces: &PieceSet) -> Self {
+        let types = pieces.types();
+        let mut openers: Vec<TetrominoType> = pieces
+            .pieces()
+            .iter()
+            .filter(|p| {
+                !matches!(
+                    p.tetromino(),
+                    Some(GuidelineTetromino::S | GuidelineTetromino::Z | GuidelineTetromino::O)
+                )
+            })
+            .map(|p| *p.tetromino_type())
+            .collect();
+        // Sets made of nothing but those can start with anything
+        if openers.is_empty() {
+            openers = types.clone();
+        }
+        Self {
+            // The history starts full of the set's last piece, Z's for the standard set as in the original TGM
+            history: VecDeque::from([*types.last().unwrap(); 4]),
+            types,
+            openers,
+            rolls: rolls.max(1),
+            first: true,
+        }
+    }
+}
+
+impl Default for TgmHistory {
+    /// 4 rolls, as in the original TGM
+    fn default() -> Self {
+        Self::new(4, &PieceSet::standard())
+    }
+}
+
+impl Randomizer for TgmHistory {
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetrominoType {
+        let next = if self.first {
+            self.first = false;
+            *self.openers.choose(rng).unwrap()
+        } else {
+            let mut next = roll(&self.types, rng);
+            for _ in 1..self.rolls {
+                if !self.history.contains(&next) {
+                    break;
+                }
+                next = roll(&self.types, rng);
+            }
+            next
+        };
+
+        self.history.pop_front();
+        self.history.push_back(next);
+        next
+    }
+
+    fn reset(&mut self) {
+        self.history = VecDeque::from([*self.types.last().unwrap(); 4]);
+        self.first = true;
+    }
+}
+
+/// NES style randomizer. Rolls an 8 sided die where the 8th side means "reroll",
+/// and rerolls once (with a 7 sided die) if it lands on the previous type.
+/// Other sets get a die with a side per type plus the reroll
+pub struct Nes {
+    types: Vec<TetrominoType>,
+    previous: Option<TetrominoType>,
+}
+
+impl Nes {
+    pub fn new(types: Vec<TetrominoType>) -> Self {
+        Self {
+            types,
+            previous: None,
+        }
+    }
+}
+
+impl Default for Nes {
+    fn default() -> Self {
+        Self::new(PieceSet::standard().types())
+    }
+}
+
+impl Randomizer for Nes {
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetrominoType {
+        let die = rng.gen_range(0..=self.types.len());
+        let next = match self.types.get(die) {
+            Some(&t) if Some(t) != self.previous => t,
+            _ => roll(&self.types, rng),
+        };
+        self.previous = Some(next);
+        next
+    }
+
+    fn reset(&mut self) {
+        self.previous = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn sorted(types: &[TetrominoType]) -> Vec<usize> {
+        let mut idxs: Vec<usize> = types.iter().map(TetrominoType::index).collect();
+        idxs.sort_unstable();
+        idxs
+    }
+
+    #[test]
+    fn test_seven_bag_deals_each_type_once() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut bag = Bag::seven();
+        for _ in 0..10 {
+            let dealt: Vec<TetrominoType> = (0..7).map(|_| bag.next(&mut rng)).collect();
+            assert_eq!(sorted(&dealt), (0..7).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn test_fourteen_bag_deals_each_type_twice() {
+        let mut rng = StdRng::seed_from_u64(14);
+        let mut bag = Bag::fourteen();
+        let dealt: Vec<TetrominoType> = (0..14).map(|_| bag.next(&mut rng)).collect();
+        let expected: Vec<usize> = (0..7).flat_map(|i| [i, i]).collect();
+        assert_eq!(sorted(&dealt), expected);
+    }
+
+    #[test]
+    fn test_tgm_never_starts_with_overhang() {
+        for seed in 0..100 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let first = TgmHistory::default().next(&mut rng);
+            let standard = PieceSet::standard();
+            assert!(!["S", "Z", "O"].contains(&standard.piece(first).name()));
+        }
+    }
+
+    #[test]
+    fn test_same_seed_deals_same_sequence() {
+        use rand_chacha::ChaCha8Rng;
+
+        let deal = |seed| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let mut bag = Bag::seven();
+            (0..50).map(|_| bag.next(&mut rng)).collect::<Vec<_>>()
+        };
+        assert_eq!(deal(42), deal(42));
+        assert_ne!(deal(42), deal(43));
+    }
+
+    #[test]
+    fn test_bags_hold_every_piece_of_the_set() {
+        let mut rng = StdRng::seed_from_u64(18);
+        let pentomino = PieceSet::load("pentomino").unwrap();
+        let mut bag = RandomizerPreset::SevenBag.build(&pentomino);
+        let dealt: Vec<TetrominoType> = (0..18).map(|_| bag.next(&mut rng)).collect();
+        assert_eq!(sorted(&dealt), (0..18).collect::<Vec<usize>>());
+    }
+}
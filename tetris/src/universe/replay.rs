@@ -0,0 +1,647 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thomas::context::Context;
+
+use super::entry_delay::EntryDelay;
+use super::lock_delay::LockReset;
+use super::mode::GameMode;
+use super::randomizer::RandomizerPreset;
+use super::rotations::RotationPreset;
+use super::scoring::ScoringPreset;
+use super::simulation::{GameAction, Rules, Simulation};
+use super::Universe;
+use crate::config::Config;
+use crate::files;
+use crate::tetromino::piece_set::PieceSet;
+
+// First line of every replay file
+pub(super) const HEADER: &str = "tetris-replay";
+// Goes up whenever the simulation changes in a way that plays older replays out differently
+const VERSION: u32 = 3;
+// Where replays are saved to, in the data directory
+const REPLAY_DIR: &str = "replays";
+// Ticks played per tick while fast-forwarding
+const FAST_FORWARD: u32 = 4;
+
+/// Every action that can end up in a replay, along with the character it's stored as.
+/// Restarting isn't in here, since a restart starts a new recording anyways
+const ACTIONS: [(GameAction, char); 10] = [
+    (GameAction::MoveLeft, '<'),
+    (GameAction::MoveRight, '>'),
+    (GameAction::SoftDrop, 'v'),
+    (GameAction::HardDrop, '_'),
+    (GameAction::RotateCW, 'x'),
+    (GameAction::RotateCCW, 'z'),
+    (GameAction::Rotate180, 'u'),
+    (GameAction::Hold, 'c'),
+    (GameAction::Pause, 'p'),
+    (GameAction::Retry, '!'),
+];
+
+fn encode(action: GameAction) -> Option<char> {
+    ACTIONS.iter().find(|(a, _)| *a == action).map(|(_, c)| *c)
+}
+
+fn decode(c: char) -> Option<GameAction> {
+    ACTIONS
+        .iter()
+        .find(|(_, a)| *a == c)
+        .map(|(action, _)| *action)
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    /// Something's wrong on the given line (starting at 1) of a replay file
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(e: std::io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+/// A whole game, as the seed and rules it started from plus every action on every tick.
+/// Since the universe is deterministic, that's enough to play the game out exactly as it happened.
+/// Key presses are kept too, so that the statistics and finesse come out the same
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    seed: u64,
+    rules: Rules,
+    // Tick, action on that tick. Sorted by tick
+    events: Vec<(u32, GameAction)>,
+    // Tick, key pressed for that tick. Sorted by tick
+    presses: Vec<(u32, GameAction)>,
+    // Whether turning 180 degrees counted as a single press when judging finesse
+    half_turns: bool,
+    // Ticks recorded
+    length: u32,
+}
+
+/// The actions of one tick of a replay, along with the key presses behind them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayTick {
+    pub actions: Vec<GameAction>,
+    pub presses: Vec<GameAction>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, rules: Rules) -> Self {
+        Self {
+            seed,
+            rules,
+            events: Vec::new(),
+            presses: Vec::new(),
+            half_turns: true,
+            length: 0,
+        }
+    }
+
+    /// Records the actions for the next tick
+    pub fn record(&mut self, actions: &[GameAction]) {
+        for &action in actions {
+            if encode(action).is_some() {
+                self.events.push((self.length, action));
+            }
+        }
+        self.length += 1;
+    }
+
+    /// Records the keys pressed for the next tick, ahead of its actions
+    pub fn record_presses(&mut self, presses: &[GameAction]) {
+        for &press in presses {
+            if encode(press).is_some() {
+                self.presses.push((self.length, press));
+            }
+        }
+    }
+
+    /// Records that the last tick's lock was tried again, ahead of the tick's other actions so that it's there when the lock comes
+    pub fn record_retry(&mut self) {
+        let tick = self.length.saturating_sub(1);
+        let at = self
+            .events
+            .iter()
+            .position(|&(t, _)| t == tick)
+            .unwrap_or(self.events.len());
+        self.events.insert(at, (tick, GameAction::Retry));
+    }
+
+    /// Plays the whole replay out without a window, e.g. to check what it ends up at
+    pub fn simulate(&self) -> Simulation {
+        let mut sim = Simulation::new(self.rules.clone(), Some(self.seed));
+        sim.set_finesse(false, self.half_turns);
+        let mut player = ReplayPlayer::new(self.clone());
+        while let Some(tick) = player.next_tick() {
+            sim.press(&tick.presses);
+            sim.tick(&tick.actions);
+        }
+        sim
+    }
+
+    /// Where replays are saved by default, in the platform's data directory
+    pub fn default_dir() -> Option<PathBuf> {
+        files::data_dir().map(|dir| dir.join(REPLAY_DIR))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+// Getters and setters
+impl Replay {
+    /// Get the seed the game started from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Get the rules the game was played under
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Get the number of ticks recorded
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// Get whether turning 180 degrees counted as a single press when judging finesse
+    pub fn half_turns(&self) -> bool {
+        self.half_turns
+    }
+
+    /// Set whether turning 180 degrees counts as a single press when judging finesse
+    pub fn set_half_turns(&mut self, half_turns: bool) {
+        self.half_turns = half_turns;
+    }
+}
+
+/// A header of the seed and rules, one `name value` per line,
+/// followed by a line per tick with any actions or presses: ticks since the last such line, then a character per action,
+/// then a `+` and a character per press
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "ticks {}", self.rules.ticks)?;
+        writeln!(f, "scoring {}", self.rules.scoring.name())?;
+        writeln!(f, "randomizer {}", self.rules.randomizer.name())?;
+        writeln!(f, "rotation {}", self.rules.rotation.name())?;
+        writeln!(f, "pieces {}", self.rules.pieces.source())?;
+        writeln!(f, "lock-delay {}", self.rules.lock_delay)?;
+        match self.rules.lock_reset {
+            LockReset::Move { max_resets } => writeln!(f, "lock-reset move {}", max_resets)?,
+            LockReset::Step => writeln!(f, "lock-reset step")?,
+            LockReset::None => writeln!(f, "lock-reset none")?,
+        }
+        let delay = self.rules.entry_delay;
+        writeln!(
+            f,
+            "entry-delay {} {} {} {}",
+            delay.are,
+            delay.line_clear_are,
+            delay.line_clear_delay,
+            if delay.buffer {
+                "buffered"
+            } else {
+                "unbuffered"
+            }
+        )?;
+        writeln!(f, "previews {}", self.rules.previews)?;
+        writeln!(f, "mode {}", self.rules.mode.name())?;
+        writeln!(
+            f,
+            "board {} {} {}",
+            self.rules.width, self.rules.height, self.rules.buffer
+        )?;
+        writeln!(
+            f,
+            "half-turns {}",
+            if self.half_turns { "on" } else { "off" }
+        )?;
+        writeln!(f, "length {}", self.length)?;
+
+        // Actions then presses, for every tick that has either
+        let mut ticks: BTreeMap<u32, (String, String)> = BTreeMap::new();
+        for &(tick, action) in self.events.iter() {
+            let (actions, _) = ticks.entry(tick).or_default();
+            actions.push(encode(action).unwrap());
+        }
+        for &(tick, press) in self.presses.iter() {
+            let (_, presses) = ticks.entry(tick).or_default();
+            presses.push(encode(press).unwrap());
+        }
+        let mut last = 0;
+        for (tick, (actions, presses)) in ticks {
+            write!(f, "{}", tick - last)?;
+            if !actions.is_empty() {
+                write!(f, " {}", actions)?;
+            }
+            if !presses.is_empty() {
+                write!(f, " +{}", presses)?;
+            }
+            writeln!(f)?;
+            last = tick;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ReplayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate();
+        let error = |line: usize, message: String| ReplayError::Parse {
+            line: line + 1,
+            message,
+        };
+
+        match lines.next() {
+            Some((_, first)) if first == format!("{} {}", HEADER, VERSION) => {}
+            _ => return Err(error(0, format!("not a version {} replay file", VERSION))),
+        }
+
+        let mut seed = None;
+        let mut rules = Rules::default();
+        let mut replay_length = 0;
+        let mut events = Vec::new();
+        let mut presses = Vec::new();
+        let mut half_turns = true;
+        let mut tick = 0;
+
+        for (i, line) in lines {
+            let mut words = line.split_whitespace();
+            let Some(name) = words.next() else {
+                continue;
+            };
+            let value = words.next().unwrap_or_default();
+            let number = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| error(i, format!("`{}` isn't a number", value)))
+            };
+
+            // Lines starting with a number are ticks, everything else is a header
+            if let Ok(delta) = name.parse::<u32>() {
+                tick += delta;
+                for word in std::iter::once(value).chain(words) {
+                    let (codes, list) = match word.strip_prefix('+') {
+                        Some(codes) => (codes, &mut presses),
+                        None => (word, &mut events),
+                    };
+                    for c in codes.chars() {
+                        let action =
+                            decode(c).ok_or_else(|| error(i, format!("unknown action `{}`", c)))?;
+                        list.push((tick, action));
+                    }
+                }
+                continue;
+            }
+            match name {
+                "seed" => seed = Some(number(value)?),
+                "ticks" => rules.ticks = number(value)? as u32,
+                "scoring" => {
+                    rules.scoring = ScoringPreset::from_name(value)
+                        .ok_or_else(|| error(i, format!("unknown scoring `{}`", value)))?
+                }
+                "randomizer" => {
+                    rules.randomizer = RandomizerPreset::from_name(value)
+                        .ok_or_else(|| error(i, format!("unknown randomizer `{}`", value)))?
+                }
+                "rotation" => {
+                    rules.rotation = RotationPreset::from_name(value)
+                        .ok_or_else(|| error(i, format!("unknown rotation system `{}`", value)))?
+                }
+                // The rest of the line, since piece files can have spaces in their path
+                "pieces" => {
+                    let source = line.trim_start()[name.len()..].trim();
+                    rules.pieces = PieceSet::load(source)
+                        .map_err(|e| error(i, format!("can't load pieces `{}`: {}", source, e)))?
+                }
+                "lock-delay" => rules.lock_delay = number(value)? as u32,
+                "lock-reset" => {
+                    rules.lock_reset = match value {
+                        "move" => LockReset::Move {
+                            max_resets: number(words.next().unwrap_or_default())? as u32,
+                        },
+                        "step" => LockReset::Step,
+                        "none" => LockReset::None,
+                        _ => return Err(error(i, format!("unknown lock reset `{}`", value))),
+                    }
+                }
+                "entry-delay" => {
+                    let mut ticks = || number(words.next().unwrap_or_default());
+                    let (are, line_clear_are) = (number(value)? as u32, ticks()? as u32);
+                    let line_clear_delay = ticks()? as u32;
+                    let buffer = match words.next().unwrap_or_default() {
+                        "buffered" => true,
+                        "unbuffered" => false,
+                        other => return Err(error(i, format!("unknown buffering `{}`", other))),
+                    };
+                    rules.entry_delay = EntryDelay {
+                        are,
+                        line_clear_are,
+                        line_clear_delay,
+                        buffer,
+                    };
+                }
+                "previews" => rules.previews = number(value)? as usize,
+                "board" => {
+                    rules.width = number(value)? as u32;
+                    rules.height = number(words.next().unwrap_or_default())? as u32;
+                    rules.buffer = number(words.next().unwrap_or_default())? as u32;
+                }
+                "mode" => {
+                    rules.mode = GameMode::from_name(value)
+                        .ok_or_else(|| error(i, format!("unknown mode `{}`", value)))?
+                }
+                "half-turns" => {
+                    half_turns = match value {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err(error(i, format!("unknown half turns `{}`", value))),
+                    }
+                }
+                "length" => replay_length = number(value)? as u32,
+                _ => return Err(error(i, format!("unknown field `{}`", name))),
+            }
+        }
+
+        let seed = seed.ok_or_else(|| error(0, String::from("missing seed")))?;
+        // Without a length, play up to the last action or press
+        let last = events
+            .last()
+            .into_iter()
+            .chain(presses.last())
+            .map(|&(tick, _)| tick + 1)
+            .max()
+            .unwrap_or_default();
+        Ok(Self {
+            seed,
+            rules,
+            events,
+            presses,
+            half_turns,
+            length: replay_length.max(last),
+        })
+    }
+}
+
+/// Feeds a replay's actions back tick by tick
+pub struct ReplayPlayer {
+    replay: Replay,
+    // Next tick to play
+    tick: u32,
+    // Index of the first event on or after `tick`
+    cursor: usize,
+    // Index of the first press on or after `tick`
+    press_cursor: usize,
+    paused: bool,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            tick: 0,
+            cursor: 0,
+            press_cursor: 0,
+            paused: false,
+        }
+    }
+
+    /// Actions and presses recorded for the next tick, `None` once the whole replay has played
+    pub fn next_tick(&mut self) -> Option<ReplayTick> {
+        if self.is_finished() {
+            return None;
+        }
+        let mut next = ReplayTick::default();
+        while let Some(&(tick, action)) = self.replay.events.get(self.cursor) {
+            if tick != self.tick {
+                break;
+            }
+            next.actions.push(action);
+            self.cursor += 1;
+        }
+        while let Some(&(tick, press)) = self.replay.presses.get(self.press_cursor) {
+            if tick != self.tick {
+                break;
+            }
+            next.presses.push(press);
+            self.press_cursor += 1;
+        }
+        self.tick += 1;
+        Some(next)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.replay.length
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Get whether playback is paused
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Get the replay being played
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+}
+
+impl Universe {
+    /// Plays back a recorded game, under the rules it was recorded with
+    pub fn from_replay(ctx: &mut Context, mut config: Config, replay: Replay) -> Self {
+        replay.rules().apply(&mut config);
+        let mut universe = Universe::new(ctx, config);
+        universe.sim_mut().start(replay.seed());
+        universe.sim_mut().set_finesse(false, replay.half_turns());
+        universe.playback = Some(ReplayPlayer::new(replay));
+        universe
+    }
+
+    /// Saves the game recorded since the last (re)start under the replay directory, returning where it went
+    pub(super) fn save_replay(&mut self) -> Result<PathBuf, ReplayError> {
+        let recording = self.sim().recording();
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let dir = Replay::default_dir().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no data directory to save to")
+        })?;
+        let path = dir.join(format!("{}-{}.replay", time, recording.seed()));
+        recording.save(&path)?;
+        Ok(path)
+    }
+
+    /// Plays back ticks from the replay instead of the keyboard.
+    /// Space pauses, down steps through a paused replay, and holding right fast-forwards
+    pub(super) fn tick_playback(&mut self, ctx: &mut Context) {
+        let toggle = self.playback_controls.toggle(ctx);
+        let step = self.playback_controls.step(ctx);
+        let fast_forward = self.playback_controls.fast_forward(ctx);
+
+        let Some(player) = self.playback.as_mut() else {
+            return;
+        };
+        if toggle {
+            player.toggle_pause();
+        }
+        let ticks = match (player.paused(), fast_forward) {
+            (true, _) => step as u32,
+            (false, true) => FAST_FORWARD,
+            (false, false) => 1,
+        };
+
+        for _ in 0..ticks {
+            let Some(tick) = self.playback.as_mut().and_then(ReplayPlayer::next_tick) else {
+                break;
+            };
+            self.sim.press(&tick.presses);
+            self.step(ctx, &tick.actions);
+        }
+    }
+
+    /// Renders the playback state in the left letterbox
+    pub(super) fn render_playback(&self, ctx: &mut Context, player: &ReplayPlayer) {
+        let status = if player.is_finished() {
+            String::from("END")
+        } else if player.paused() {
+            String::from("PAUSED")
+        } else if self.playback_controls.fast_forward(ctx) {
+            format!("x{}", FAST_FORWARD)
+        } else {
+            String::from("PLAYING")
+        };
+        ctx.graphics.draw_text(
+            &format!("REPLAY {}", status),
+            self.config.hud_l(),
+            450.0,
+            self.color_palette.line().into(),
+            30.0,
+        );
+        ctx.graphics.draw_text(
+            "space: pause  down: step  right: fast-forward",
+            self.config.hud_l(),
+            500.0,
+            self.color_palette.line().into(),
+            20.0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::entry_delay::TimingPreset;
+    use super::*;
+
+    fn replay() -> Replay {
+        let mut replay = Replay::new(
+            42,
+            Rules {
+                lock_reset: LockReset::Step,
+                entry_delay: TimingPreset::Tgm.entry_delay(60),
+                randomizer: RandomizerPreset::TgmHistory,
+                mode: GameMode::Sprint,
+                width: 6,
+                height: 30,
+                pieces: PieceSet::load("pentomino").unwrap(),
+                ..Rules::default()
+            },
+        );
+        replay.record(&[]);
+        replay.record_presses(&[GameAction::MoveLeft]);
+        replay.record(&[GameAction::MoveLeft, GameAction::RotateCCW]);
+        replay.record(&[GameAction::Restart]);
+        replay.record(&[GameAction::HardDrop]);
+        // Pressed while nothing happened, e.g. a pause that came too late
+        replay.record_presses(&[GameAction::Pause]);
+        replay.record(&[]);
+        replay.set_half_turns(false);
+        replay
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let replay = replay();
+        let text = replay.to_string();
+        assert!(text.ends_with("1 <z +<\n2 _\n1 +p\n"));
+        assert_eq!(text.parse::<Replay>().unwrap(), replay);
+    }
+
+    #[test]
+    fn test_player_feeds_ticks_back() {
+        let mut player = ReplayPlayer::new(replay());
+        let tick = |actions: &[GameAction], presses: &[GameAction]| {
+            Some(ReplayTick {
+                actions: actions.to_vec(),
+                presses: presses.to_vec(),
+            })
+        };
+        assert_eq!(player.next_tick(), tick(&[], &[]));
+        assert_eq!(
+            player.next_tick(),
+            tick(
+                &[GameAction::MoveLeft, GameAction::RotateCCW],
+                &[GameAction::MoveLeft]
+            )
+        );
+        // Restarts aren't recorded
+        assert_eq!(player.next_tick(), tick(&[], &[]));
+        assert_eq!(player.next_tick(), tick(&[GameAction::HardDrop], &[]));
+        assert_eq!(player.next_tick(), tick(&[], &[GameAction::Pause]));
+        assert!(player.is_finished());
+        assert_eq!(player.next_tick(), None);
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_line() {
+        let text = "tetris-replay 3\nseed 1\nscoring tetris99\n";
+        match text.parse::<Replay>() {
+            Err(ReplayError::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        // Replays from before the delays between tetrominos were recorded play out differently
+        assert!(matches!(
+            "tetris-replay 2\nseed 1\n".parse::<Replay>(),
+            Err(ReplayError::Parse { line: 1, .. })
+        ));
+    }
+}
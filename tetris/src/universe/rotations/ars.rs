@@ -0,0 +1,252 @@
+use super::*;
+use crate::tetromino::coord::Coord;
+
+// Cells of each tetromino in each rotation state, bottom left of the box at (0, 0).
+// Flat states rest on the bottom of the box rather than turning about its middle,
+// and J, L and T spawn pointing down with their flat side up
+const T_SHAPES: Shapes = [
+    [[1, 1], [0, 1], [2, 1], [1, 0]],
+    [[1, 1], [1, 2], [1, 0], [0, 1]],
+    [[1, 0], [0, 0], [2, 0], [1, 1]],
+    [[1, 1], [1, 2], [1, 0], [2, 1]],
+];
+const J_SHAPES: Shapes = [
+    [[1, 1], [0, 1], [2, 1], [2, 0]],
+    [[1, 1], [1, 2], [1, 0], [0, 0]],
+    [[1, 0], [0, 0], [2, 0], [0, 1]],
+    [[1, 1], [1, 2], [1, 0], [2, 2]],
+];
+const L_SHAPES: Shapes = [
+    [[1, 1], [0, 1], [2, 1], [0, 0]],
+    [[1, 1], [1, 2], [1, 0], [0, 2]],
+    [[1, 0], [0, 0], [2, 0], [2, 1]],
+    [[1, 1], [1, 2], [1, 0], [2, 0]],
+];
+// S, Z and I only have two states. Standing up, S leans left, Z leans right and I sits right of the middle
+const S_SHAPES: Shapes = [
+    [[1, 0], [0, 0], [1, 1], [2, 1]],
+    [[0, 1], [0, 2], [1, 1], [1, 0]],
+    [[1, 0], [0, 0], [1, 1], [2, 1]],
+    [[0, 1], [0, 2], [1, 1], [1, 0]],
+];
+const Z_SHAPES: Shapes = [
+    [[1, 0], [2, 0], [0, 1], [1, 1]],
+    [[1, 1], [2, 1], [2, 2], [1, 0]],
+    [[1, 0], [2, 0], [0, 1], [1, 1]],
+    [[1, 1], [2, 1], [2, 2], [1, 0]],
+];
+const I_SHAPES: Shapes = [
+    [[1, 2], [0, 2], [2, 2], [3, 2]],
+    [[2, 2], [2, 3], [2, 1], [2, 0]],
+    [[1, 2], [0, 2], [2, 2], [3, 2]],
+    [[2, 2], [2, 3], [2, 1], [2, 0]],
+];
+const O_SHAPES: Shapes = [[[0, 0], [1, 0], [0, 1], [1, 1]]; 4];
+
+// In place, then a column right, then a column left
+const KICKS: [[i32; 2]; 3] = [[0, 0], [1, 0], [-1, 0]];
+// Floor kicks from TGM3, tried once nothing else fits: a row up for T, and one or two rows for I standing up
+const T_FLOOR_KICKS: [[i32; 2]; 1] = [[0, 1]];
+const I_FLOOR_KICKS: [[i32; 2]; 2] = [[0, 1], [0, 2]];
+
+/// The arcade shapes of a tetromino, or `None` for pieces that aren't one and turn through their own states
+fn shapes(piece: &Piece) -> Option<&'static Shapes> {
+    match piece.tetromino()? {
+        GuidelineTetromino::I => Some(&I_SHAPES),
+        GuidelineTetromino::J => Some(&J_SHAPES),
+        GuidelineTetromino::L => Some(&L_SHAPES),
+        GuidelineTetromino::O => Some(&O_SHAPES),
+        GuidelineTetromino::S => Some(&S_SHAPES),
+        GuidelineTetromino::T => Some(&T_SHAPES),
+        GuidelineTetromino::Z => Some(&Z_SHAPES),
+    }
+}
+
+/// The Arika Rotation System from the arcade games, which only kicks a column to either side, and never for I.
+/// T and I can also kick up off the floor once a tetromino, as in TGM3.
+/// Pieces other than the tetrominoes kick like J, L, S, T and Z if they're on that kick table, and not at all otherwise
+pub struct Ars;
+
+impl RotationSystem for Ars {
+    fn candidates(&self, tetromino: &Tetromino, direction: RotationDirection) -> Vec<Tetromino> {
+        let piece = tetromino.piece();
+        let shapes = shapes(piece);
+        let turned = reshape(tetromino, direction, |state| {
+            shapes.map_or_else(|| piece.state(state), |shapes| &shapes[state as usize][..])
+        });
+        let mut candidates = match piece.kicks() {
+            KickTable::Jlstz => kicked(turned.clone(), &KICKS),
+            _ => vec![turned.clone()],
+        };
+        if tetromino.floor_kicked() {
+            return candidates;
+        }
+        // I only kicks up as it stands up from lying flat
+        let lying = (*tetromino.rotation_state().rn()).is_multiple_of(2);
+        let floor_kicks: &[[i32; 2]] = match piece.tetromino() {
+            Some(GuidelineTetromino::T) => &T_FLOOR_KICKS,
+            Some(GuidelineTetromino::I) if lying && direction != RotationDirection::Half => {
+                &I_FLOOR_KICKS
+            }
+            _ => &[],
+        };
+        candidates.extend(kicked(turned, floor_kicks).into_iter().map(|mut kicked| {
+            kicked.set_floor_kicked(true);
+            kicked
+        }));
+        candidates
+    }
+
+    /// The center column rule: J, L and T don't kick if the first cell in their way,
+    /// reading their box from the top left, is in its middle column
+    fn refuses_kicks(&self, turned: &Tetromino, playfield: &Playfield, dim: &Dimensions) -> bool {
+        if !matches!(
+            turned.piece().tetromino(),
+            Some(GuidelineTetromino::J | GuidelineTetromino::L | GuidelineTetromino::T)
+        ) {
+            return false;
+        }
+        // Off the side of the board, which includes anything that wrapped around past the left
+        let blocked = |c: &&Coord| c.x >= *dim.w() || playfield.is_occupied(c.x as i32, c.y as i32);
+        // J, L and T put their first cell in the middle column of their box in every state
+        let middle = turned.coords()[0].x;
+        let mut in_the_way: Vec<&Coord> = turned.coords().iter().filter(blocked).collect();
+        // Top row first, then left to right
+        in_the_way.sort_by_key(|c| (std::cmp::Reverse(c.y), c.x));
+        in_the_way.first().is_some_and(|c| c.x == middle)
+    }
+
+    fn spawn_shape(&self, piece: &Piece) -> Option<&'static [[u32; 2]]> {
+        shapes(piece).map(|shapes| &shapes[0][..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::{board, cells, diagram, in_state};
+    use super::*;
+
+    #[test]
+    fn test_ars_matches_the_published_states() {
+        // States in the order they turn clockwise from spawning, as drawn for the arcade games
+        let published: [(&str, [[&str; 3]; 4]); 4] = [
+            (
+                "T",
+                [
+                    ["...", "###", ".#."],
+                    [".#.", "##.", ".#."],
+                    ["...", ".#.", "###"],
+                    [".#.", ".##", ".#."],
+                ],
+            ),
+            (
+                "J",
+                [
+                    ["...", "###", "..#"],
+                    [".#.", ".#.", "##."],
+                    ["...", "#..", "###"],
+                    [".##", ".#.", ".#."],
+                ],
+            ),
+            (
+                "L",
+                [
+                    ["...", "###", "#.."],
+                    ["##.", ".#.", ".#."],
+                    ["...", "..#", "###"],
+                    [".#.", ".#.", ".##"],
+                ],
+            ),
+            (
+                "S",
+                [
+                    ["...", ".##", "##."],
+                    ["#..", "##.", ".#."],
+                    ["...", ".##", "##."],
+                    ["#..", "##.", ".#."],
+                ],
+            ),
+        ];
+        for (name, states) in published {
+            let spawned = cells(&in_state(name, 0, &Ars));
+            let reference = diagram(&states[0]);
+            let origin = [
+                spawned[0][0] - reference[0][0],
+                spawned[0][1] - reference[0][1],
+            ];
+            for (state, rows) in states.iter().enumerate() {
+                let mut expected: Vec<[i32; 2]> = diagram(rows)
+                    .iter()
+                    .map(|[x, y]| [x + origin[0], y + origin[1]])
+                    .collect();
+                expected.sort();
+                assert_eq!(
+                    cells(&in_state(name, state as u32, &Ars)),
+                    expected,
+                    "{} in state {}",
+                    name,
+                    state
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ars_kicks_and_the_center_column_rule() {
+        let (mut playfield, dim) = board();
+        // A T pointing right against the left wall kicks a column right to point down
+        let mut t = in_state("T", 3, &Ars);
+        let left = t.coords().iter().map(|c| c.x).min().unwrap() as i32;
+        t.move_by([-left, 0]);
+        let (turned, kick) =
+            rotate(&t, RotationDirection::Clockwise, &playfield, &dim, &Ars).unwrap();
+        assert_eq!(kick, 1);
+        assert_eq!(*turned.rotation_state().rn(), 0);
+
+        // An I never kicks to the side
+        let mut i = in_state("I", 1, &Ars);
+        let right = i.coords()[0].x as i32;
+        i.move_by([9 - right, 0]);
+        assert!(rotate(&i, RotationDirection::Clockwise, &playfield, &dim, &Ars).is_none());
+
+        // A T blocked in the middle column of its box can't kick out of it
+        let t = in_state("T", 0, &Ars);
+        let center = t.coords()[0];
+        playfield.lock(&{
+            let mut block = in_state("O", 0, &Ars);
+            let corner = block.coords()[0];
+            block.move_by([
+                center.x as i32 - corner.x as i32 - 1,
+                center.y as i32 - corner.y as i32 + 1,
+            ]);
+            block
+        });
+        assert!(rotate(&t, RotationDirection::Clockwise, &playfield, &dim, &Ars).is_none());
+    }
+
+    #[test]
+    fn test_ars_floor_kicks_once() {
+        let (mut playfield, dim) = board();
+        // A T pointing up, lying on the floor
+        let mut t = in_state("T", 2, &Ars);
+        t.move_by([0, -(t.lowest_y() as i32)]);
+        let [x, y] = [t.coords()[0].x as i32 - 1, t.coords()[0].y as i32];
+        // Blocks turning to point right in place and a column either side, but not a row up
+        for [dx, dy] in [[2, 1], [0, 2]] {
+            let mut block = t.clone();
+            for coord in block.coords_mut() {
+                *coord = Coord::new((x + dx) as u32, (y + dy) as u32);
+            }
+            playfield.lock(&block);
+        }
+        let (kicked, kick) =
+            rotate(&t, RotationDirection::Clockwise, &playfield, &dim, &Ars).unwrap();
+        assert_eq!(kick, 3);
+        assert_eq!(kicked.lowest_y(), 1);
+
+        // It doesn't get to do that again
+        let mut again = t.clone();
+        again.set_floor_kicked(true);
+        assert!(rotate(&again, RotationDirection::Clockwise, &playfield, &dim, &Ars).is_none());
+    }
+}
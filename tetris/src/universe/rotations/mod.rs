@@ -1,93 +1,317 @@
+use serde::{Deserialize, Serialize};
+
+use super::playfield::Playfield;
 use super::*;
+use crate::tetromino::piece_set::{GuidelineTetromino, KickTable, Piece, PieceSet};
+use crate::tetromino::tetromino_type::TetrominoType;
+pub mod ars;
+pub mod nrs;
 pub mod rotation_direction;
+pub mod srs;
+
+use ars::Ars;
+use nrs::Nrs;
+use srs::{Srs, SrsPlus};
+
+/// Decides where a tetromino can end up when it's rotated
+pub trait RotationSystem {
+    /// Every position `tetromino` could end up in turning `direction`, in the order they're tried.
+    /// The first is turning in place, the rest are kicks
+    fn candidates(&self, tetromino: &Tetromino, direction: RotationDirection) -> Vec<Tetromino>;
+
+    /// Whether kicking is off the table once turning in place into `turned` is blocked
+    fn refuses_kicks(
+        &self,
+        _turned: &Tetromino,
+        _playfield: &Playfield,
+        _dim: &Dimensions,
+    ) -> bool {
+        false
+    }
+
+    /// Whether kick number `kick` of turning `direction` always makes a full T-spin rather than a mini one
+    fn full_spin_kick(&self, _direction: RotationDirection, _kick: usize) -> bool {
+        false
+    }
 
-/// Tetrominos of type J, L, S, T or Z each have 5 tests, accounting for each of the 4 indices, each with a cartesion coord
-pub const JLSTZ_OFFSET_DATA: [[[i32; 2]; 4]; 5] = [
-    [[0, 0], [0, 0], [0, 0], [0, 0]],
-    [[0, 0], [1, 0], [0, 0], [-1, 0]],
-    [[0, 0], [1, -1], [0, 0], [-1, -1]],
-    [[0, 0], [0, 2], [0, 0], [0, 2]],
-    [[0, 0], [1, 2], [0, 0], [-1, 2]],
-];
-
-/// Tetromino of type  has 5 tests, each with 4 indices, each with a cartesion coord
-pub const I_OFFSET_DATA: [[[i32; 2]; 4]; 5] = [
-    [[0, 0], [-1, 0], [-1, 1], [0, 1]],
-    [[-1, 0], [0, 0], [1, 1], [0, 1]],
-    [[2, 0], [0, 0], [-2, 1], [0, -1]],
-    [[-1, 0], [0, 1], [1, 0], [0, -1]],
-    [[2, 0], [0, -2], [-2, 0], [0, 2]],
-];
-pub const O_OFFSET_DATA: [[[i32; 2]; 4]; 1] = [[[0, 0], [0, -1], [-1, -1], [-1, 0]]];
-
-impl Universe {
-    pub fn rotate_focused(&mut self, rot_direction: RotationDirection) {
-        let center_x = self.focused_tetromino.coords()[0].x;
-        let center_y = self.focused_tetromino.coords()[0].y;
-
-        let (next_index_diff, m) = match rot_direction {
-            RotationDirection::Clockwise => (1, [[0, -1], [1, 0]]),
-            RotationDirection::CounterClockwise => (-1, [[0, 1], [-1, 0]]),
-        };
-
-        for i in 1..self.focused_tetromino.coords().len() {
-            let t = &mut self.focused_tetromino.coords_mut()[i];
-
-            // Get the original coords by subtracting the origin
-            // e.g. (1, 1), (1, 0), etc.
-            let x = t.x as i32 - center_x as i32;
-            let y = t.y as i32 - center_y as i32;
-            // Rotate the coords 90 degrees to the left
-
-            let f_x = x * m[0][0] + y * m[1][0];
-            let f_y = x * m[0][1] + y * m[1][1];
-
-            // Add the coords back
-            t.x = (f_x + center_x as i32) as u32;
-            t.y = (f_y + center_y as i32) as u32;
+    /// Cells `piece` spawns with, in the box the piece set spawns it in, if it isn't the piece's own first state
+    fn spawn_shape(&self, _piece: &Piece) -> Option<&'static [[u32; 2]]> {
+        None
+    }
+}
+
+/// Which rotation system a game is played with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RotationPreset {
+    #[default]
+    #[serde(rename = "srs")]
+    Srs,
+    #[serde(rename = "srs-plus")]
+    SrsPlus,
+    #[serde(rename = "ars")]
+    Ars,
+    #[serde(rename = "nrs")]
+    Nrs,
+}
+
+impl RotationPreset {
+    pub const ALL: [RotationPreset; 4] = [
+        RotationPreset::Srs,
+        RotationPreset::SrsPlus,
+        RotationPreset::Ars,
+        RotationPreset::Nrs,
+    ];
+
+    /// Short name used in files and on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            RotationPreset::Srs => "srs",
+            RotationPreset::SrsPlus => "srs-plus",
+            RotationPreset::Ars => "ars",
+            RotationPreset::Nrs => "nrs",
         }
+    }
 
-        let offset_data = match self.focused_tetromino.tetromino_type() {
-            TetrominoType::J
-            | TetrominoType::L
-            | TetrominoType::S
-            | TetrominoType::T
-            | TetrominoType::Z => &JLSTZ_OFFSET_DATA[..],
-            TetrominoType::I => &I_OFFSET_DATA[..],
-            TetrominoType::O => &O_OFFSET_DATA[..],
-        };
-
-        // Try all of the 5 test cases
-        for test in offset_data {
-            let current_set = test[*self.focused_tetromino().rotation_state().rn() as usize];
-            let new_set = test[self
-                .focused_tetromino()
-                .rotation_state()
-                .get_increment(next_index_diff) as usize];
-            // Checkout <https://harddrop.com/wiki/SRS#How_Guideline_SRS_Really_Works> for more information on how the offset wallkicks are derived
-            // Current - Next
-            let dx_dy = [current_set[0] - new_set[0], current_set[1] - new_set[1]];
-
-            // Test collisions
-            // First make sure it's in boundaries
-            if Tetromino::within_boundary(&self.focused_tetromino, dx_dy, &self.dim)
-                && !self
-                    .focused_tetromino
-                    .will_collide_diff(&self.stagnant_tetrominos, dx_dy)
-            {
-                // Move tetrimino
-                self.focused_tetromino_mut().move_by(dx_dy);
-                // Update indice
-                self.focused_tetromino_mut()
-                    .rotation_state_mut()
-                    .increment(next_index_diff);
-                // Otherwise need to rotate back
-                return;
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    pub fn system(&self) -> Box<dyn RotationSystem> {
+        match self {
+            RotationPreset::Srs => Box::new(Srs),
+            RotationPreset::SrsPlus => Box::new(SrsPlus),
+            RotationPreset::Ars => Box::new(Ars),
+            RotationPreset::Nrs => Box::new(Nrs),
+        }
+    }
+}
+
+impl Simulation {
+    /// Rotates the focused tetromino, trying each wallkick in turn. Returns whether any of them fit
+    pub fn rotate_focused(&mut self, rot_direction: RotationDirection) -> bool {
+        match rotate(
+            self.focused_tetromino(),
+            rot_direction,
+            self.playfield(),
+            self.dim(),
+            self.rotation_system(),
+        ) {
+            Some((rotated, kick)) => {
+                *self.focused_tetromino_mut() = rotated;
+                self.last_rotation = Some((rot_direction, kick));
+                self.lock_delay.on_move();
+                true
             }
+            // Nothing happens if there is conflict
+            // Good place to add sound as well
+            None => false,
         }
+    }
+}
+
+/// Spawns a tetromino of `pieces` in the shape `system` spawns it in
+pub fn spawn(
+    pieces: &PieceSet,
+    tetromino_type: TetrominoType,
+    dim: &Dimensions,
+    system: &dyn RotationSystem,
+) -> Tetromino {
+    let mut spawned = pieces.spawn(tetromino_type, dim);
+    if let Some(shape) = system.spawn_shape(spawned.piece()) {
+        let current = spawned.piece().state(0).to_vec();
+        place(&mut spawned, &current, shape);
+    }
+    spawned
+}
 
-        // Just rotate back if there is conflict, will show up as nothing happened
-        // Good place to add sound as well
-        self.rotate_focused(RotationDirection::flip(rot_direction));
+/// Rotates a tetromino with `system`, trying each wallkick in turn.
+/// Returns the rotated tetromino along with the index of the kick that fit, or `None` if none of them did
+pub fn rotate(
+    tetromino: &Tetromino,
+    rot_direction: RotationDirection,
+    playfield: &Playfield,
+    dim: &Dimensions,
+    system: &dyn RotationSystem,
+) -> Option<(Tetromino, usize)> {
+    for (kick, rotated) in system
+        .candidates(tetromino, rot_direction)
+        .into_iter()
+        .enumerate()
+    {
+        // Test collisions
+        // First make sure it's in boundaries
+        if rotated.within_boundary([0, 0], dim) && !rotated.will_collide_diff(playfield, [0, 0]) {
+            return Some((rotated, kick));
+        }
+        if kick == 0 && system.refuses_kicks(&rotated, playfield, dim) {
+            return None;
+        }
+    }
+    None
+}
+
+/// Cells of each tetromino in each rotation state, for systems that draw the guideline tetrominoes their own way.
+/// Inside a box with its bottom left at (0, 0), the first cell of each being the one that ends up as `coords()[0]`
+type Shapes = [[[u32; 2]; 4]; 4];
+
+/// Swaps a tetromino's cells for its shape `direction` from where it is, keeping the shapes' box where it was.
+/// `shape` gives the cells in each rotation state, and the tetromino has to be in the one for its current state
+fn reshape<'a>(
+    tetromino: &Tetromino,
+    rot_direction: RotationDirection,
+    shape: impl Fn(u32) -> &'a [[u32; 2]],
+) -> Tetromino {
+    let current = shape(*tetromino.rotation_state().rn());
+    let mut reshaped = tetromino.clone();
+    reshaped
+        .rotation_state_mut()
+        .increment(rot_direction.turns());
+    let next = shape(*reshaped.rotation_state().rn());
+    place(&mut reshaped, current, next);
+    reshaped
+}
+
+/// Swaps a tetromino's cells, which are `current` in their box, for `next` in the same box
+fn place(tetromino: &mut Tetromino, current: &[[u32; 2]], next: &[[u32; 2]]) {
+    // Bottom left of the box, from where the tetromino's bottom left sits in it
+    let corner = |cells: &[[i32; 2]]| {
+        [0, 1].map(|axis| {
+            cells
+                .iter()
+                .map(|cell| cell[axis])
+                .min()
+                .unwrap_or_default()
+        })
+    };
+    let placed: Vec<[i32; 2]> = tetromino
+        .coords()
+        .iter()
+        .map(|c| [c.x as i32, c.y as i32])
+        .collect();
+    let shaped: Vec<[i32; 2]> = current.iter().map(|&[x, y]| [x as i32, y as i32]).collect();
+    let (placed, shaped) = (corner(&placed), corner(&shaped));
+    let (box_x, box_y) = (placed[0] - shaped[0], placed[1] - shaped[1]);
+
+    for (coord, [x, y]) in tetromino.coords_mut().iter_mut().zip(next) {
+        coord.x = (box_x + *x as i32) as u32;
+        coord.y = (box_y + *y as i32) as u32;
+    }
+}
+
+/// `turned` tried at each of `kicks` in turn
+fn kicked(turned: Tetromino, kicks: &[[i32; 2]]) -> Vec<Tetromino> {
+    kicks
+        .iter()
+        .map(|&kick| {
+            let mut candidate = turned.clone();
+            candidate.move_by(kick);
+            candidate
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tetromino::piece_set::PieceSet;
+
+    /// An empty standard board
+    pub(super) fn board() -> (Playfield, Dimensions) {
+        let dim = Dimensions::new(10, 20, 4);
+        (Playfield::new(&dim), dim)
+    }
+
+    /// Sorted cells, to compare tetrominos no matter the order of their coords
+    pub(super) fn cells(tetromino: &Tetromino) -> Vec<[i32; 2]> {
+        let mut cells: Vec<[i32; 2]> = tetromino
+            .coords()
+            .iter()
+            .map(|c| [c.x as i32, c.y as i32])
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    /// Cells of a drawing with a `#` for every cell, the top row first
+    pub(super) fn diagram(rows: &[&str]) -> Vec<[i32; 2]> {
+        let mut cells: Vec<[i32; 2]> = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                let y = (rows.len() - 1 - row) as i32;
+                line.chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c == '#')
+                    .map(move |(x, _)| [x as i32, y])
+            })
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    /// A tetromino of the standard set spawned in the middle of an empty board and turned into `state` by its system
+    pub(super) fn in_state(name: &str, state: u32, system: &dyn RotationSystem) -> Tetromino {
+        spawned_in_state(&PieceSet::standard(), name, state, system)
+    }
+
+    /// A piece of `pieces` spawned in the middle of an empty board and turned into `state` by its system
+    fn spawned_in_state(
+        pieces: &PieceSet,
+        name: &str,
+        state: u32,
+        system: &dyn RotationSystem,
+    ) -> Tetromino {
+        let (playfield, dim) = board();
+        let piece = pieces.find(name).unwrap();
+        let mut tetromino = spawn(pieces, *piece.tetromino_type(), &dim, system);
+        tetromino.move_by([0, -10]);
+        for _ in 0..state {
+            tetromino = rotate(
+                &tetromino,
+                RotationDirection::Clockwise,
+                &playfield,
+                &dim,
+                system,
+            )
+            .unwrap()
+            .0;
+        }
+        tetromino
+    }
+
+    #[test]
+    fn test_every_system_comes_back_around() {
+        let (playfield, dim) = board();
+        for (preset, name) in RotationPreset::ALL
+            .into_iter()
+            .flat_map(|preset| PieceSet::built_in().map(move |name| (preset, name)))
+        {
+            let system = preset.system();
+            let pieces = PieceSet::load(name).unwrap();
+            for piece in pieces.pieces() {
+                let spawned = spawned_in_state(&pieces, piece.name(), 0, system.as_ref());
+                let mut tetromino = spawned.clone();
+                for direction in [
+                    RotationDirection::Clockwise,
+                    RotationDirection::Half,
+                    RotationDirection::Clockwise,
+                    RotationDirection::CounterClockwise,
+                    RotationDirection::Half,
+                    RotationDirection::CounterClockwise,
+                ] {
+                    tetromino = rotate(&tetromino, direction, &playfield, &dim, system.as_ref())
+                        .unwrap()
+                        .0;
+                }
+                assert_eq!(
+                    cells(&tetromino),
+                    cells(&spawned),
+                    "{:?} {}",
+                    preset,
+                    piece.name()
+                );
+                assert_eq!(*tetromino.rotation_state().rn(), 0);
+            }
+        }
     }
 }
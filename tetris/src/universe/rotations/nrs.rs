@@ -0,0 +1,173 @@
+use super::*;
+
+// Cells of each tetromino in each rotation state, bottom left of the box at (0, 0).
+// J, L and T turn about the middle of their box, and spawn pointing down with their flat side up
+const T_SHAPES: Shapes = [
+    [[1, 1], [0, 1], [2, 1], [1, 0]],
+    [[1, 1], [1, 2], [1, 0], [0, 1]],
+    [[1, 1], [0, 1], [2, 1], [1, 2]],
+    [[1, 1], [1, 2], [1, 0], [2, 1]],
+];
+const J_SHAPES: Shapes = [
+    [[1, 1], [0, 1], [2, 1], [2, 0]],
+    [[1, 1], [1, 2], [1, 0], [0, 0]],
+    [[1, 1], [0, 1], [2, 1], [0, 2]],
+    [[1, 1], [1, 2], [1, 0], [2, 2]],
+];
+const L_SHAPES: Shapes = [
+    [[1, 1], [0, 1], [2, 1], [0, 0]],
+    [[1, 1], [1, 2], [1, 0], [0, 2]],
+    [[1, 1], [0, 1], [2, 1], [2, 2]],
+    [[1, 1], [1, 2], [1, 0], [2, 0]],
+];
+// S, Z and I only have two states, standing up right of the middle
+const S_SHAPES: Shapes = [
+    [[1, 0], [0, 0], [1, 1], [2, 1]],
+    [[1, 1], [1, 2], [2, 1], [2, 0]],
+    [[1, 0], [0, 0], [1, 1], [2, 1]],
+    [[1, 1], [1, 2], [2, 1], [2, 0]],
+];
+const Z_SHAPES: Shapes = [
+    [[1, 0], [2, 0], [0, 1], [1, 1]],
+    [[1, 1], [2, 1], [2, 2], [1, 0]],
+    [[1, 0], [2, 0], [0, 1], [1, 1]],
+    [[1, 1], [2, 1], [2, 2], [1, 0]],
+];
+const I_SHAPES: Shapes = [
+    [[1, 1], [0, 1], [2, 1], [3, 1]],
+    [[2, 1], [2, 3], [2, 2], [2, 0]],
+    [[1, 1], [0, 1], [2, 1], [3, 1]],
+    [[2, 1], [2, 3], [2, 2], [2, 0]],
+];
+const O_SHAPES: Shapes = [[[0, 0], [1, 0], [0, 1], [1, 1]]; 4];
+
+/// The NES shapes of a tetromino, or `None` for pieces that aren't one and turn through their own states
+fn shapes(piece: &Piece) -> Option<&'static Shapes> {
+    match piece.tetromino()? {
+        GuidelineTetromino::I => Some(&I_SHAPES),
+        GuidelineTetromino::J => Some(&J_SHAPES),
+        GuidelineTetromino::L => Some(&L_SHAPES),
+        GuidelineTetromino::O => Some(&O_SHAPES),
+        GuidelineTetromino::S => Some(&S_SHAPES),
+        GuidelineTetromino::T => Some(&T_SHAPES),
+        GuidelineTetromino::Z => Some(&Z_SHAPES),
+    }
+}
+
+/// The Nintendo Rotation System from the NES game, which never kicks
+pub struct Nrs;
+
+impl RotationSystem for Nrs {
+    fn candidates(&self, tetromino: &Tetromino, direction: RotationDirection) -> Vec<Tetromino> {
+        let piece = tetromino.piece();
+        let shapes = shapes(piece);
+        vec![reshape(tetromino, direction, |state| {
+            shapes.map_or_else(|| piece.state(state), |shapes| &shapes[state as usize][..])
+        })]
+    }
+
+    fn spawn_shape(&self, piece: &Piece) -> Option<&'static [[u32; 2]]> {
+        shapes(piece).map(|shapes| &shapes[0][..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::{board, cells, diagram, in_state};
+    use super::*;
+    use crate::tetromino::piece_set::PieceSet;
+
+    #[test]
+    fn test_nrs_matches_the_published_states() {
+        // States in the order they turn clockwise from spawning, as drawn for the NES game
+        let published: [(&str, [[&str; 4]; 4]); 5] = [
+            (
+                "T",
+                [
+                    ["....", "###.", ".#..", "...."],
+                    [".#..", "##..", ".#..", "...."],
+                    [".#..", "###.", "....", "...."],
+                    [".#..", ".##.", ".#..", "...."],
+                ],
+            ),
+            (
+                "J",
+                [
+                    ["....", "###.", "..#.", "...."],
+                    [".#..", ".#..", "##..", "...."],
+                    ["#...", "###.", "....", "...."],
+                    [".##.", ".#..", ".#..", "...."],
+                ],
+            ),
+            (
+                "S",
+                [
+                    ["....", ".##.", "##..", "...."],
+                    [".#..", ".##.", "..#.", "...."],
+                    ["....", ".##.", "##..", "...."],
+                    [".#..", ".##.", "..#.", "...."],
+                ],
+            ),
+            (
+                "Z",
+                [
+                    ["....", "##..", ".##.", "...."],
+                    ["..#.", ".##.", ".#..", "...."],
+                    ["....", "##..", ".##.", "...."],
+                    ["..#.", ".##.", ".#..", "...."],
+                ],
+            ),
+            (
+                "I",
+                [
+                    ["....", "....", "####", "...."],
+                    ["..#.", "..#.", "..#.", "..#."],
+                    ["....", "....", "####", "...."],
+                    ["..#.", "..#.", "..#.", "..#."],
+                ],
+            ),
+        ];
+        for (name, states) in published {
+            let spawned = cells(&in_state(name, 0, &Nrs));
+            let reference = diagram(&states[0]);
+            let origin = [
+                spawned[0][0] - reference[0][0],
+                spawned[0][1] - reference[0][1],
+            ];
+            for (state, rows) in states.iter().enumerate() {
+                let mut expected: Vec<[i32; 2]> = diagram(rows)
+                    .iter()
+                    .map(|[x, y]| [x + origin[0], y + origin[1]])
+                    .collect();
+                expected.sort();
+                assert_eq!(
+                    cells(&in_state(name, state as u32, &Nrs)),
+                    expected,
+                    "{} in state {}",
+                    name,
+                    state
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_nrs_never_kicks() {
+        let (playfield, dim) = board();
+        for piece in PieceSet::standard().pieces() {
+            let tetromino = in_state(piece.name(), 0, &Nrs);
+            for direction in [
+                RotationDirection::Clockwise,
+                RotationDirection::CounterClockwise,
+                RotationDirection::Half,
+            ] {
+                assert_eq!(Nrs.candidates(&tetromino, direction).len(), 1);
+            }
+        }
+        // Standing against the wall, an I can't lie back down
+        let mut i = in_state("I", 1, &Nrs);
+        let column = i.coords()[0].x as i32;
+        i.move_by([-column, 0]);
+        assert!(rotate(&i, RotationDirection::Clockwise, &playfield, &dim, &Nrs).is_none());
+    }
+}
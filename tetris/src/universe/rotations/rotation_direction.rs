@@ -1,13 +1,17 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RotationDirection {
     Clockwise,
     CounterClockwise,
+    Half,
 }
+
 impl RotationDirection {
-    /// Gets reverse
-    pub fn flip(direction: RotationDirection) -> RotationDirection {
-        match direction {
-            RotationDirection::Clockwise => RotationDirection::CounterClockwise,
-            RotationDirection::CounterClockwise => RotationDirection::Clockwise,
+    /// Quarter turns clockwise
+    pub fn turns(&self) -> i32 {
+        match self {
+            RotationDirection::Clockwise => 1,
+            RotationDirection::CounterClockwise => -1,
+            RotationDirection::Half => 2,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,324 @@
+use super::*;
+
+/// Kicks for the J, L, S, T and Z tetrominos as published for the guideline, y pointing up.
+/// Indexed by the rotation state turned from, then clockwise and counterclockwise
+pub const JLSTZ_KICKS: [[[[i32; 2]; 5]; 2]; 4] = [
+    [
+        [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]],
+        [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],
+    ],
+    [
+        [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
+        [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
+    ],
+    [
+        [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],
+        [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]],
+    ],
+    [
+        [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
+        [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
+    ],
+];
+
+/// Kicks for the I tetromino as published for the guideline, indexed the same way
+pub const I_KICKS: [[[[i32; 2]; 5]; 2]; 4] = [
+    [
+        [[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]],
+        [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]],
+    ],
+    [
+        [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]],
+        [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]],
+    ],
+    [
+        [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]],
+        [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]],
+    ],
+    [
+        [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]],
+        [[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]],
+    ],
+];
+
+/// SRS+ kicks for the I tetromino, which mirror each other left to right unlike SRS's.
+/// Indexed by the rotation state turned from, clockwise then counterclockwise
+const SRS_PLUS_I_KICKS: [[[[i32; 2]; 5]; 2]; 4] = [
+    [
+        [[0, 0], [1, 0], [-2, 0], [-2, -1], [1, 2]],
+        [[0, 0], [-1, 0], [2, 0], [2, -1], [-1, 2]],
+    ],
+    [
+        [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]],
+        [[0, 0], [-1, 0], [2, 0], [-1, -2], [2, 1]],
+    ],
+    [
+        [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]],
+        [[0, 0], [-2, 0], [1, 0], [-2, 1], [1, -2]],
+    ],
+    [
+        [[0, 0], [1, 0], [-2, 0], [1, 2], [-2, -1]],
+        [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]],
+    ],
+];
+
+/// SRS+ kicks for turning J, L, S, T and Z 180 degrees, indexed by the rotation state turned from
+const SRS_PLUS_HALF_KICKS: [[[i32; 2]; 6]; 4] = [
+    [[0, 0], [0, 1], [1, 1], [-1, 1], [1, 0], [-1, 0]],
+    [[0, 0], [1, 0], [1, 2], [1, 1], [0, 2], [0, 1]],
+    [[0, 0], [0, -1], [-1, -1], [1, -1], [-1, 0], [1, 0]],
+    [[0, 0], [-1, 0], [-1, 2], [-1, 1], [0, 2], [0, 1]],
+];
+
+/// Index of the last quarter turn kick, (±1, ±2), which always counts as a full T-spin (TST/fin kicks)
+const LAST_QUARTER_KICK: usize = 4;
+
+// Turning in place, and nothing else
+const IN_PLACE: [[i32; 2]; 1] = [[0, 0]];
+
+/// Turns a tetromino into the next of its piece's rotation states, keeping the box it turns about where it was.
+/// Returns the turned tetromino, along with the state it was turned from
+fn turn(tetromino: &Tetromino, rot_direction: RotationDirection) -> (Tetromino, usize) {
+    let piece = tetromino.piece();
+    let turned = reshape(tetromino, rot_direction, |state| piece.state(state));
+    (turned, *tetromino.rotation_state().rn() as usize)
+}
+
+/// Whether a kick is the last of a quarter turn, the 180 kicks of SRS+ being plain shifts
+fn full_spin_kick(direction: RotationDirection, kick: usize) -> bool {
+    direction != RotationDirection::Half && kick == LAST_QUARTER_KICK
+}
+
+/// Which of a quarter turn table's kicks apply turning `rot_direction`
+fn quarter(rot_direction: RotationDirection) -> usize {
+    match rot_direction {
+        RotationDirection::CounterClockwise => 1,
+        _ => 0,
+    }
+}
+
+/// The Super Rotation System of the guideline, with the kicks published for it.
+/// There's no such thing as a 180 in the guideline, so turning 180 degrees only happens in place
+pub struct Srs;
+
+impl RotationSystem for Srs {
+    fn candidates(&self, tetromino: &Tetromino, direction: RotationDirection) -> Vec<Tetromino> {
+        let (turned, from) = turn(tetromino, direction);
+        let kicks: &[[i32; 2]] = match (tetromino.piece().kicks(), direction) {
+            (_, RotationDirection::Half) | (KickTable::None, _) => &IN_PLACE,
+            (KickTable::Jlstz, _) => &JLSTZ_KICKS[from][quarter(direction)],
+            (KickTable::I, _) => &I_KICKS[from][quarter(direction)],
+        };
+        kicked(turned, kicks)
+    }
+
+    fn full_spin_kick(&self, direction: RotationDirection, kick: usize) -> bool {
+        full_spin_kick(direction, kick)
+    }
+}
+
+/// SRS with symmetric I kicks and a set of 180 kicks, as played online.
+/// Pieces on the I table still only turn 180 degrees in place
+pub struct SrsPlus;
+
+impl RotationSystem for SrsPlus {
+    fn candidates(&self, tetromino: &Tetromino, direction: RotationDirection) -> Vec<Tetromino> {
+        let (turned, from) = turn(tetromino, direction);
+        let kicks: &[[i32; 2]] = match (tetromino.piece().kicks(), direction) {
+            (KickTable::None, _) | (KickTable::I, RotationDirection::Half) => &IN_PLACE,
+            (KickTable::Jlstz, RotationDirection::Half) => &SRS_PLUS_HALF_KICKS[from],
+            (KickTable::Jlstz, _) => &JLSTZ_KICKS[from][quarter(direction)],
+            (KickTable::I, _) => &SRS_PLUS_I_KICKS[from][quarter(direction)],
+        };
+        kicked(turned, kicks)
+    }
+
+    fn full_spin_kick(&self, direction: RotationDirection, kick: usize) -> bool {
+        full_spin_kick(direction, kick)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::{cells, in_state};
+    use super::*;
+
+    /// Cells of a tetromino in the guideline's rotation state `state`, turned about the middle of its box
+    /// (3 cells wide, 4 for I and 2 for O) from how it spawns, in a box with its bottom left at (0, 0)
+    fn reference(name: &str, state: u32) -> Vec<[i32; 2]> {
+        let (size, spawn) = match name {
+            "I" => (4, [[0, 2], [1, 2], [2, 2], [3, 2]]),
+            "J" => (3, [[0, 2], [0, 1], [1, 1], [2, 1]]),
+            "L" => (3, [[2, 2], [0, 1], [1, 1], [2, 1]]),
+            "O" => (2, [[0, 0], [1, 0], [0, 1], [1, 1]]),
+            "S" => (3, [[1, 2], [2, 2], [0, 1], [1, 1]]),
+            "T" => (3, [[1, 2], [0, 1], [1, 1], [2, 1]]),
+            _ => (3, [[0, 2], [1, 2], [1, 1], [2, 1]]),
+        };
+        let mut cells: Vec<[i32; 2]> = spawn
+            .iter()
+            .map(|&[x, y]| {
+                // Twice the distance from the middle of the box, so that it's whole for even sized boxes too
+                let (mut dx, mut dy) = (2 * x + 1 - size, 2 * y + 1 - size);
+                for _ in 0..state {
+                    (dx, dy) = (dy, -dx);
+                }
+                [(dx + size - 1) / 2, (dy + size - 1) / 2]
+            })
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    fn shifted(cells: &[[i32; 2]], [dx, dy]: [i32; 2]) -> Vec<[i32; 2]> {
+        let mut cells: Vec<[i32; 2]> = cells.iter().map(|[x, y]| [x + dx, y + dy]).collect();
+        cells.sort();
+        cells
+    }
+
+    /// Checks every candidate against the reference position turned into plus the published kick
+    fn check(
+        system: &dyn RotationSystem,
+        name: &str,
+        direction: RotationDirection,
+        kicks: impl Fn(usize) -> Vec<[i32; 2]>,
+    ) {
+        for from in 0..4 {
+            let tetromino = in_state(name, from, system);
+            // Where the reference box sits on the board
+            let reference_from = reference(name, from);
+            let ours = cells(&tetromino);
+            let origin = [
+                ours[0][0] - reference_from[0][0],
+                ours[0][1] - reference_from[0][1],
+            ];
+            assert_eq!(ours, shifted(&reference_from, origin));
+
+            let to = (from as i32 + direction.turns()).rem_euclid(4) as u32;
+            let kicks = kicks(from as usize);
+            let candidates = system.candidates(&tetromino, direction);
+            assert_eq!(candidates.len(), kicks.len(), "{} from {}", name, from);
+            for (candidate, [x, y]) in candidates.iter().zip(kicks) {
+                assert_eq!(
+                    cells(candidate),
+                    shifted(&reference(name, to), [origin[0] + x, origin[1] + y]),
+                    "{} from {} kicked by ({}, {})",
+                    name,
+                    from,
+                    x,
+                    y
+                );
+                assert_eq!(*candidate.rotation_state().rn(), to);
+            }
+        }
+    }
+
+    // The published guideline kicks, clockwise then counterclockwise from each of 0, R, 2 and L
+    const PUBLISHED_JLSTZ: [[[[i32; 2]; 5]; 2]; 4] = [
+        [
+            [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]],
+            [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],
+        ],
+        [
+            [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
+            [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
+        ],
+        [
+            [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],
+            [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]],
+        ],
+        [
+            [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
+            [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
+        ],
+    ];
+
+    #[test]
+    fn test_srs_matches_the_published_kicks() {
+        let i_kicks = [
+            [
+                [[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]],
+                [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]],
+            ],
+            [
+                [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]],
+                [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]],
+            ],
+            [
+                [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]],
+                [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]],
+            ],
+            [
+                [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]],
+                [[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]],
+            ],
+        ];
+        for name in ["I", "J", "L", "O", "S", "T", "Z"] {
+            for (i, direction) in [
+                RotationDirection::Clockwise,
+                RotationDirection::CounterClockwise,
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                check(&Srs, name, direction, |from| match name {
+                    "I" => i_kicks[from][i].to_vec(),
+                    // The O tetromino turns without moving
+                    "O" => vec![[0, 0]],
+                    _ => PUBLISHED_JLSTZ[from][i].to_vec(),
+                });
+            }
+            check(&Srs, name, RotationDirection::Half, |_| vec![[0, 0]]);
+        }
+    }
+
+    #[test]
+    fn test_srs_plus_matches_the_published_kicks() {
+        let i_kicks = [
+            [
+                [[0, 0], [1, 0], [-2, 0], [-2, -1], [1, 2]],
+                [[0, 0], [-1, 0], [2, 0], [2, -1], [-1, 2]],
+            ],
+            [
+                [[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]],
+                [[0, 0], [-1, 0], [2, 0], [-1, -2], [2, 1]],
+            ],
+            [
+                [[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]],
+                [[0, 0], [-2, 0], [1, 0], [-2, 1], [1, -2]],
+            ],
+            [
+                [[0, 0], [1, 0], [-2, 0], [1, 2], [-2, -1]],
+                [[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]],
+            ],
+        ];
+        let half_kicks = [
+            vec![[0, 0], [0, 1], [1, 1], [-1, 1], [1, 0], [-1, 0]],
+            vec![[0, 0], [1, 0], [1, 2], [1, 1], [0, 2], [0, 1]],
+            vec![[0, 0], [0, -1], [-1, -1], [1, -1], [-1, 0], [1, 0]],
+            vec![[0, 0], [-1, 0], [-1, 2], [-1, 1], [0, 2], [0, 1]],
+        ];
+        check(&SrsPlus, "I", RotationDirection::Clockwise, |from| {
+            i_kicks[from][0].to_vec()
+        });
+        check(&SrsPlus, "I", RotationDirection::CounterClockwise, |from| {
+            i_kicks[from][1].to_vec()
+        });
+        check(&SrsPlus, "I", RotationDirection::Half, |_| vec![[0, 0]]);
+        for name in ["J", "L", "S", "T", "Z"] {
+            // Quarter turns are the same as SRS
+            check(&SrsPlus, name, RotationDirection::Clockwise, |from| {
+                PUBLISHED_JLSTZ[from][0].to_vec()
+            });
+            check(
+                &SrsPlus,
+                name,
+                RotationDirection::CounterClockwise,
+                |from| PUBLISHED_JLSTZ[from][1].to_vec(),
+            );
+            check(&SrsPlus, name, RotationDirection::Half, |from| {
+                half_kicks[from].clone()
+            });
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use thomas::context::Context;
+use thomas::winit::event::VirtualKeyCode;
+
+use super::game::GameState;
+use super::replay::{self, Replay, ReplayError, ReplayPlayer};
+use super::simulation::{GameAction, Simulation};
+use super::Universe;
+use crate::files;
+
+// First line of every save file
+const HEADER: &str = "tetris-save";
+// Goes up whenever the simulation changes in a way that plays older saves out differently
+const VERSION: u32 = 3;
+// Name of the save file under the game's data directory
+const SAVE_FILE: &str = "save";
+// Keys that answer whether to resume a saved game
+const RESUME_KEY: VirtualKeyCode = VirtualKeyCode::Y;
+const DISCARD_KEY: VirtualKeyCode = VirtualKeyCode::N;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    /// Written by a version of the game that saves differently, holding the version it was written by
+    Version(u32),
+    /// Something's wrong on the given line (starting at 1) of a save file
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// Playing the save back doesn't end up where the game was saved at
+    Mismatch,
+    /// Saved at a different tick rate than the window runs at, holding the saved one
+    Ticks(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{}", e),
+            SaveError::Version(version) => write!(
+                f,
+                "saved by version {} of the save format, this game only resumes version {}",
+                version, VERSION
+            ),
+            SaveError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            SaveError::Mismatch => {
+                write!(f, "the saved game plays out differently in this version")
+            }
+            SaveError::Ticks(ticks) => write!(
+                f,
+                "saved at {} ticks per second, set `ticks` back to resume it",
+                ticks
+            ),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+/// How far along a game is, which a resumed game has to end up at exactly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Progress {
+    score: u32,
+    lines: u32,
+    level: u32,
+    // Ticks spent playing
+    time: u32,
+    // Tetrominos locked
+    pieces: u32,
+}
+
+impl Progress {
+    fn of(sim: &Simulation) -> Self {
+        let game = sim.game();
+        Self {
+            score: *game.score(),
+            lines: game.lines_cleared(),
+            level: *game.level(),
+            time: game.elapsed(),
+            pieces: sim.pieces(),
+        }
+    }
+}
+
+/// A game in progress, kept as its recording so far along with how far along it got.
+/// Since the universe is deterministic, playing the recording back restores the board, the pieces,
+/// the randomness and the game's timers exactly as they were
+#[derive(Debug, Clone, PartialEq)]
+pub struct Save {
+    recording: Replay,
+    progress: Progress,
+}
+
+impl Save {
+    /// Saves the session `sim` is in the middle of
+    pub fn new(sim: &Simulation) -> Self {
+        Self {
+            recording: sim.recording().clone(),
+            progress: Progress::of(sim),
+        }
+    }
+
+    /// Where the save file goes by default, in the platform's data directory
+    pub fn default_path() -> Option<PathBuf> {
+        files::data_dir().map(|dir| dir.join(SAVE_FILE))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Plays the game back up to where it was saved. Restarting it starts from `fixed_seed` if it's set
+    pub fn resume(&self, fixed_seed: Option<u64>) -> Result<Simulation, SaveError> {
+        let mut sim = Simulation::new(self.recording.rules().clone(), fixed_seed);
+        sim.start(self.recording.seed());
+        sim.set_finesse(false, self.recording.half_turns());
+        let mut player = ReplayPlayer::new(self.recording.clone());
+        while let Some(tick) = player.next_tick() {
+            sim.press(&tick.presses);
+            sim.tick(&tick.actions);
+        }
+        if Progress::of(&sim) != self.progress {
+            return Err(SaveError::Mismatch);
+        }
+        Ok(sim)
+    }
+}
+
+// Getters and setters
+impl Save {
+    /// Get every action of the saved game, along with the seed and rules it started from
+    pub fn recording(&self) -> &Replay {
+        &self.recording
+    }
+}
+
+/// A header of how far along the game got, one `name value` per line, followed by the recording as a replay
+impl fmt::Display for Save {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        writeln!(f, "score {}", self.progress.score)?;
+        writeln!(f, "lines {}", self.progress.lines)?;
+        writeln!(f, "level {}", self.progress.level)?;
+        writeln!(f, "time {}", self.progress.time)?;
+        writeln!(f, "pieces {}", self.progress.pieces)?;
+        write!(f, "{}", self.recording)
+    }
+}
+
+impl FromStr for Save {
+    type Err = SaveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |line: usize, message: String| SaveError::Parse {
+            line: line + 1,
+            message,
+        };
+        let mut lines = s.lines();
+        let version = lines
+            .next()
+            .and_then(|first| first.strip_prefix(HEADER))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or_else(|| error(0, String::from("not a save file")))?;
+        if version != VERSION {
+            return Err(SaveError::Version(version));
+        }
+
+        let mut progress = Progress {
+            score: 0,
+            lines: 0,
+            level: 0,
+            time: 0,
+            pieces: 0,
+        };
+        // Everything from the replay's header on is the recording
+        let mut recording = None;
+        for (i, line) in lines.enumerate().map(|(i, line)| (i + 1, line)) {
+            if line.starts_with(replay::HEADER) {
+                let rest: Vec<&str> = s.lines().skip(i).collect();
+                recording = Some(rest.join("\n").parse::<Replay>().map_err(|e| match e {
+                    ReplayError::Parse { line, message } => error(i + line - 1, message),
+                    ReplayError::Io(e) => SaveError::Io(e),
+                })?);
+                break;
+            }
+            let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| error(i, format!("`{}` isn't a number", value)))?;
+            match name {
+                "score" => progress.score = value,
+                "lines" => progress.lines = value,
+                "level" => progress.level = value,
+                "time" => progress.time = value,
+                "pieces" => progress.pieces = value,
+                _ => return Err(error(i, format!("unknown field `{}`", name))),
+            }
+        }
+
+        let recording = recording.ok_or_else(|| error(0, String::from("missing the recording")))?;
+        Ok(Self {
+            recording,
+            progress,
+        })
+    }
+}
+
+impl Universe {
+    /// Saves the game to `path` whenever the window closes, offering to resume whatever was saved there last time
+    pub fn with_save(mut self, path: PathBuf) -> Self {
+        self.offered_save = path.exists().then(|| Save::load(&path));
+        self.save_path = Some(path);
+        self
+    }
+
+    /// Waits for the player to pick between resuming the offered save and starting a new game.
+    /// A save that can't be resumed just waits for any key
+    pub(super) fn tick_offered_save(&mut self, ctx: &mut Context) {
+        let Some(key) = ctx.keyboard.take_pressed() else {
+            return;
+        };
+        match self.offered_save.take() {
+            Some(Ok(save)) if key == RESUME_KEY => self.resume_save(&save),
+            Some(Ok(save)) if key != DISCARD_KEY => self.offered_save = Some(Ok(save)),
+            _ => {}
+        }
+    }
+
+    /// Picks the saved game back up under the rules it was played with, paused until the player's ready
+    fn resume_save(&mut self, save: &Save) {
+        let rules = save.recording().rules();
+        // The tick rate is fixed once the window's open
+        if rules.ticks != *self.config.ticks() {
+            self.offered_save = Some(Err(SaveError::Ticks(rules.ticks)));
+            return;
+        }
+        match save.resume(self.config.seed()) {
+            Ok(mut sim) => {
+                rules.apply(&mut self.config);
+                if sim.game().running() {
+                    sim.tick(&[GameAction::Pause]);
+                }
+                self.sim = sim;
+            }
+            Err(e) => self.offered_save = Some(Err(e)),
+        }
+    }
+
+    /// Saves the game in progress for next time, or clears out the old save if there isn't one.
+    /// A save that was never answered is kept as it is
+    pub(super) fn save_game(&self) -> Result<(), SaveError> {
+        let Some(path) = &self.save_path else {
+            return Ok(());
+        };
+        if matches!(self.offered_save, Some(Ok(_))) {
+            return Ok(());
+        }
+        let in_progress = matches!(
+            self.sim.game().state(),
+            GameState::Countdown(_)
+                | GameState::Playing
+                | GameState::Paused
+                | GameState::LineClearDelay(_)
+                | GameState::Entry(_)
+        );
+        if in_progress && self.sim.recording().length() > 0 {
+            return Save::new(&self.sim).save(path);
+        }
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Renders the question of whether to resume the offered save, or why it can't be
+    pub(super) fn render_offered_save(&self, ctx: &mut Context, offered: &Result<Save, SaveError>) {
+        match offered {
+            Ok(save) => {
+                self.render_centered(ctx, "RESUME?", 100.0, -80.0);
+                let progress = save.progress;
+                self.render_centered(
+                    ctx,
+                    &format!(
+                        "{} {} points, {} lines",
+                        save.recording().rules().mode.name(),
+                        progress.score,
+                        progress.lines
+                    ),
+                    30.0,
+                    20.0,
+                );
+                self.render_centered(
+                    ctx,
+                    "Press \"y\" to resume or \"n\" for a new game",
+                    20.0,
+                    80.0,
+                );
+            }
+            Err(e) => {
+                self.render_centered(ctx, "Couldn't resume the saved game", 30.0, 0.0);
+                self.render_centered(ctx, &e.to_string(), 20.0, 50.0);
+                self.render_centered(ctx, "Press any key for a new game", 20.0, 100.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::universe::simulation::Rules;
+
+    fn played() -> Simulation {
+        let mut sim = Simulation::new(
+            Rules {
+                ticks: 10,
+                ..Rules::default()
+            },
+            Some(11),
+        );
+        let script = [
+            GameAction::MoveLeft,
+            GameAction::HardDrop,
+            GameAction::Hold,
+            GameAction::RotateCW,
+            GameAction::HardDrop,
+        ];
+        for _ in 0..30 {
+            sim.tick(&[]);
+        }
+        for _ in 0..6 {
+            for &action in script.iter() {
+                sim.press(&[action]);
+                sim.tick(&[action]);
+                sim.tick(&[]);
+            }
+        }
+        sim
+    }
+
+    #[test]
+    fn test_resumes_where_it_was_saved() {
+        let sim = played();
+        let save: Save = Save::new(&sim).to_string().parse().unwrap();
+        let mut resumed = save.resume(None).unwrap();
+        assert_eq!(resumed.playfield(), sim.playfield());
+        assert_eq!(resumed.next_queue(), sim.next_queue());
+        assert_eq!(resumed.held(), sim.held());
+        assert_eq!(
+            resumed.focused_tetromino().coords(),
+            sim.focused_tetromino().coords()
+        );
+        assert_eq!(resumed.recording(), sim.recording());
+        // Presses are in the recording, so the inputs and finesse carry on from where they were
+        assert_eq!(resumed.stats(), sim.stats());
+
+        // And keeps playing the same game from there
+        let mut sim = sim;
+        for _ in 0..20 {
+            sim.tick(&[GameAction::HardDrop]);
+            resumed.tick(&[GameAction::HardDrop]);
+        }
+        assert_eq!(resumed.playfield(), sim.playfield());
+        assert_eq!(resumed.game().score(), sim.game().score());
+    }
+
+    #[test]
+    fn test_unresumable_saves_are_errors() {
+        let text = Save::new(&played()).to_string();
+        let older = text.replacen("tetris-save 3", "tetris-save 2", 1);
+        assert!(matches!(older.parse::<Save>(), Err(SaveError::Version(2))));
+        assert!(matches!(
+            "tetris-replay 3\nseed 1\n".parse::<Save>(),
+            Err(SaveError::Parse { line: 1, .. })
+        ));
+        // Lines in the recording count from the top of the save
+        match text
+            .replacen("scoring ", "scoring tetris99", 1)
+            .parse::<Save>()
+        {
+            Err(SaveError::Parse { line, .. }) => {
+                assert_eq!(
+                    text.lines().nth(line - 1).map(|l| l.starts_with("scoring")),
+                    Some(true)
+                )
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+
+        let tampered: Save = text.replacen("pieces ", "pieces 1", 1).parse().unwrap();
+        assert!(matches!(tampered.resume(None), Err(SaveError::Mismatch)));
+    }
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+
+use super::clear::{LineClear, TSpin};
+
+/// Everything a scoring rule gets to know about a lock
+pub struct ScoredLock {
+    pub clear: LineClear,
+    // Level before the lock, starting at 0
+    pub level: u32,
+    // Line clearing locks in a row before this one, `None` if this lock didn't clear anything
+    pub combo: Option<u32>,
+    // Whether this is a difficult clear that follows another difficult clear
+    pub back_to_back: bool,
+}
+
+/// Decides how many points everything is worth
+pub trait ScoringRule {
+    /// Points for a lock that cleared lines, spun, or both
+    fn lock(&self, lock: &ScoredLock) -> u32;
+
+    /// Points for soft dropping a row
+    fn soft_drop(&self, level: u32) -> u32 {
+        level + 1
+    }
+
+    /// Points for hard dropping `rows` rows
+    fn hard_drop(&self, level: u32, rows: u32) -> u32 {
+        (level + 2) * rows
+    }
+}
+
+/// Which scoring rule a game is played with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoringPreset {
+    #[default]
+    Guideline,
+    GameBoy,
+    Nes,
+}
+
+impl ScoringPreset {
+    pub const ALL: [ScoringPreset; 3] = [
+        ScoringPreset::Guideline,
+        ScoringPreset::GameBoy,
+        ScoringPreset::Nes,
+    ];
+
+    /// Short name used in files
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScoringPreset::Guideline => "guideline",
+            ScoringPreset::GameBoy => "gameboy",
+            ScoringPreset::Nes => "nes",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    pub fn rule(&self) -> Box<dyn ScoringRule> {
+        match self {
+            ScoringPreset::Guideline => Box::new(Guideline),
+            ScoringPreset::GameBoy => Box::new(GameBoy),
+            ScoringPreset::Nes => Box::new(Nes),
+        }
+    }
+}
+
+/// Modern guideline scoring, with every value multiplied by the level (starting at 1).
+/// Difficult clears (tetrises and spins that clear lines) in a row are worth 1.5x,
+/// and every line clear in a row adds a combo bonus
+pub struct Guideline;
+
+// No lines, single, double, triple, tetris
+const GUIDELINE_SCORE: [u32; 5] = [0, 100, 300, 500, 800];
+// No lines, single, double, triple
+const GUIDELINE_T_SPIN_SCORE: [u32; 4] = [400, 800, 1200, 1600];
+// No lines, single, double
+const GUIDELINE_MINI_T_SPIN_SCORE: [u32; 3] = [100, 200, 400];
+// Single, double, triple, tetris
+const GUIDELINE_PERFECT_CLEAR_SCORE: [u32; 4] = [800, 1200, 1800, 2000];
+const GUIDELINE_B2B_PERFECT_CLEAR_SCORE: u32 = 3200;
+const GUIDELINE_COMBO_SCORE: u32 = 50;
+
+impl ScoringRule for Guideline {
+    fn lock(&self, lock: &ScoredLock) -> u32 {
+        let lines = lock.clear.lines as usize;
+        let level = lock.level + 1;
+
+        let mut score = match lock.clear.t_spin {
+            TSpin::None => GUIDELINE_SCORE[lines.min(GUIDELINE_SCORE.len() - 1)],
+            TSpin::Mini => {
+                GUIDELINE_MINI_T_SPIN_SCORE[lines.min(GUIDELINE_MINI_T_SPIN_SCORE.len() - 1)]
+            }
+            TSpin::Full => GUIDELINE_T_SPIN_SCORE[lines.min(GUIDELINE_T_SPIN_SCORE.len() - 1)],
+        };
+        if lock.back_to_back {
+            score += score / 2;
+        }
+        if lock.clear.perfect_clear && lines > 0 {
+            score += if lock.back_to_back && lines >= 4 {
+                GUIDELINE_B2B_PERFECT_CLEAR_SCORE
+            } else {
+                GUIDELINE_PERFECT_CLEAR_SCORE[(lines - 1).min(3)]
+            };
+        }
+        if let Some(combo) = lock.combo {
+            score += GUIDELINE_COMBO_SCORE * combo;
+        }
+        score * level
+    }
+
+    fn soft_drop(&self, _level: u32) -> u32 {
+        1
+    }
+
+    fn hard_drop(&self, _level: u32, rows: u32) -> u32 {
+        2 * rows
+    }
+}
+
+// Single, double, triple, tetris, based off of gameboy
+const SCORE: [u32; 4] = [40, 100, 300, 1200];
+// No lines, single, double, triple, based off of the guideline
+const T_SPIN_SCORE: [u32; 4] = [400, 800, 1200, 1600];
+// No lines, single, double
+const MINI_T_SPIN_SCORE: [u32; 3] = [100, 200, 400];
+
+/// The original table this game shipped with, a flat Game Boy score per clear with guideline T-spin values
+pub struct GameBoy;
+
+impl ScoringRule for GameBoy {
+    fn lock(&self, lock: &ScoredLock) -> u32 {
+        // Update score depending on lines cleared, and whether they were cleared with a spin
+        let lines = lock.clear.lines as usize;
+        match lock.clear.t_spin {
+            TSpin::None if lines == 0 => 0,
+            TSpin::None => SCORE[(lines - 1).min(SCORE.len() - 1)],
+            TSpin::Mini => MINI_T_SPIN_SCORE[lines.min(MINI_T_SPIN_SCORE.len() - 1)],
+            TSpin::Full => T_SPIN_SCORE[lines.min(T_SPIN_SCORE.len() - 1)],
+        }
+    }
+}
+
+/// NES scoring, the Game Boy table multiplied by the level (starting at 1).
+/// Knows nothing about spins, and there's no hard drop to reward
+pub struct Nes;
+
+impl ScoringRule for Nes {
+    fn lock(&self, lock: &ScoredLock) -> u32 {
+        match lock.clear.lines {
+            0 => 0,
+            lines => SCORE[(lines as usize - 1).min(SCORE.len() - 1)] * (lock.level + 1),
+        }
+    }
+
+    fn soft_drop(&self, _level: u32) -> u32 {
+        1
+    }
+
+    fn hard_drop(&self, _level: u32, _rows: u32) -> u32 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lock(lines: u32, t_spin: TSpin) -> ScoredLock {
+        ScoredLock {
+            clear: LineClear {
+                lines,
+                t_spin,
+                perfect_clear: false,
+            },
+            level: 0,
+            combo: None,
+            back_to_back: false,
+        }
+    }
+
+    #[test]
+    fn test_guideline_level_and_b2b() {
+        assert_eq!(Guideline.lock(&lock(4, TSpin::None)), 800);
+        assert_eq!(Guideline.lock(&lock(2, TSpin::Full)), 1200);
+
+        let mut b2b_tetris = lock(4, TSpin::None);
+        b2b_tetris.back_to_back = true;
+        b2b_tetris.level = 2;
+        assert_eq!(Guideline.lock(&b2b_tetris), 1200 * 3);
+    }
+
+    #[test]
+    fn test_guideline_combo_and_perfect_clear() {
+        let mut single = lock(1, TSpin::None);
+        single.combo = Some(3);
+        assert_eq!(Guideline.lock(&single), 100 + 150);
+
+        single.combo = None;
+        single.clear.perfect_clear = true;
+        assert_eq!(Guideline.lock(&single), 100 + 800);
+    }
+
+    #[test]
+    fn test_nes_multiplies_by_level() {
+        let mut tetris = lock(4, TSpin::Full);
+        tetris.level = 9;
+        assert_eq!(Nes.lock(&tetris), 12000);
+        assert_eq!(GameBoy.lock(&lock(4, TSpin::None)), 1200);
+    }
+}
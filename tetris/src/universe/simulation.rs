@@ -0,0 +1,1080 @@
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::clear::{self, LineClear, TSpin};
+use super::entry_delay::{EntryDelay, InitialActions};
+use super::finesse::{FinesseTracker, Judgement};
+use super::game::{Game, GameState};
+use super::garbage;
+use super::lock_delay::{LockDelay, LockReset};
+use super::mode::GameMode;
+use super::playfield::Playfield;
+use super::randomizer::{Randomizer, RandomizerPreset};
+use super::replay::Replay;
+use super::rotations::rotation_direction::RotationDirection;
+use super::rotations::{self, RotationPreset, RotationSystem};
+use super::scoring::ScoringPreset;
+use super::stats::Stats;
+use super::Dimensions;
+use crate::config::Config;
+use crate::tetromino::piece_set::PieceSet;
+use crate::tetromino::{tetromino_type::TetrominoType, MoveDirection, Tetromino};
+
+/// Everything a player can ask of the game, independent of whatever key it's bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GameAction {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCW,
+    RotateCCW,
+    Rotate180,
+    Hold,
+    Pause,
+    Restart,
+    /// Puts the focused piece back where it spawned the next time it would lock, for finesse practice.
+    /// Never bound to a key, the simulation records it itself so that replays try pieces again too
+    Retry,
+}
+
+impl GameAction {
+    /// Every action that can be bound to a key, in the order they're carried out when pressed on the same tick
+    pub const ALL: [GameAction; 10] = [
+        GameAction::MoveLeft,
+        GameAction::MoveRight,
+        GameAction::SoftDrop,
+        GameAction::RotateCW,
+        GameAction::RotateCCW,
+        GameAction::Rotate180,
+        GameAction::Hold,
+        GameAction::HardDrop,
+        GameAction::Pause,
+        GameAction::Restart,
+    ];
+
+    /// Name of the action in the settings file
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameAction::MoveLeft => "move_left",
+            GameAction::MoveRight => "move_right",
+            GameAction::SoftDrop => "soft_drop",
+            GameAction::HardDrop => "hard_drop",
+            GameAction::RotateCW => "rotate_cw",
+            GameAction::RotateCCW => "rotate_ccw",
+            GameAction::Rotate180 => "rotate_180",
+            GameAction::Hold => "hold",
+            GameAction::Pause => "pause",
+            GameAction::Restart => "restart",
+            GameAction::Retry => "retry",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// Things that happened during a tick, which whoever's driving the simulation can react to (e.g. with sounds)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The focused tetromino locked into the playfield
+    Locked,
+    /// The lock was worth something, i.e. it cleared lines, spun, or both
+    Cleared(LineClear),
+    /// Level went up to the given level
+    LevelUp(u32),
+    Held,
+    Paused,
+    Resumed,
+    /// A new session started from scratch
+    Restarted,
+    /// The stack reached the top, which ends the game
+    ToppedOut,
+    /// Lines of garbage sent to the opponent, left over after cancelling any incoming garbage
+    Attack(u32),
+    /// The focused tetromino locked, or was about to, with more key presses than it needed
+    FinesseFault(Judgement),
+}
+
+/// Every setting that changes how a game plays out
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    pub ticks: u32,
+    pub scoring: ScoringPreset,
+    pub randomizer: RandomizerPreset,
+    pub rotation: RotationPreset,
+    pub lock_delay: u32,
+    pub lock_reset: LockReset,
+    pub entry_delay: EntryDelay,
+    pub previews: usize,
+    pub mode: GameMode,
+    // Board size in cells, see `Dimensions`
+    pub width: u32,
+    pub height: u32,
+    pub buffer: u32,
+    // Pieces dealt by the randomizer
+    pub pieces: PieceSet,
+}
+
+impl Rules {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            ticks: *config.ticks(),
+            scoring: config.scoring(),
+            randomizer: config.randomizer(),
+            rotation: config.rotation(),
+            lock_delay: config.lock_delay(),
+            lock_reset: config.lock_reset(),
+            entry_delay: config.entry_delay(),
+            previews: config.previews(),
+            mode: config.mode(),
+            width: config.board_width(),
+            height: config.board_height(),
+            buffer: config.buffer_height(),
+            pieces: config.pieces().clone(),
+        }
+    }
+
+    /// Overwrites the config's rules. Ticks only take effect before the context is built
+    pub fn apply(&self, config: &mut Config) {
+        config.set_ticks(self.ticks);
+        config.set_scoring(self.scoring);
+        config.set_randomizer(self.randomizer);
+        config.set_rotation(self.rotation);
+        config.set_lock_delay(self.lock_delay);
+        config.set_lock_reset(self.lock_reset);
+        config.set_entry_delay(self.entry_delay);
+        config.set_previews(self.previews);
+        config.set_mode(self.mode);
+        config.set_board(self.width, self.height, self.buffer);
+        config.set_pieces(self.pieces.clone());
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+/// The rules of the game, without a window, GPU or audio device.
+/// Actions go in every tick, events come out
+pub struct Simulation {
+    // Board
+    dim: Dimensions,
+    rules: Rules,
+    // Player controlled tetrimino
+    focused_tetromino: Tetromino,
+    ghost: Tetromino,
+    // Tetromino type set aside by holding
+    held: Option<TetrominoType>,
+    // Whether hold was used since the last tetromino locked
+    hold_locked: bool,
+    // Upcoming tetrominos, filled ahead of time by the randomizer
+    next_queue: VecDeque<TetrominoType>,
+    // Decides which tetromino comes next
+    randomizer: Box<dyn Randomizer>,
+    // Decides where the focused tetromino can turn to
+    rotation: Box<dyn RotationSystem>,
+    // Every session starts from this seed if set, or a fresh one otherwise
+    fixed_seed: Option<u64>,
+    // Seed the session started from, kept around so that it can be reproduced
+    seed: u64,
+    // Every source of randomness in the session draws from this.
+    // ChaCha rather than `StdRng`, since its output is guaranteed not to change between releases
+    rng: ChaCha8Rng,
+    // Except for garbage holes, which have their own stream so that
+    // two boards started from the same seed get the same tetrominos no matter the garbage
+    garbage_rng: ChaCha8Rng,
+    // Garbage sent by the opponent that hasn't risen yet, oldest first
+    incoming_garbage: VecDeque<u32>,
+    // Kick used by the last rotation, if rotating was the last thing that moved the focused tetromino
+    pub(super) last_rotation: Option<(RotationDirection, usize)>,
+    // Counts down until the focused tetromino locks
+    pub(super) lock_delay: LockDelay,
+    // Locked cells on board
+    playfield: Playfield,
+    // Full rows waiting out the line clear delay before they're cleared, from the bottom up
+    clearing: Vec<u32>,
+    // Turns and holds pressed during the delays, for the next tetromino to spawn with
+    initial_actions: InitialActions,
+    // Tetrominos locked this session
+    pieces: u32,
+    // How the session's being played, for the HUD and the results
+    stats: Stats,
+    // Key presses reported for the coming tick
+    presses: Vec<GameAction>,
+    // Key presses that went into the focused tetromino
+    finesse: FinesseTracker,
+    // Whether finesse faults put the tetromino back where it spawned rather than locking it
+    finesse_practice: bool,
+    // Whether turning 180 degrees counts as a single press when judging finesse
+    half_turns: bool,
+    // Whether the next lock gets tried again instead, from a recorded `GameAction::Retry`
+    pub(super) retry: bool,
+    // Every action since the session started
+    recording: Replay,
+    // Game mechanics
+    game: Game,
+    // Events since the last tick returned them
+    events: Vec<Event>,
+}
+
+impl Simulation {
+    /// Starts a session, from `seed` if it's set and a fresh seed otherwise.
+    /// Restarting sticks to the same choice
+    pub fn new(rules: Rules, seed: Option<u64>) -> Self {
+        // Wide enough for every piece of the set to spawn
+        let width = rules.width.max(rules.pieces.widest());
+        let dim = Dimensions::new(width, rules.height, rules.buffer);
+        let first_seed = seed.unwrap_or_else(rand::random);
+        // Placeholder until the session starts and deals the first tetromino
+        let focused_tetromino = rules.pieces.spawn(TetrominoType::default(), &dim);
+        let mut sim = Simulation {
+            playfield: Playfield::new(&dim),
+            clearing: Vec::new(),
+            initial_actions: InitialActions::default(),
+            pieces: 0,
+            stats: Stats::default(),
+            presses: Vec::new(),
+            finesse: FinesseTracker::default(),
+            finesse_practice: false,
+            half_turns: true,
+            retry: false,
+            dim,
+            ghost: focused_tetromino.clone(),
+            focused_tetromino,
+            held: None,
+            hold_locked: false,
+            last_rotation: None,
+            lock_delay: LockDelay::new(rules.lock_delay, rules.lock_reset),
+            next_queue: VecDeque::new(),
+            randomizer: rules.randomizer.build(&rules.pieces),
+            rotation: rules.rotation.system(),
+            fixed_seed: seed,
+            seed: first_seed,
+            rng: ChaCha8Rng::seed_from_u64(first_seed),
+            garbage_rng: ChaCha8Rng::seed_from_u64(!first_seed),
+            incoming_garbage: VecDeque::new(),
+            recording: Replay::new(first_seed, rules.clone()),
+            game: Game::default(),
+            events: Vec::new(),
+            rules,
+        };
+        sim.start(first_seed);
+        sim
+    }
+
+    /// Plays out a tick with the given actions, returning everything that happened
+    pub fn tick(&mut self, actions: &[GameAction]) -> Vec<Event> {
+        // Count down, run out delays, or keep playing
+        let before = self.game.state();
+        self.game.tick();
+        match before {
+            // Everything above the cleared rows falls once they've been shown for long enough
+            GameState::LineClearDelay(0) => {
+                self.clear_rows();
+                if self.game.running() {
+                    self.spawn_focused();
+                }
+            }
+            GameState::Entry(0) => self.spawn_focused(),
+            _ => {}
+        }
+        // Mashing keys while paused or once the game's over doesn't play anything
+        let presses = std::mem::take(&mut self.presses);
+        if self.game.running() {
+            self.stats.press(&presses);
+            self.finesse.press(&presses);
+        } else if self.buffering() {
+            // Turns and holds for the next tetromino count towards it once it's spawned
+            self.presses = presses
+                .into_iter()
+                .filter(|&action| InitialActions::keeps(action))
+                .collect();
+        }
+
+        // Keep track of the actions, so that the game can be played back later
+        self.recording.record(actions);
+        for &action in actions {
+            self.apply(action);
+            // Restarting starts a new recording, and whatever came after it this tick was meant for the old game
+            if action == GameAction::Restart {
+                break;
+            }
+        }
+
+        if self.game.running() {
+            // update preview/ghost
+            self.full_fall_focused();
+
+            // Literally just move current .y down
+            if self.game.should_fall() {
+                self.fall_focused();
+            }
+
+            // Lock the focused tetromino once it's spent long enough on the stack
+            if self.lock_delay.tick(self.grounded()) {
+                self.generate_new_tetromino();
+            }
+        }
+
+        std::mem::take(&mut self.events)
+    }
+
+    /// Counts keys pressed for the coming tick towards the stats and finesse, for whoever's driving the simulation to report.
+    /// Held keys repeating aren't presses, which can't be told apart from the actions alone
+    pub fn press(&mut self, presses: &[GameAction]) {
+        self.recording.record_presses(presses);
+        self.presses.extend_from_slice(presses);
+    }
+
+    /// Judges the focused tetromino's finesse as it's about to lock, returning whether it has to be tried again instead.
+    /// Practice tries faults again, and so does anything recorded as tried again
+    fn retry_focused(&mut self) -> bool {
+        let spawned = self.spawn(*self.focused_tetromino.tetromino_type());
+        let fault = match self.finesse.judge(
+            &spawned,
+            &self.focused_tetromino,
+            &self.dim,
+            self.rotation.as_ref(),
+            self.half_turns,
+        ) {
+            Some(judgement) => {
+                self.stats.judge(judgement.is_fault());
+                judgement.is_fault().then_some(judgement)
+            }
+            None => None,
+        };
+        if let Some(fault) = fault {
+            self.events.push(Event::FinesseFault(fault));
+        }
+        let recorded = std::mem::take(&mut self.retry);
+        let practice = self.finesse_practice && fault.is_some();
+        if !recorded && !practice {
+            return false;
+        }
+        if practice && !recorded {
+            self.recording.record_retry();
+        }
+        self.focused_tetromino = spawned;
+        self.finesse.reset();
+        self.last_rotation = None;
+        self.lock_delay.on_spawn(self.focused_tetromino.lowest_y());
+        self.full_fall_focused();
+        true
+    }
+
+    fn within_boundary(&self) -> bool {
+        self.focused_tetromino
+            .within_boundary(Tetromino::get_dxdy(MoveDirection::Down), &self.dim)
+    }
+
+    /// Spawns the tetromino at the front of the next queue, topping the queue back up
+    fn next_tetromino(&mut self) -> Tetromino {
+        self.next_queue
+            .push_back(self.randomizer.next(&mut self.rng));
+        let next = self.next_queue.pop_front().unwrap();
+        self.spawn(next)
+    }
+
+    /// Spawns a tetromino in the shape its rotation system spawns it in
+    pub fn spawn(&self, tetromino_type: TetrominoType) -> Tetromino {
+        rotations::spawn(
+            &self.rules.pieces,
+            tetromino_type,
+            &self.dim,
+            self.rotation.as_ref(),
+        )
+    }
+
+    /// Locks the focused tetromino and spawns the next one once the delays are over,
+    /// returning whether it locked rather than being tried again
+    pub(super) fn generate_new_tetromino(&mut self) -> bool {
+        if self.retry_focused() {
+            return false;
+        }
+        // Spins are judged on the board as it was before the tetromino locked
+        let t_spin = match self.last_rotation {
+            Some(rotation) => clear::t_spin(
+                &self.focused_tetromino,
+                rotation,
+                self.rotation.as_ref(),
+                &self.playfield,
+                &self.dim,
+            ),
+            None => TSpin::None,
+        };
+
+        // Solidify the tetromino by locking it into the playfield
+        let t = self.focused_tetromino.clone();
+        self.playfield.lock(&t);
+        self.pieces += 1;
+        self.events.push(Event::Locked);
+
+        self.clearing = self.playfield.full_rows();
+        let clear = LineClear {
+            lines: self.clearing.len() as u32,
+            t_spin,
+            perfect_clear: !self.clearing.is_empty()
+                && self.playfield.is_empty_without(&self.clearing),
+        };
+        if !clear.is_empty() {
+            self.events.push(Event::Cleared(clear));
+        }
+        if self.game.update(clear) {
+            self.events.push(Event::LevelUp(*self.game.level()));
+        }
+
+        // The game knows whether the clear kept a back-to-back chain going, which is worth an extra line
+        let back_to_back = clear.is_difficult() && self.game.back_to_back().is_some_and(|b| b > 0);
+        let attack = garbage::attack(&clear, self.game.combo(), back_to_back);
+        self.stats.lock(
+            *t.tetromino_type(),
+            &clear,
+            attack,
+            self.game.combo(),
+            self.game.back_to_back(),
+        );
+        self.send_garbage(attack);
+        // Incoming garbage rises on any lock that doesn't clear something, if it pushed the stack out the top, game ova
+        if clear.lines == 0 && self.raise_garbage() {
+            self.top_out();
+        }
+
+        // Cleared rows stay up for the line clear delay, and the next tetromino waits for the entry delay
+        let delayed = self.game.delay_entry(clear.lines);
+        if !matches!(self.game.state(), GameState::LineClearDelay(_)) {
+            self.clear_rows();
+        }
+        if !delayed {
+            self.spawn_focused();
+        }
+        true
+    }
+
+    /// Deletes the full rows left from the last lock and moves everything above them down (gravity)
+    fn clear_rows(&mut self) {
+        let rows = std::mem::take(&mut self.clearing);
+        if !rows.is_empty() {
+            self.playfield.clear_rows(&rows);
+        }
+    }
+
+    /// Spawns the next tetromino, turning or holding it straight away if that was buffered during the delays
+    fn spawn_focused(&mut self) {
+        self.focused_tetromino = self.next_tetromino();
+        self.finesse.reset();
+        // Hold is allowed again now that a tetromino locked
+        self.hold_locked = false;
+        self.last_rotation = None;
+        self.lock_delay.on_spawn(self.focused_tetromino.lowest_y());
+        self.full_fall_focused();
+
+        let initial = std::mem::take(&mut self.initial_actions);
+        if initial.hold {
+            self.hold();
+        }
+        if let Some(direction) = initial.rotation {
+            self.rotate_focused(direction);
+            self.full_fall_focused();
+        }
+        // If it generates into a piece, game ova
+        self.check_top_out();
+    }
+
+    /// Whether turns and holds are kept for the next tetromino right now, i.e. during the delays if the rules allow it
+    pub(super) fn buffering(&self) -> bool {
+        self.rules.entry_delay.buffer
+            && matches!(
+                self.game.state(),
+                GameState::LineClearDelay(_) | GameState::Entry(_)
+            )
+    }
+
+    /// Keeps a turn or hold for the next tetromino, dropping anything else
+    pub(super) fn buffer(&mut self, action: GameAction) {
+        self.initial_actions.buffer(action);
+    }
+
+    /// Cancels as much incoming garbage as possible with `attack` lines, sending on whatever's left
+    fn send_garbage(&mut self, mut attack: u32) {
+        while let Some(incoming) = self.incoming_garbage.front_mut() {
+            if attack == 0 {
+                break;
+            }
+            let cancelled = attack.min(*incoming);
+            *incoming -= cancelled;
+            attack -= cancelled;
+            if *incoming == 0 {
+                self.incoming_garbage.pop_front();
+            }
+        }
+        if attack > 0 {
+            self.events.push(Event::Attack(attack));
+        }
+    }
+
+    /// Pushes every incoming garbage line up into the playfield, each attack with a single hole in a random column.
+    /// Returns whether any of the stack got pushed out the top
+    fn raise_garbage(&mut self) -> bool {
+        let mut overflowed = false;
+        while let Some(lines) = self.incoming_garbage.pop_front() {
+            let hole = self.garbage_rng.gen_range(0..self.dim.w);
+            overflowed |= self.playfield.add_garbage(lines, hole);
+        }
+        overflowed
+    }
+
+    /// Queues up garbage sent by the opponent, which rises once a lock doesn't clear anything
+    pub fn receive_garbage(&mut self, lines: u32) {
+        if lines > 0 {
+            self.incoming_garbage.push_back(lines);
+        }
+    }
+
+    /// Swapping or spawning into a piece is game over, if it isn't over already
+    fn check_top_out(&mut self) {
+        if self.game.running()
+            && self
+                .focused_tetromino
+                .will_collide_towards(&self.playfield, MoveDirection::None)
+        {
+            self.top_out();
+        }
+    }
+
+    /// Ends the game, unless the mode can't be topped out
+    fn top_out(&mut self) {
+        if self.rules.mode.can_top_out() {
+            self.game.top_out();
+            self.events.push(Event::ToppedOut);
+        } else {
+            // Modes without topping out just start over on an empty board
+            self.clear();
+            self.full_fall_focused();
+        }
+    }
+
+    /// Swaps the focused tetromino with the held one, or the next one if nothing is held.
+    /// Only allowed once until the next tetromino locks
+    pub(super) fn hold(&mut self) {
+        if self.hold_locked {
+            return;
+        }
+        let current = *self.focused_tetromino.tetromino_type();
+        // Spawn orientation and position, regardless of how it was moved before it was held
+        self.focused_tetromino = match self.held.replace(current) {
+            Some(held) => self.spawn(held),
+            None => self.next_tetromino(),
+        };
+        self.hold_locked = true;
+        self.last_rotation = None;
+        self.finesse.reset();
+        self.lock_delay.on_spawn(self.focused_tetromino.lowest_y());
+        self.full_fall_focused();
+        self.events.push(Event::Held);
+
+        self.check_top_out();
+    }
+
+    /// Whether the focused tetromino is resting on the stack or the floor
+    fn grounded(&self) -> bool {
+        !self.within_boundary()
+            || self
+                .focused_tetromino
+                .will_collide_towards(&self.playfield, MoveDirection::Down)
+    }
+
+    /// Moves the focused tetromino down a row, returning whether there was room to.
+    /// Locking is left to the lock delay
+    pub(super) fn fall_focused(&mut self) -> bool {
+        if self.grounded() {
+            return false;
+        }
+        self.focused_tetromino
+            .move_by(Tetromino::get_dxdy(MoveDirection::Down));
+        self.last_rotation = None;
+        self.lock_delay.on_fall(self.focused_tetromino.lowest_y());
+        true
+    }
+
+    /// Implmentation of hard drop preview
+    pub fn full_fall_focused(&mut self) {
+        self.ghost = self.focused_tetromino.clone();
+
+        // While it's within boundary and won't collide with anything
+        // TODO I swear I have no clue wtf I'm looking at but
+        while self
+            .ghost
+            .within_boundary(Tetromino::get_dxdy(MoveDirection::Down), &self.dim)
+            && !self
+                .ghost
+                .will_collide_towards(&self.playfield, MoveDirection::Down)
+        {
+            // Move it down
+            self.ghost.move_by(Tetromino::get_dxdy(MoveDirection::Down));
+        }
+    }
+
+    fn clear(&mut self) {
+        self.playfield.clear();
+    }
+
+    /// Starts a fresh session, from the fixed seed if there is one or a new one otherwise.
+    /// The same seed with the same actions always plays out the same game
+    pub fn restart(&mut self) {
+        self.start(self.fixed_seed.unwrap_or_else(rand::random));
+        self.events.push(Event::Restarted);
+    }
+
+    /// Resets the board, game, and randomness to the start of a session played from `seed`
+    pub fn start(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.garbage_rng = ChaCha8Rng::seed_from_u64(!seed);
+        self.incoming_garbage.clear();
+        self.recording = Replay::new(seed, self.rules.clone());
+        self.recording.set_half_turns(self.half_turns);
+        self.randomizer.reset();
+        // Clear board
+        self.clear();
+        self.clearing.clear();
+        self.initial_actions = InitialActions::default();
+        self.pieces = 0;
+        self.stats = Stats::default();
+        self.presses.clear();
+        self.finesse.reset();
+        self.retry = false;
+        // Create new game instance
+        self.game = Game::new(self.rules.scoring.rule(), self.rules.mode, self.rules.ticks)
+            .with_entry_delay(self.rules.entry_delay);
+        // Deal the first tetromino, then fill the queue behind it
+        let first = self.randomizer.next(&mut self.rng);
+        self.focused_tetromino = self.spawn(first);
+        self.ghost = self.focused_tetromino.clone();
+        self.held = None;
+        self.hold_locked = false;
+        self.last_rotation = None;
+        self.lock_delay = LockDelay::new(self.rules.lock_delay, self.rules.lock_reset);
+        self.lock_delay.on_spawn(self.focused_tetromino.lowest_y());
+        self.next_queue.clear();
+        for _ in 0..self.rules.previews {
+            self.next_queue
+                .push_back(self.randomizer.next(&mut self.rng));
+        }
+    }
+
+    /// Pauses or resumes the game
+    pub(super) fn toggle_pause(&mut self) {
+        let before = self.game.state();
+        let paused = self.game.toggle_pause();
+        if self.game.state() != before {
+            self.events.push(if paused {
+                Event::Paused
+            } else {
+                Event::Resumed
+            });
+        }
+    }
+}
+
+// Getters and setters
+impl Simulation {
+    /// Get a reference to the simulation's board dimensions
+    pub fn dim(&self) -> &Dimensions {
+        &self.dim
+    }
+
+    /// Get the rules the simulation plays by
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Get the set of pieces the randomizer deals from
+    pub fn piece_set(&self) -> &PieceSet {
+        &self.rules.pieces
+    }
+
+    /// Get the rotation system the focused tetromino turns by
+    pub fn rotation_system(&self) -> &dyn RotationSystem {
+        self.rotation.as_ref()
+    }
+
+    /// Get a reference to the simulation's game
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub(super) fn game_mut(&mut self) -> &mut Game {
+        &mut self.game
+    }
+
+    /// Get a reference to the simulation's current.
+    pub fn focused_tetromino(&self) -> &Tetromino {
+        &self.focused_tetromino
+    }
+
+    /// Get a mutable reference to the simulation's current.
+    pub fn focused_tetromino_mut(&mut self) -> &mut Tetromino {
+        &mut self.focused_tetromino
+    }
+
+    /// Get a reference to the simulation's ghost.
+    pub fn ghost(&self) -> &Tetromino {
+        &self.ghost
+    }
+
+    /// Get a reference to the simulation's playfield.
+    pub fn playfield(&self) -> &Playfield {
+        &self.playfield
+    }
+
+    pub fn playfield_mut(&mut self) -> &mut Playfield {
+        &mut self.playfield
+    }
+
+    /// Get the full rows waiting out the line clear delay, from the bottom up
+    pub fn clearing(&self) -> &[u32] {
+        &self.clearing
+    }
+
+    /// Get the number of tetrominos locked this session
+    pub fn pieces(&self) -> u32 {
+        self.pieces
+    }
+
+    /// Get how the session's been played so far
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Get the held tetromino type, if anything is held
+    pub fn held(&self) -> Option<TetrominoType> {
+        self.held
+    }
+
+    /// Get whether hold was already used since the last tetromino locked
+    pub fn hold_locked(&self) -> bool {
+        self.hold_locked
+    }
+
+    /// Get the total lines of garbage waiting to rise
+    pub fn incoming_garbage(&self) -> u32 {
+        self.incoming_garbage.iter().sum()
+    }
+
+    /// Get the upcoming tetrominos, front of the queue spawns next
+    pub fn next_queue(&self) -> &VecDeque<TetrominoType> {
+        &self.next_queue
+    }
+
+    /// Get the seed the current session started from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Get every action since the session started
+    pub fn recording(&self) -> &Replay {
+        &self.recording
+    }
+
+    /// Sets whether finesse faults get tried again rather than locking, and whether turning 180 degrees is a single press
+    pub fn set_finesse(&mut self, practice: bool, half_turns: bool) {
+        self.finesse_practice = practice;
+        self.half_turns = half_turns;
+        self.recording.set_half_turns(half_turns);
+    }
+
+    /// Swaps out the randomizer, which takes effect from the next tetromino onwards.
+    /// Replays only know about the configured preset, so a custom randomizer won't play back
+    pub fn set_randomizer(&mut self, randomizer: Box<dyn Randomizer>) {
+        self.randomizer = randomizer;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tetromino::coord::Coord;
+    use crate::universe::ai::{AiPlayer, Weights};
+    use crate::universe::game::GameState;
+    use crate::universe::playfield::Cell;
+
+    fn sim() -> Simulation {
+        sim_with_mode(GameMode::default())
+    }
+
+    fn sim_with_mode(mode: GameMode) -> Simulation {
+        let mut sim = Simulation::new(
+            Rules {
+                ticks: 10,
+                mode,
+                ..Rules::default()
+            },
+            Some(7),
+        );
+        // Through the countdown
+        while sim.game().state() != GameState::Playing {
+            sim.tick(&[]);
+        }
+        sim
+    }
+
+    #[test]
+    fn test_hard_drop_locks() {
+        let mut sim = sim();
+        let events = sim.tick(&[GameAction::HardDrop]);
+        assert!(events.contains(&Event::Locked));
+        let locked = (0..10)
+            .flat_map(|x| (0..2).map(move |y| (x, y)))
+            .filter(|&(x, y)| sim.playfield().is_occupied(x, y))
+            .count();
+        assert_eq!(locked, 4);
+    }
+
+    #[test]
+    fn test_stacking_tops_out() {
+        let mut sim = sim();
+        let topped_out = (0..100).any(|_| {
+            sim.tick(&[GameAction::HardDrop])
+                .contains(&Event::ToppedOut)
+        });
+        assert!(topped_out);
+        assert!(!sim.game().running());
+    }
+
+    #[test]
+    fn test_garbage_cancels_then_rises() {
+        let mut sim = sim();
+        sim.receive_garbage(3);
+        sim.receive_garbage(2);
+        assert_eq!(sim.incoming_garbage(), 5);
+        sim.send_garbage(4);
+        assert_eq!(sim.incoming_garbage(), 1);
+        sim.send_garbage(3);
+        assert_eq!(sim.incoming_garbage(), 0);
+        assert!(sim.tick(&[]).contains(&Event::Attack(2)));
+
+        // A lock that doesn't clear anything lets garbage in underneath it
+        sim.receive_garbage(2);
+        sim.tick(&[GameAction::HardDrop]);
+        assert_eq!(sim.incoming_garbage(), 0);
+        let garbage = (0..10)
+            .filter(|&x| sim.playfield().get(x, 1) == Some(Cell::Garbage))
+            .count();
+        assert_eq!(garbage, 9);
+    }
+
+    #[test]
+    fn test_zen_never_tops_out() {
+        let mut sim = sim_with_mode(GameMode::Zen);
+        let topped_out = (0..100).any(|_| {
+            sim.tick(&[GameAction::HardDrop])
+                .contains(&Event::ToppedOut)
+        });
+        assert!(!topped_out);
+        assert!(sim.game().running());
+    }
+
+    #[test]
+    fn test_half_turn_is_two_quarter_turns() {
+        let mut half = sim();
+        let mut quarters = sim();
+        half.tick(&[GameAction::Rotate180]);
+        quarters.tick(&[GameAction::RotateCW]);
+        quarters.tick(&[GameAction::RotateCW]);
+        assert_eq!(
+            half.focused_tetromino().coords(),
+            quarters.focused_tetromino().coords()
+        );
+        assert_ne!(
+            half.focused_tetromino().coords(),
+            sim().focused_tetromino().coords()
+        );
+    }
+
+    #[test]
+    fn test_recording_plays_back_the_same_game() {
+        let mut sim = sim();
+        let script = [
+            GameAction::MoveLeft,
+            GameAction::RotateCW,
+            GameAction::HardDrop,
+            GameAction::Hold,
+            GameAction::MoveRight,
+            GameAction::MoveRight,
+            GameAction::SoftDrop,
+            GameAction::HardDrop,
+        ];
+        for _ in 0..5 {
+            for &action in script.iter() {
+                sim.tick(&[action]);
+                sim.tick(&[]);
+            }
+        }
+
+        let replayed = sim.recording().simulate();
+        assert_eq!(replayed.playfield(), sim.playfield());
+        assert_eq!(replayed.game().score(), sim.game().score());
+        assert_eq!(replayed.next_queue(), sim.next_queue());
+    }
+
+    #[test]
+    fn test_restarting_mid_tick_plays_back_the_same_game() {
+        let mut sim = sim();
+        sim.tick(&[GameAction::MoveLeft, GameAction::HardDrop]);
+        // A retry after the restart would hold up the new game's first lock, without being in its recording
+        sim.tick(&[GameAction::Restart, GameAction::Retry, GameAction::MoveLeft]);
+        while sim.game().state() != GameState::Playing {
+            sim.tick(&[]);
+        }
+        sim.tick(&[GameAction::HardDrop]);
+        assert_eq!(sim.pieces(), 1);
+
+        // Through a replay file and back
+        let replay: Replay = sim.recording().to_string().parse().unwrap();
+        let replayed = replay.simulate();
+        assert_eq!(replayed.pieces(), sim.pieces());
+        assert_eq!(replayed.playfield(), sim.playfield());
+    }
+
+    #[test]
+    fn test_practice_retries_finesse_faults_and_replays_them() {
+        let mut sim = sim();
+        sim.set_finesse(true, true);
+        let spawned = sim.focused_tetromino().clone();
+        // There and back again is two presses for a placement that needed none
+        for action in [GameAction::MoveLeft, GameAction::MoveRight] {
+            sim.press(&[action]);
+            sim.tick(&[action]);
+        }
+        sim.press(&[GameAction::HardDrop]);
+        let events = sim.tick(&[GameAction::HardDrop]);
+        assert!(events.contains(&Event::FinesseFault(Judgement {
+            used: 2,
+            minimal: 0
+        })));
+        assert_eq!(sim.pieces(), 0);
+        // Back where it spawned, give or take gravity
+        let columns = |t: &Tetromino| t.coords().iter().map(|c| c.x).collect::<Vec<u32>>();
+        assert_eq!(columns(sim.focused_tetromino()), columns(&spawned));
+        assert_eq!(sim.stats().finesse_faults(), 1);
+
+        // Straight down is fine
+        sim.press(&[GameAction::HardDrop]);
+        sim.tick(&[GameAction::HardDrop]);
+        assert_eq!(sim.pieces(), 1);
+        assert_eq!(sim.stats().finesse(), Some(50.0));
+
+        // Played back without practice, the retry is in the recording and the presses judge the same
+        let replayed = sim.recording().simulate();
+        assert_eq!(replayed.pieces(), 1);
+        assert_eq!(replayed.playfield(), sim.playfield());
+        assert_eq!(replayed.stats(), sim.stats());
+    }
+
+    #[test]
+    fn test_entry_delays_show_the_clear_then_spawn_with_buffered_actions() {
+        let mut sim = Simulation::new(
+            Rules {
+                ticks: 10,
+                entry_delay: EntryDelay {
+                    are: 3,
+                    line_clear_are: 2,
+                    line_clear_delay: 4,
+                    buffer: true,
+                },
+                ..Rules::default()
+            },
+            Some(7),
+        );
+        while sim.game().state() != GameState::Playing {
+            sim.tick(&[]);
+        }
+        // Fill the floor but for where the focused tetromino lands
+        let landing: Vec<u32> = sim
+            .ghost()
+            .coords()
+            .iter()
+            .filter(|c| c.y == 0)
+            .map(|c| c.x)
+            .collect();
+        let floor = (0..sim.dim().w)
+            .filter(|x| !landing.contains(x))
+            .map(|x| Coord::new(x, 0))
+            .collect::<Vec<_>>();
+        let piece = PieceSet::standard().find("I").unwrap().clone();
+        sim.playfield
+            .lock(&Tetromino::spawn_tetromino(floor.clone(), floor[0], piece));
+
+        // The full row stays up until the line clear delay runs out
+        sim.tick(&[GameAction::HardDrop]);
+        assert_eq!(sim.game().state(), GameState::LineClearDelay(4));
+        assert_eq!(sim.clearing(), &[0]);
+        for _ in 0..4 {
+            sim.tick(&[]);
+        }
+        assert!(sim.playfield().is_row_full(0));
+        sim.tick(&[]);
+        assert!(sim.clearing().is_empty());
+        assert!(!sim.playfield().is_row_full(0));
+        assert_eq!(sim.game().state(), GameState::Entry(1));
+        sim.tick(&[]);
+        sim.tick(&[]);
+        assert!(sim.game().running());
+        assert_eq!(sim.pieces(), 1);
+
+        // Turning and holding during the entry delay carries over to the next tetromino
+        let next = sim.next_queue()[0];
+        sim.tick(&[GameAction::HardDrop]);
+        assert_eq!(sim.game().state(), GameState::Entry(3));
+        sim.tick(&[GameAction::Hold, GameAction::RotateCW]);
+        sim.tick(&[GameAction::MoveLeft]);
+        sim.tick(&[]);
+        assert!(!sim.game().running());
+        let events = sim.tick(&[]);
+        assert!(sim.game().running());
+        assert!(events.contains(&Event::Held));
+        assert_eq!(sim.held(), Some(next));
+        assert_eq!(*sim.focused_tetromino().rotation_state().rn(), 1);
+    }
+
+    #[test]
+    fn test_any_board_size_plays() {
+        for (width, height) in [(4, 20), (10, 20), (20, 20), (10, 40)] {
+            let rules = Rules {
+                ticks: 10,
+                width,
+                height,
+                ..Rules::default()
+            };
+            let dim = Dimensions::new(width, height, rules.buffer);
+            let playfield = Playfield::new(&dim);
+            for tetromino_type in rules.pieces.types() {
+                let spawned = rules.pieces.spawn(tetromino_type, &dim);
+                assert!(spawned.within_boundary([0, 0], &dim));
+                assert!(!spawned.will_collide_diff(&playfield, [0, 0]));
+                // As much room either side, give or take a cell
+                let left = spawned.coords().iter().map(|c| c.x).min().unwrap();
+                let right = width - 1 - spawned.coords().iter().map(|c| c.x).max().unwrap();
+                assert!(left.abs_diff(right) <= 1);
+            }
+
+            // Stacking in the middle tops out
+            let mut sim = Simulation::new(rules.clone(), Some(7));
+            let topped_out = (0..200).any(|_| {
+                sim.tick(&[GameAction::HardDrop]);
+                matches!(sim.game().state(), GameState::GameOver(_))
+            });
+            assert!(topped_out);
+
+            // And every kind of move works from anywhere
+            let mut sim = Simulation::new(rules, Some(7));
+            AiPlayer::new(Weights::default(), 0).play(&mut sim, 150);
+        }
+    }
+}
@@ -0,0 +1,491 @@
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thomas::context::Context;
+use thomas::winit::event::VirtualKeyCode;
+
+use super::clear::{LineClear, TSpin};
+use super::high_scores::format_date;
+use super::mode::format_time;
+use super::simulation::{GameAction, Simulation};
+use super::Universe;
+use crate::files;
+use crate::tetromino::tetromino_type::TetrominoType;
+
+// Name of the file exported statistics go in under the game's data directory, a row per game
+const STATS_FILE: &str = "stats.csv";
+// Shows the full statistics on the results screen
+pub(super) const STATS_KEY: VirtualKeyCode = VirtualKeyCode::T;
+// Adds the game's statistics to the CSV file
+pub(super) const EXPORT_KEY: VirtualKeyCode = VirtualKeyCode::E;
+
+/// Every kind of clear that's counted on its own, along with its CSV column.
+/// Anything clearing more than 4 lines, which only bigger pieces can, is counted together
+const CLEARS: [(TSpin, u32, &str); 11] = [
+    (TSpin::None, 1, "singles"),
+    (TSpin::None, 2, "doubles"),
+    (TSpin::None, 3, "triples"),
+    (TSpin::None, 4, "tetrises"),
+    (TSpin::Mini, 0, "mini_t_spins"),
+    (TSpin::Mini, 1, "mini_t_spin_singles"),
+    (TSpin::Mini, 2, "mini_t_spin_doubles"),
+    (TSpin::Full, 0, "t_spins"),
+    (TSpin::Full, 1, "t_spin_singles"),
+    (TSpin::Full, 2, "t_spin_doubles"),
+    (TSpin::Full, 3, "t_spin_triples"),
+];
+
+/// Running counts of how a session is being played, kept by the simulation as pieces lock
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    // Pieces locked, indexed by their position in the piece set
+    placed: Vec<u32>,
+    // Keys pressed towards playing, i.e. not counting held keys repeating
+    inputs: u32,
+    // Pieces locked since whoever's driving the simulation started reporting key presses.
+    // Replays only hold actions, so watching one never does
+    keyed: Option<u32>,
+    // Lines of garbage sent, before any of it was cancelled
+    attack: u32,
+    // Clears of each kind in `CLEARS`
+    clears: [u32; CLEARS.len()],
+    // Clears of more than 4 lines
+    big_clears: u32,
+    perfect_clears: u32,
+    max_combo: u32,
+    max_back_to_back: u32,
+    // Pieces whose finesse could be judged, and how many of them were faults
+    judged: u32,
+    finesse_faults: u32,
+}
+
+impl Stats {
+    /// Counts key presses for the piece being played. Repeats of held keys aren't presses
+    pub fn press(&mut self, presses: &[GameAction]) {
+        let presses = presses
+            .iter()
+            .filter(|a| !matches!(a, GameAction::Pause | GameAction::Restart))
+            .count();
+        self.inputs += presses as u32;
+        self.keyed.get_or_insert(0);
+    }
+
+    /// Counts a lock of `piece`, along with what it cleared, sent, and the chains it kept going
+    pub fn lock(
+        &mut self,
+        piece: TetrominoType,
+        clear: &LineClear,
+        attack: u32,
+        combo: Option<u32>,
+        back_to_back: Option<u32>,
+    ) {
+        if self.placed.len() <= piece.index() {
+            self.placed.resize(piece.index() + 1, 0);
+        }
+        self.placed[piece.index()] += 1;
+        if let Some(keyed) = self.keyed.as_mut() {
+            *keyed += 1;
+        }
+        self.attack += attack;
+        match CLEARS
+            .iter()
+            .position(|&(t_spin, lines, _)| t_spin == clear.t_spin && lines == clear.lines)
+        {
+            Some(kind) => self.clears[kind] += 1,
+            None if clear.lines > 4 => self.big_clears += 1,
+            None => {}
+        }
+        self.perfect_clears += clear.perfect_clear as u32;
+        self.max_combo = self.max_combo.max(combo.unwrap_or_default());
+        self.max_back_to_back = self.max_back_to_back.max(back_to_back.unwrap_or_default());
+    }
+
+    /// Counts a piece judged on its finesse, `fault` being whether it took more presses than it needed
+    pub fn judge(&mut self, fault: bool) {
+        self.judged += 1;
+        self.finesse_faults += fault as u32;
+    }
+
+    /// Share of judged pieces placed without a finesse fault, in percent, if any were judged
+    pub fn finesse(&self) -> Option<f32> {
+        (self.judged > 0)
+            .then(|| (self.judged - self.finesse_faults) as f32 * 100.0 / self.judged as f32)
+    }
+
+    /// Inputs per piece, if key presses were reported for any of them
+    pub fn kpp(&self) -> Option<f32> {
+        self.keyed
+            .filter(|&keyed| keyed > 0)
+            .map(|keyed| self.inputs as f32 / keyed as f32)
+    }
+
+    /// Pieces per second over `ticks` of play
+    pub fn pps(&self, ticks: u32, ticks_per_second: u32) -> f32 {
+        self.pieces() as f32 * ticks_per_second as f32 / ticks.max(1) as f32
+    }
+
+    /// Attack per minute over `ticks` of play
+    pub fn apm(&self, ticks: u32, ticks_per_second: u32) -> f32 {
+        self.attack as f32 * 60.0 * ticks_per_second as f32 / ticks.max(1) as f32
+    }
+
+    /// Every kind of clear with how many times it happened, by its name on screen and its CSV column
+    pub fn clears(&self) -> Vec<(String, &'static str, u32)> {
+        let mut clears: Vec<(String, &'static str, u32)> = CLEARS
+            .iter()
+            .zip(self.clears)
+            .map(|(&(t_spin, lines, column), count)| {
+                let clear = LineClear {
+                    lines,
+                    t_spin,
+                    perfect_clear: false,
+                };
+                (clear.to_string(), column, count)
+            })
+            .collect();
+        clears.push((String::from("5+ LINES"), "big_clears", self.big_clears));
+        clears.push((
+            String::from("PERFECT CLEAR"),
+            "perfect_clears",
+            self.perfect_clears,
+        ));
+        clears
+    }
+}
+
+// Getters and setters
+impl Stats {
+    /// Get the number of pieces locked
+    pub fn pieces(&self) -> u32 {
+        self.placed.iter().sum()
+    }
+
+    /// Get the number of times `piece` was locked
+    pub fn placed(&self, piece: TetrominoType) -> u32 {
+        self.placed.get(piece.index()).copied().unwrap_or_default()
+    }
+
+    /// Get the number of keys pressed towards playing
+    pub fn inputs(&self) -> u32 {
+        self.inputs
+    }
+
+    /// Get the lines of garbage sent, before any were cancelled
+    pub fn attack(&self) -> u32 {
+        self.attack
+    }
+
+    /// Get the longest combo
+    pub fn max_combo(&self) -> u32 {
+        self.max_combo
+    }
+
+    /// Get the longest back-to-back chain
+    pub fn max_back_to_back(&self) -> u32 {
+        self.max_back_to_back
+    }
+
+    /// Get the number of pieces placed with more key presses than they needed
+    pub fn finesse_faults(&self) -> u32 {
+        self.finesse_faults
+    }
+}
+
+/// Puts a field in quotes if it has to be, for CSV
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// A game's statistics as a CSV header and row, played on `date`
+fn csv(sim: &Simulation, date: u64) -> (String, String) {
+    let game = sim.game();
+    let stats = sim.stats();
+    let (ticks, tps) = (game.elapsed(), game.ticks_per_second());
+    let rules = sim.rules();
+    let distribution: Vec<String> = sim
+        .piece_set()
+        .pieces()
+        .iter()
+        .map(|piece| format!("{}={}", piece.name(), stats.placed(*piece.tetromino_type())))
+        .collect();
+    let mut columns: Vec<(&str, String)> = vec![
+        ("date", format_date(date)),
+        ("mode", rules.mode.name().to_string()),
+        ("scoring", rules.scoring.name().to_string()),
+        ("pieces_set", sim.piece_set().name().to_string()),
+        ("seed", sim.seed().to_string()),
+        (
+            "time_ms",
+            (ticks as u64 * 1000 / tps.max(1) as u64).to_string(),
+        ),
+        ("score", game.score().to_string()),
+        ("lines", game.lines_cleared().to_string()),
+        ("level", game.level().to_string()),
+        ("pieces", stats.pieces().to_string()),
+        ("inputs", stats.inputs().to_string()),
+        (
+            "kpp",
+            stats
+                .kpp()
+                .map_or(String::new(), |kpp| format!("{:.2}", kpp)),
+        ),
+        ("pps", format!("{:.2}", stats.pps(ticks, tps))),
+        ("apm", format!("{:.2}", stats.apm(ticks, tps))),
+        ("attack", stats.attack().to_string()),
+        ("max_combo", stats.max_combo().to_string()),
+        ("max_back_to_back", stats.max_back_to_back().to_string()),
+        ("finesse_faults", stats.finesse_faults().to_string()),
+        (
+            "finesse",
+            stats
+                .finesse()
+                .map_or(String::new(), |finesse| format!("{:.1}", finesse)),
+        ),
+    ];
+    for (_, column, count) in stats.clears() {
+        columns.push((column, count.to_string()));
+    }
+    columns.push(("piece_distribution", distribution.join(" ")));
+
+    let header: Vec<&str> = columns.iter().map(|(column, _)| *column).collect();
+    let row: Vec<String> = columns.iter().map(|(_, value)| csv_field(value)).collect();
+    (header.join(","), row.join(","))
+}
+
+/// Where exported statistics go by default, in the platform's data directory
+pub fn default_path() -> Option<PathBuf> {
+    files::data_dir().map(|dir| dir.join(STATS_FILE))
+}
+
+impl Universe {
+    /// Adds the game's statistics as a row to the CSV file, starting it with a header if it's new.
+    /// Returns where it went
+    pub(super) fn export_stats(&self) -> io::Result<PathBuf> {
+        let path = default_path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no data directory to export to")
+        })?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let (header, row) = csv(&self.sim, date);
+        let new = !path.exists();
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if new {
+            writeln!(file, "{}", header)?;
+        }
+        writeln!(file, "{}", row)?;
+        Ok(path)
+    }
+
+    /// Renders the stats worth keeping an eye on while playing, under the rest of the HUD
+    pub(super) fn render_live_stats(&self, ctx: &mut Context) {
+        let game = self.sim.game();
+        let stats = self.sim.stats();
+        let (ticks, tps) = (game.elapsed(), game.ticks_per_second());
+        let mut lines = vec![
+            format!("PPS: {:.2}", stats.pps(ticks, tps)),
+            format!("APM: {:.1}", stats.apm(ticks, tps)),
+        ];
+        if let Some(kpp) = stats.kpp() {
+            lines.push(format!("KPP: {:.2}", kpp));
+        }
+        if stats.finesse().is_some() {
+            lines.push(format!("faults: {}", stats.finesse_faults()));
+        }
+        if self.finesse_flash > 0 {
+            lines.push(String::from("FINESSE FAULT"));
+        }
+        for (i, line) in lines.iter().enumerate() {
+            ctx.graphics.draw_text(
+                line,
+                self.config.hud_l(),
+                450.0 + i as f32 * 30.0,
+                self.color_palette.line().into(),
+                20.0,
+            );
+        }
+    }
+
+    /// Renders every statistic of the finished game in place of the results
+    pub(super) fn render_stats(&self, ctx: &mut Context) {
+        let game = self.sim.game();
+        let stats = self.sim.stats();
+        let (ticks, tps) = (game.elapsed(), game.ticks_per_second());
+        self.render_centered(ctx, "STATISTICS", 50.0, -330.0);
+
+        let mut summary = format!(
+            "pieces: {}  PPS: {:.2}  APM: {:.1}  attack: {}",
+            stats.pieces(),
+            stats.pps(ticks, tps),
+            stats.apm(ticks, tps),
+            stats.attack()
+        );
+        if let Some(kpp) = stats.kpp() {
+            let _ = write!(summary, "  inputs: {}  KPP: {:.2}", stats.inputs(), kpp);
+        }
+        if let Some(finesse) = stats.finesse() {
+            let _ = write!(
+                summary,
+                "  finesse: {:.1}% ({} faults)",
+                finesse,
+                stats.finesse_faults()
+            );
+        }
+        self.render_centered(ctx, &summary, 22.0, -270.0);
+        self.render_centered(
+            ctx,
+            &format!(
+                "time: {}  max combo: {}  max B2B: {}",
+                format_time(ticks, tps),
+                stats.max_combo(),
+                stats.max_back_to_back()
+            ),
+            22.0,
+            -240.0,
+        );
+
+        let distribution: Vec<String> = self
+            .sim
+            .piece_set()
+            .pieces()
+            .iter()
+            .map(|piece| format!("{} {}", piece.name(), stats.placed(*piece.tetromino_type())))
+            .collect();
+        // A row per 7 pieces, so that the bigger sets still fit
+        for (row, pieces) in distribution.chunks(7).enumerate() {
+            self.render_centered(ctx, &pieces.join("  "), 22.0, -195.0 + row as f32 * 28.0);
+        }
+
+        let rows = distribution.len().div_ceil(7) as f32;
+        let clears = stats.clears();
+        for (i, (name, _, count)) in clears.iter().enumerate() {
+            self.render_centered(
+                ctx,
+                &format!("{}: {}", name, count),
+                20.0,
+                -165.0 + rows * 28.0 + i as f32 * 24.0,
+            );
+        }
+        let bottom = -165.0 + rows * 28.0 + clears.len() as f32 * 24.0;
+        self.render_centered(
+            ctx,
+            "\"t\": back to results  \"e\": export as CSV",
+            20.0,
+            bottom + 20.0,
+        );
+        if let Some(status) = &self.stats_status {
+            self.render_centered(ctx, status, 20.0, bottom + 50.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::universe::game::GameState;
+    use crate::universe::simulation::Rules;
+
+    #[test]
+    fn test_counts_locks_and_clears() {
+        let mut stats = Stats::default();
+        let clear = |lines, t_spin| LineClear {
+            lines,
+            t_spin,
+            perfect_clear: false,
+        };
+        stats.press(&[
+            GameAction::MoveLeft,
+            GameAction::HardDrop,
+            GameAction::Pause,
+        ]);
+        stats.lock(TetrominoType::new(2), &clear(0, TSpin::None), 0, None, None);
+        stats.press(&[GameAction::HardDrop]);
+        stats.lock(
+            TetrominoType::new(2),
+            &clear(2, TSpin::Full),
+            4,
+            Some(0),
+            Some(0),
+        );
+        stats.lock(
+            TetrominoType::new(0),
+            &clear(4, TSpin::None),
+            5,
+            Some(1),
+            Some(1),
+        );
+        stats.lock(
+            TetrominoType::new(0),
+            &clear(5, TSpin::None),
+            0,
+            Some(2),
+            None,
+        );
+
+        assert_eq!(stats.pieces(), 4);
+        assert_eq!(stats.placed(TetrominoType::new(2)), 2);
+        assert_eq!(stats.placed(TetrominoType::new(6)), 0);
+        // Pausing isn't playing
+        assert_eq!(stats.inputs(), 3);
+        assert_eq!(stats.kpp(), Some(0.75));
+        assert_eq!(stats.attack(), 9);
+        assert_eq!((stats.max_combo(), stats.max_back_to_back()), (2, 1));
+        let counted: Vec<(String, u32)> = stats
+            .clears()
+            .into_iter()
+            .filter(|&(_, _, count)| count > 0)
+            .map(|(name, _, count)| (name, count))
+            .collect();
+        assert_eq!(
+            counted,
+            [
+                (String::from("TETRIS"), 1),
+                (String::from("T-SPIN DOUBLE"), 1),
+                (String::from("5+ LINES"), 1)
+            ]
+        );
+        // Two minutes at 60 ticks a second
+        assert_eq!(stats.pps(7200, 60), 4.0 / 120.0);
+        assert_eq!(stats.apm(7200, 60), 4.5);
+    }
+
+    #[test]
+    fn test_csv_row_lines_up_with_its_header() {
+        let mut sim = Simulation::new(
+            Rules {
+                ticks: 10,
+                ..Rules::default()
+            },
+            Some(7),
+        );
+        while sim.game().state() != GameState::Playing {
+            sim.tick(&[]);
+        }
+        for _ in 0..5 {
+            sim.press(&[GameAction::HardDrop]);
+            sim.tick(&[GameAction::HardDrop]);
+        }
+        let (header, row) = csv(&sim, 0);
+        let header: Vec<&str> = header.split(',').collect();
+        let row: Vec<&str> = row.split(',').collect();
+        assert_eq!(header.len(), row.len());
+        let column = |name| row[header.iter().position(|&c| c == name).unwrap()];
+        assert_eq!(column("date"), "1970-01-01");
+        assert_eq!(column("pieces"), "5");
+        assert_eq!(column("inputs"), "5");
+        assert_eq!(column("kpp"), "1.00");
+        assert_eq!(column("seed"), "7");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+    }
+}
@@ -0,0 +1,149 @@
+use thomas::{context::Context, Runnable};
+
+use super::game::GameState;
+use super::simulation::Event;
+use super::Universe;
+use crate::config::Config;
+use crate::tetris_input::{Bindings, TetrominoControls};
+
+/// Two boards side by side in one window, sending each other garbage until one of them tops out
+pub struct Versus {
+    players: [Universe; 2],
+    // Which board won the match, once it's over
+    winner: Option<usize>,
+    // Every match is played from this seed if it's set, and a fresh one otherwise
+    seed: Option<u64>,
+}
+
+impl Versus {
+    pub fn new(ctx: &mut Context, config: Config) -> Self {
+        let board = |ctx: &mut Context, slot, bindings| {
+            let mut config = config.clone();
+            config.set_slot(slot, 2);
+            let controls = TetrominoControls::new(bindings, &config);
+            Universe::versus(ctx, config, controls)
+        };
+        let left = board(ctx, 0, Bindings::left_player());
+        let mut right = board(ctx, 1, Bindings::right_player());
+        // A single soundtrack is enough
+        right.audio.music = false;
+
+        let mut versus = Self {
+            players: [left, right],
+            winner: None,
+            seed: config.seed(),
+        };
+        versus.start(versus.next_seed());
+        versus
+    }
+
+    /// The seed the next match is played from, the same one every time if it's been set
+    fn next_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(rand::random)
+    }
+
+    /// Starts a match, both boards getting the same tetrominos
+    fn start(&mut self, seed: u64) {
+        for player in self.players.iter_mut() {
+            player.sim_mut().start(seed);
+        }
+        self.winner = None;
+    }
+
+    /// Resizes both boards to fit the window
+    pub fn resize(&mut self, w: f32, h: f32) {
+        for player in self.players.iter_mut() {
+            player.config.resize(w, h);
+        }
+    }
+
+    /// Get the board that won the match, once it's over
+    pub fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+
+    /// Decides the match once either board stops playing.
+    /// Topping out loses, reaching the mode's goal wins, and if both happen at once the higher score wins
+    fn decide(&self) -> Option<usize> {
+        let ended = |i: usize| {
+            let state = self.players[i].sim().game().state();
+            match state {
+                GameState::GameOver(_) => Some(false),
+                GameState::Finished(_) => Some(true),
+                _ => None,
+            }
+        };
+        match (ended(0), ended(1)) {
+            (None, None) => None,
+            (Some(won), None) => Some(if won { 0 } else { 1 }),
+            (None, Some(won)) => Some(if won { 1 } else { 0 }),
+            (Some(left), Some(right)) if left != right => Some(if left { 0 } else { 1 }),
+            _ => {
+                let score = |i: usize| *self.players[i].sim().game().score();
+                Some(if score(1) > score(0) { 1 } else { 0 })
+            }
+        }
+    }
+}
+
+impl Runnable for Versus {
+    fn tick(&mut self, ctx: &mut Context) {
+        let events = [self.players[0].update(ctx), self.players[1].update(ctx)];
+        let happened = |event: Event| events.iter().flatten().any(|&e| e == event);
+
+        // Either board restarting restarts the whole match
+        if happened(Event::Restarted) {
+            self.start(self.next_seed());
+            return;
+        }
+        // Pausing pauses both boards, even if one of them was in the middle of a line clear
+        for player in self.players.iter_mut() {
+            let paused = player.sim().game().state() == GameState::Paused;
+            if (happened(Event::Paused) && !paused) || (happened(Event::Resumed) && paused) {
+                player.sim_mut().toggle_pause();
+            }
+        }
+
+        // Garbage goes over to the other board
+        for (i, events) in events.iter().enumerate() {
+            for event in events {
+                if let Event::Attack(lines) = *event {
+                    self.players[1 - i].sim_mut().receive_garbage(lines);
+                }
+            }
+        }
+
+        if self.winner.is_none() {
+            self.winner = self.decide();
+            // The match is over for both boards at once
+            if let Some(winner) = self.winner {
+                let playing = |state| {
+                    !matches!(
+                        state,
+                        GameState::GameOver(_) | GameState::Finished(_) | GameState::Results
+                    )
+                };
+                let game = self.players[winner].sim_mut().game_mut();
+                if playing(game.state()) {
+                    game.finish();
+                }
+                let game = self.players[1 - winner].sim_mut().game_mut();
+                if playing(game.state()) {
+                    game.top_out();
+                }
+            }
+        }
+    }
+
+    fn render(&self, ctx: &mut Context) {
+        for player in self.players.iter() {
+            player.render(ctx);
+        }
+        if let Some(winner) = self.winner {
+            for (i, player) in self.players.iter().enumerate() {
+                let text = if i == winner { "WIN" } else { "LOSE" };
+                player.render_centered(ctx, text, 60.0, -250.0);
+            }
+        }
+    }
+}
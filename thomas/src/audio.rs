@@ -25,9 +25,19 @@ impl Audio {
 
 /// Mainly useful for playing sound effects I think?
 pub fn play_once_vorbis(ctx: &mut Context, path: &str) -> Result<(), Box<dyn Error>> {
+    play_once_vorbis_with_volume(ctx, path, 1.0)
+}
+
+/// Same as [`play_once_vorbis`], but louder or quieter, `1.0` being the file's own volume
+pub fn play_once_vorbis_with_volume(
+    ctx: &mut Context,
+    path: &str,
+    volume: f32,
+) -> Result<(), Box<dyn Error>> {
     // Load the file via resource manager
     let file = ctx.resource_mgr.load_file(path)?;
     let sink = rodio::Sink::try_new(&ctx.audio.stream_handle)?;
+    sink.set_volume(volume);
     sink.append(rodio::Decoder::new_vorbis(BufReader::new(file))?);
     sink.detach();
     Ok(())
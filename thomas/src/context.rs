@@ -1,7 +1,7 @@
+use super::audio::Audio;
 use super::graphics::backend::State;
 use super::keyboard::Keyboard;
 use super::resource::ResourceManager;
-use super::audio::Audio;
 
 pub struct Context {
     pub graphics: State,
@@ -9,5 +9,5 @@ pub struct Context {
     pub audio: Audio,
     pub window: winit::window::Window,
     pub resource_mgr: ResourceManager,
-    pub config: crate::Config
-}
\ No newline at end of file
+    pub config: crate::Config,
+}
@@ -21,8 +21,7 @@ impl FontInterface {
         let font_buffer = std::fs::read(path).expect("cannot find font file");
         // let mut buf: Vec<u8> = Vec::new();
         // Default font, let's use visitor
-        let visitor = ab_glyph::FontArc::try_from_vec(font_buffer)
-            .unwrap();
+        let visitor = ab_glyph::FontArc::try_from_vec(font_buffer).unwrap();
         let glyph_brush = GlyphBrushBuilder::using_font(visitor).build(device, format);
         let staging_belt = wgpu::util::StagingBelt::new(1024);
 
@@ -48,7 +47,7 @@ impl FontInterface {
         encoder: &mut wgpu::CommandEncoder,
         size: winit::dpi::PhysicalSize<u32>,
         frame: &wgpu::TextureView,
-        ) {
+    ) {
         self.glyph_brush
             .draw_queued(
                 device,
@@ -57,7 +56,7 @@ impl FontInterface {
                 frame,
                 size.width,
                 size.height,
-                )
+            )
             .expect("Draw queued");
     }
 }
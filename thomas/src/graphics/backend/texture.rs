@@ -95,4 +95,4 @@
 //         Ok(Self {texture, view, sampler})
 
 //     }
-// }
\ No newline at end of file
+// }
@@ -98,9 +98,9 @@ mod test {
         assert_eq!(
             wgpu::Color::from(color),
             wgpu::Color {
-                r: 41.0 / 256.0,
-                g: 40.0 / 256.0,
-                b: 40.0 / 256.0,
+                r: (41.0_f64 / 256.0).powf(2.2),
+                g: (40.0_f64 / 256.0).powf(2.2),
+                b: (40.0_f64 / 256.0).powf(2.2),
                 a: 1.0
             }
         );
@@ -135,7 +135,6 @@ impl State {
             },
         ];
 
-
         let indices = &[
             0, 2, 3, // Top triangle
             3, 1, 0, // Bot triangle
@@ -1,2 +1,2 @@
 pub mod backend;
-pub mod frontend;
\ No newline at end of file
+pub mod frontend;
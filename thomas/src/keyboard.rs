@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use winit::event::{ElementState, VirtualKeyCode, WindowEvent};
 
 pub struct Keyboard {
@@ -14,7 +16,14 @@ pub struct Keyboard {
     pub r: bool,
     pub z: bool,
     pub c: bool,
+    pub x: bool,
+    pub shift: bool,
+    pub p: bool,
     pub space: bool,
+    // Every other key that's held down
+    pressed: HashSet<VirtualKeyCode>,
+    // Last key to go down, until it's taken
+    last_pressed: Option<VirtualKeyCode>,
 }
 
 impl Keyboard {
@@ -33,10 +42,21 @@ impl Keyboard {
             r: false,
             z: false,
             c: false,
+            x: false,
+            shift: false,
+            p: false,
             space: false,
+            pressed: HashSet::new(),
+            last_pressed: None,
         }
     }
 
+    /// Takes the last key that went down since this was last called, e.g. to bind it to something.
+    /// Keys held down long enough for the OS to repeat them only count once
+    pub fn take_pressed(&mut self) -> Option<VirtualKeyCode> {
+        self.last_pressed.take()
+    }
+
     pub fn is_pressed(&mut self, key: VirtualKeyCode) -> bool {
         match key {
             VirtualKeyCode::Up => self.up,
@@ -52,8 +72,11 @@ impl Keyboard {
             VirtualKeyCode::R => self.r,
             VirtualKeyCode::Z => self.z,
             VirtualKeyCode::C => self.c,
+            VirtualKeyCode::X => self.x,
+            VirtualKeyCode::LShift | VirtualKeyCode::RShift => self.shift,
+            VirtualKeyCode::P => self.p,
             VirtualKeyCode::Space => self.space,
-            _ => false,
+            _ => self.pressed.contains(&key),
         }
     }
 
@@ -69,6 +92,9 @@ impl Keyboard {
                 ..
             } => {
                 let is_pressed = *state == ElementState::Pressed;
+                if is_pressed && !self.is_pressed(*keycode) && *keycode != VirtualKeyCode::Escape {
+                    self.last_pressed = Some(*keycode);
+                }
                 match keycode {
                     VirtualKeyCode::Up => {
                         self.up = is_pressed;
@@ -122,11 +148,32 @@ impl Keyboard {
                         self.c = is_pressed;
                         true
                     }
+                    VirtualKeyCode::X => {
+                        self.x = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::LShift | VirtualKeyCode::RShift => {
+                        self.shift = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::P => {
+                        self.p = is_pressed;
+                        true
+                    }
                     VirtualKeyCode::Space => {
                         self.space = is_pressed;
                         true
                     }
-                    _ => false,
+                    // Left for the event loop, which exits on it
+                    VirtualKeyCode::Escape => false,
+                    _ => {
+                        if is_pressed {
+                            self.pressed.insert(*keycode);
+                        } else {
+                            self.pressed.remove(keycode);
+                        }
+                        true
+                    }
                 }
             }
             _ => false,
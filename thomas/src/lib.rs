@@ -7,7 +7,7 @@ inspiration from [ggez](https://github.com/ggez/ggez) and [raylib](https://githu
 Create a struct that implements the [`Runnable`] trait, a context using [`ContextBuilder`],
 Then start the game loop with [`main::run`]
 
-```
+```ignore
 use thomas::Color;
 
 struct Game {
@@ -42,14 +42,13 @@ use context::Context;
 pub mod graphics;
 mod keyboard;
 pub use graphics::frontend;
-use image::GenericImageView;
 use resource::ResourceManager;
 pub use rodio;
 pub use winit;
 pub mod audio;
 mod resource;
 
-use winit::dpi::PhysicalPosition;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
@@ -72,6 +71,7 @@ impl Default for Config {
 pub struct ContextBuilder {
     title: String,
     margin: f32,
+    size: Option<PhysicalSize<u32>>,
     icon: Option<PathBuf>,
     resource_mgr: PathBuf,
     config: Config,
@@ -80,6 +80,7 @@ pub struct ContextBuilder {
 impl ContextBuilder {
     /// ## Defaults
     /// `100.0` px margin
+    /// Fills the monitor, less the margin
     /// `Game` title
     /// No icon
     /// Default config
@@ -87,6 +88,7 @@ impl ContextBuilder {
         Self {
             title: String::from("Game"),
             margin: 100.0,
+            size: None,
             icon: None,
             resource_mgr: PathBuf::new(),
             config: Config::default(),
@@ -107,6 +109,11 @@ impl ContextBuilder {
         self.margin = margin;
         self
     }
+    /// Changes size of the inside of the window, instead of filling the monitor
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some(PhysicalSize::new(width, height));
+        self
+    }
     /// Changes icon of window
     pub fn with_icon(mut self, path: PathBuf) -> Self {
         self.icon = Some(path);
@@ -130,8 +137,12 @@ impl ContextBuilder {
             Some(icon_path) => {
                 let image = image::open(icon_path).expect("Unable to find image");
                 Some(
-                    winit::window::Icon::from_rgba(image.to_bytes(), image.width(), image.height())
-                        .expect("Bad image"),
+                    winit::window::Icon::from_rgba(
+                        image.as_bytes().to_vec(),
+                        image.width(),
+                        image.height(),
+                    )
+                    .expect("Bad image"),
                 )
             }
             None => None,
@@ -143,9 +154,12 @@ impl ContextBuilder {
             .with_visible(false)
             .with_window_icon(icon);
         let window = builder.build(&event_loop).unwrap();
-        let mut size = window.current_monitor().unwrap().size();
-        size.width -= (self.margin * 2.0) as u32;
-        size.height -= (self.margin * 2.0) as u32;
+        let size = self.size.unwrap_or_else(|| {
+            let mut size = window.current_monitor().unwrap().size();
+            size.width -= (self.margin * 2.0) as u32;
+            size.height -= (self.margin * 2.0) as u32;
+            size
+        });
         window.set_inner_size(size);
         window.set_outer_position(PhysicalPosition {
             x: self.margin,
@@ -193,6 +207,10 @@ pub trait Runnable {
     /// Runs every frame, which matches the refresh rate of whatever device the program
     /// is run on. Use [`Context`]`.graphics.(render)` here
     fn render(&self, ctx: &mut context::Context);
+
+    /// Runs once as the window closes, e.g. from Escape or the close button.
+    /// Put anything that should outlive the program, like saving, here
+    fn exit(&mut self, _ctx: &mut context::Context) {}
 }
 
 /// This module includes the [`main::run`] function
@@ -223,26 +241,24 @@ pub mod main {
 
             // world.tick,
             match event {
-                Event::WindowEvent { ref event, .. } => {
-                    if !context.keyboard.input(event) {
-                        match event {
-                            WindowEvent::CloseRequested => exit(control_flow),
-                            WindowEvent::KeyboardInput {
-                                input:
-                                    KeyboardInput {
-                                        state: ElementState::Pressed,
-                                        virtual_keycode: Some(VirtualKeyCode::Escape),
-                                        ..
-                                    },
-                                ..
-                            } => exit(control_flow),
-                            WindowEvent::Resized(size) => context.graphics.resize(*size),
-                            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                                // new_inner_size is &&mut so we have to dereference it twice
-                                context.graphics.resize(**new_inner_size);
-                            }
-                            _ => (),
+                Event::WindowEvent { ref event, .. } if !context.keyboard.input(event) => {
+                    match event {
+                        WindowEvent::CloseRequested => exit(&mut state, &mut context, control_flow),
+                        WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInput {
+                                    state: ElementState::Pressed,
+                                    virtual_keycode: Some(VirtualKeyCode::Escape),
+                                    ..
+                                },
+                            ..
+                        } => exit(&mut state, &mut context, control_flow),
+                        WindowEvent::Resized(size) => context.graphics.resize(*size),
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &&mut so we have to dereference it twice
+                            context.graphics.resize(**new_inner_size);
                         }
+                        _ => (),
                     }
                 }
                 Event::MainEventsCleared => {
@@ -296,7 +312,9 @@ pub mod main {
                             context.graphics.resize(*context.graphics.size())
                         }
                         // The system is out of memory, we should probably quit
-                        Err(wgpu::SurfaceError::OutOfMemory) => exit(control_flow),
+                        Err(wgpu::SurfaceError::OutOfMemory) => {
+                            exit(&mut state, &mut context, control_flow)
+                        }
                         // All other errors (Outdated, Timeout) should be resolved by the next frame
                         Err(e) => eprintln!("Err: {:?}", e),
                     };
@@ -306,7 +324,8 @@ pub mod main {
         });
     }
     #[inline]
-    fn exit(control_flow: &mut ControlFlow) {
+    fn exit<T: Runnable>(state: &mut T, context: &mut Context, control_flow: &mut ControlFlow) {
+        state.exit(context);
         *control_flow = ControlFlow::Exit;
     }
 }